@@ -0,0 +1,13 @@
+-- 以前のcookieはユーザーIDのみを持つので、全員ログインし直しになる
+CREATE TABLE IF NOT EXISTS `sessions` (
+    `id` BINARY(16) NOT NULL, -- cookieに保存する推測できないID
+    `user_id` BINARY(16) NOT NULL,
+    `user_agent` VARCHAR(511) NOT NULL,
+    `created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    `last_seen_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    `expires_at` TIMESTAMP NOT NULL,
+    `revoked_at` TIMESTAMP NULL DEFAULT NULL,
+    PRIMARY KEY (`id`),
+    INDEX `idx_sessions_user_id` (`user_id`),
+    FOREIGN KEY (`user_id`) REFERENCES `users` (`id`)
+);
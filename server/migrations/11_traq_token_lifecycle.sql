@@ -0,0 +1,11 @@
+-- 平文で保存されていたトークンは暗号化し直せないので、全員認可し直しになる
+DELETE FROM `traq_token`;
+
+ALTER TABLE `traq_token`
+    DROP COLUMN `token`,
+    -- nonceと暗号文を連結したもの
+    ADD COLUMN `access_token` VARBINARY(2048) NOT NULL,
+    ADD COLUMN `refresh_token` VARBINARY(2048) NULL DEFAULT NULL,
+    ADD COLUMN `expires_at` TIMESTAMP NULL DEFAULT NULL,
+    -- traQで無効にされたトークン; 認可し直すまで使わない
+    ADD COLUMN `needs_reauth` BOOLEAN NOT NULL DEFAULT FALSE;
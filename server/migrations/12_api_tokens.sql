@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS `api_tokens` (
+    `id` BINARY(16) NOT NULL,
+    `user_id` BINARY(16) NOT NULL,
+    `name` VARCHAR(255) NOT NULL,
+    `token_hash` BINARY(32) NOT NULL, -- トークンのSHA-256; トークン自体は保存しない
+    `scopes` VARCHAR(255) NOT NULL, -- カンマ区切り
+    `created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    `last_used_at` TIMESTAMP NULL DEFAULT NULL,
+    PRIMARY KEY (`id`),
+    UNIQUE INDEX `idx_api_tokens_token_hash` (`token_hash`),
+    INDEX `idx_api_tokens_user_id` (`user_id`),
+    FOREIGN KEY (`user_id`) REFERENCES `users` (`id`)
+);
@@ -0,0 +1,4 @@
+-- MariaDBはngramパーサーに対応していないので、アプリで2文字ずつに区切った本文を標準のパーサーで索引する
+-- 既存のメッセージは起動時に埋める
+ALTER TABLE `messages` ADD COLUMN `content_bigram` TEXT NULL;
+ALTER TABLE `messages` ADD FULLTEXT INDEX `idx_messages_content_bigram` (`content_bigram`);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS `user_positions` (
+    `user_id` BINARY(16) NOT NULL,
+    `position_x` INT UNSIGNED NOT NULL,
+    `position_y` INT UNSIGNED NOT NULL,
+    `created_at` TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    `updated_at` TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    PRIMARY KEY (`user_id`),
+    FOREIGN KEY (`user_id`) REFERENCES `users` (`id`)
+);
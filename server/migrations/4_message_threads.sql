@@ -0,0 +1,3 @@
+ALTER TABLE `messages`
+    ADD COLUMN `parent_id` BINARY(16) NULL AFTER `user_id`,
+    ADD INDEX `idx_messages_parent_id` (`parent_id`);
@@ -0,0 +1,4 @@
+-- 既存のSpeakerPhoneは所有者なし
+ALTER TABLE `speaker_phones`
+    ADD COLUMN `user_id` BINARY(16) NULL DEFAULT NULL,
+    ADD FOREIGN KEY (`user_id`) REFERENCES `users` (`id`);
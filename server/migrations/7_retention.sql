@@ -0,0 +1,8 @@
+-- 期限切れの検索・削除のため
+ALTER TABLE `messages`
+    ADD INDEX `idx_messages_expires_at` (`expires_at`),
+    ADD INDEX `idx_messages_deleted_at` (`deleted_at`);
+ALTER TABLE `reactions`
+    ADD INDEX `idx_reactions_expires_at` (`expires_at`);
+ALTER TABLE `traq_messages`
+    ADD INDEX `idx_traq_messages_message_id` (`message_id`);
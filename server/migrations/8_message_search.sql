@@ -0,0 +1,10 @@
+-- 日本語を検索できるようにngramパーサーを使う
+-- MariaDBはngramパーサーに対応していないので、標準のパーサーで作成する
+SET @ddl = IF(
+    VERSION() LIKE '%MariaDB%',
+    'ALTER TABLE `messages` ADD FULLTEXT INDEX `idx_messages_content` (`content`)',
+    'ALTER TABLE `messages` ADD FULLTEXT INDEX `idx_messages_content` (`content`) WITH PARSER ngram'
+);
+PREPARE stmt FROM @ddl;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS `notifications` (
+    `id` BINARY(16) NOT NULL,
+    `user_id` BINARY(16) NOT NULL, -- 通知先
+    `message_id` BINARY(16) NOT NULL, -- メンションしたメッセージ
+    `sender_id` BINARY(16) NOT NULL, -- メンションしたユーザー
+    `position_x` INT UNSIGNED NOT NULL,
+    `position_y` INT UNSIGNED NOT NULL,
+    `created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    `read_at` TIMESTAMP NULL DEFAULT NULL,
+    PRIMARY KEY (`id`),
+    INDEX `idx_notifications_user_id` (`user_id`, `id`),
+    FOREIGN KEY (`user_id`) REFERENCES `users` (`id`),
+    FOREIGN KEY (`sender_id`) REFERENCES `users` (`id`)
+);
@@ -1,3 +1,7 @@
+pub mod api_token {
+    tonic::include_proto!("api_token");
+}
+
 pub mod auth {
     tonic::include_proto!("auth");
 }
@@ -10,10 +14,18 @@ pub mod msg {
     tonic::include_proto!("msg");
 }
 
+pub mod notification {
+    tonic::include_proto!("notification");
+}
+
 pub mod reaction {
     tonic::include_proto!("reaction");
 }
 
+pub mod session {
+    tonic::include_proto!("session");
+}
+
 pub mod speaker_phone {
     tonic::include_proto!("speaker_phone");
 }
@@ -0,0 +1,241 @@
+//! `api_token.proto`
+
+pub mod error;
+pub mod grpc;
+mod r#impl;
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::{IntoStatus, Timestamp};
+
+pub use error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct ApiTokenId(pub uuid::Uuid);
+
+/// APIトークンで使えるRPCの範囲
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ApiTokenScope {
+    /// 取得・購読
+    Read,
+    /// 作成・更新・削除
+    Write,
+    /// `Explore`と`/ws`
+    Explore,
+}
+
+impl ApiTokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Explore => "explore",
+        }
+    }
+}
+
+impl std::str::FromStr for ApiTokenScope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            "explore" => Ok(Self::Explore),
+            _ => Err(Error::InvalidScope(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct ApiTokenScopes(pub BTreeSet<ApiTokenScope>);
+
+impl ApiTokenScopes {
+    pub fn contains(&self, scope: ApiTokenScope) -> bool {
+        self.0.contains(&scope)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub id: ApiTokenId,
+    pub user_id: crate::user::UserId,
+    pub name: String,
+    pub scopes: ApiTokenScopes,
+    pub created_at: Timestamp,
+    pub last_used_at: Option<Timestamp>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct CreatedApiToken {
+    pub token: ApiToken,
+    /// トークン本体; 作成時にしか分からない
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct CreateApiTokenParams {
+    pub user_id: crate::user::UserId,
+    pub name: String,
+    pub scopes: ApiTokenScopes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ListApiTokensParams {
+    pub user_id: crate::user::UserId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct RevokeApiTokenParams {
+    /// トークンの持ち主でなければならない
+    pub user_id: crate::user::UserId,
+    pub id: ApiTokenId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct AuthenticateApiTokenParams {
+    pub secret: String,
+}
+
+pub trait ApiTokenService<Context>: Send + Sync + 'static {
+    type Error: IntoStatus;
+
+    fn create_api_token<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: CreateApiTokenParams,
+    ) -> BoxFuture<'a, Result<CreatedApiToken, Self::Error>>;
+    /// 新しい順
+    fn list_api_tokens<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: ListApiTokensParams,
+    ) -> BoxFuture<'a, Result<Vec<ApiToken>, Self::Error>>;
+    fn revoke_api_token<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: RevokeApiTokenParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>>;
+    /// 有効なトークンでなければ`None`
+    fn authenticate_api_token<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: AuthenticateApiTokenParams,
+    ) -> BoxFuture<'a, Result<Option<ApiToken>, Self::Error>>;
+}
+
+#[allow(clippy::type_complexity)]
+pub trait ProvideApiTokenService: Send + Sync + 'static {
+    type Context;
+    type ApiTokenService: ApiTokenService<Self::Context>;
+
+    fn context(&self) -> &Self::Context;
+    fn api_token_service(&self) -> &Self::ApiTokenService;
+
+    fn create_api_token(
+        &self,
+        params: CreateApiTokenParams,
+    ) -> BoxFuture<
+        '_,
+        Result<CreatedApiToken, <Self::ApiTokenService as ApiTokenService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.api_token_service().create_api_token(ctx, params)
+    }
+    fn list_api_tokens(
+        &self,
+        params: ListApiTokensParams,
+    ) -> BoxFuture<
+        '_,
+        Result<Vec<ApiToken>, <Self::ApiTokenService as ApiTokenService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.api_token_service().list_api_tokens(ctx, params)
+    }
+    fn revoke_api_token(
+        &self,
+        params: RevokeApiTokenParams,
+    ) -> BoxFuture<'_, Result<(), <Self::ApiTokenService as ApiTokenService<Self::Context>>::Error>>
+    {
+        let ctx = self.context();
+        self.api_token_service().revoke_api_token(ctx, params)
+    }
+    fn authenticate_api_token(
+        &self,
+        params: AuthenticateApiTokenParams,
+    ) -> BoxFuture<
+        '_,
+        Result<Option<ApiToken>, <Self::ApiTokenService as ApiTokenService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.api_token_service().authenticate_api_token(ctx, params)
+    }
+}
+
+/// gRPCのパス`/{service}/{method}`を呼ぶのに必要なスコープ
+///
+/// `None`ならAPIトークンでは呼べない
+pub fn required_scope(path: &str) -> Option<ApiTokenScope> {
+    let (service, method) = path.trim_start_matches('/').split_once('/')?;
+    // トークンから新しいトークンやセッションを操作させない
+    if service == SERVICE_NAME || service == crate::session::SERVICE_NAME {
+        return None;
+    }
+    if service == crate::explore::SERVICE_NAME {
+        return Some(ApiTokenScope::Explore);
+    }
+    let read = ["Get", "List", "Search", "Subscribe"]
+        .iter()
+        .any(|prefix| method.starts_with(prefix));
+    if read {
+        Some(ApiTokenScope::Read)
+    } else {
+        Some(ApiTokenScope::Write)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiTokenServiceImpl;
+
+pub fn build_server<State>(state: Arc<State>) -> ApiTokenServiceServer<State>
+where
+    State: ProvideApiTokenService + crate::session::ProvideSessionService,
+{
+    let service = grpc::ServiceImpl::new(state);
+    ApiTokenServiceServer::new(service)
+}
+
+pub type ApiTokenServiceServer<State> =
+    schema::api_token::api_token_service_server::ApiTokenServiceServer<grpc::ServiceImpl<State>>;
+
+pub use schema::api_token::api_token_service_server::SERVICE_NAME;
+
+#[test]
+fn test_required_scope() {
+    assert_eq!(
+        required_scope("/msg.MessageService/GetMessageHistory"),
+        Some(ApiTokenScope::Read)
+    );
+    assert_eq!(
+        required_scope("/msg.MessageService/CreateMessage"),
+        Some(ApiTokenScope::Write)
+    );
+    assert_eq!(
+        required_scope("/explore.ExploreService/Explore"),
+        Some(ApiTokenScope::Explore)
+    );
+    assert_eq!(required_scope("/session.SessionService/Logout"), None);
+    assert_eq!(
+        required_scope("/api_token.ApiTokenService/ListApiTokens"),
+        None
+    );
+    assert_eq!(required_scope("/"), None);
+}
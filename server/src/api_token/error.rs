@@ -0,0 +1,35 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Not found")]
+    NotFound,
+    #[error("Invalid token name")]
+    InvalidName,
+    #[error("Invalid scope: {0}")]
+    InvalidScope(String),
+    #[error("At least one scope is required")]
+    NoScopes,
+    #[error("Too many API tokens")]
+    TooManyTokens,
+    #[error("Database error")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+impl From<Error> for tonic::Status {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::NotFound => tonic::Status::not_found("Not found"),
+            Error::InvalidName => tonic::Status::invalid_argument("Invalid token name"),
+            Error::InvalidScope(scope) => {
+                tonic::Status::invalid_argument(format!("Invalid scope: {scope}"))
+            }
+            Error::NoScopes => tonic::Status::invalid_argument("At least one scope is required"),
+            Error::TooManyTokens => tonic::Status::resource_exhausted("Too many API tokens"),
+            Error::Sqlx(e) => {
+                tracing::error!(error = &e as &dyn std::error::Error);
+                tonic::Status::internal("Database error")
+            }
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::prelude::IntoStatus;
+
+// MARK: type conversions
+
+impl From<super::ApiToken> for schema::api_token::ApiToken {
+    fn from(value: super::ApiToken) -> Self {
+        let super::ApiToken {
+            id,
+            user_id: _,
+            name,
+            scopes,
+            created_at,
+            last_used_at,
+        } = value;
+        Self {
+            id: id.0.to_string(),
+            name,
+            scopes: scopes.0.iter().map(|s| s.as_str().to_string()).collect(),
+            created_at: Some(created_at.into()),
+            last_used_at: last_used_at.map(Into::into),
+        }
+    }
+}
+
+// MARK: ServiceImpl
+
+pub struct ServiceImpl<State> {
+    state: Arc<State>,
+}
+
+impl<State> Clone for ServiceImpl<State>
+where
+    State: super::ProvideApiTokenService + crate::session::ProvideSessionService,
+{
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<State> ServiceImpl<State>
+where
+    State: super::ProvideApiTokenService + crate::session::ProvideSessionService,
+{
+    pub(super) fn new(state: Arc<State>) -> Self {
+        Self { state }
+    }
+}
+
+#[async_trait::async_trait]
+impl<State> schema::api_token::api_token_service_server::ApiTokenService for ServiceImpl<State>
+where
+    State: super::ProvideApiTokenService + crate::session::ProvideSessionService,
+{
+    async fn create_api_token(
+        &self,
+        request: tonic::Request<schema::api_token::CreateApiTokenRequest>,
+    ) -> Result<tonic::Response<schema::api_token::CreateApiTokenResponse>, tonic::Status> {
+        let (meta, _, request) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let schema::api_token::CreateApiTokenRequest { name, scopes } = request;
+        let scopes = scopes
+            .iter()
+            .map(|s| s.parse())
+            .collect::<Result<_, super::Error>>()
+            .map_err(IntoStatus::into_status)?;
+        let params = super::CreateApiTokenParams {
+            user_id,
+            name,
+            scopes: super::ApiTokenScopes(scopes),
+        };
+        let super::CreatedApiToken { token, secret } = self
+            .state
+            .create_api_token(params)
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let res = schema::api_token::CreateApiTokenResponse {
+            token: Some(token.into()),
+            secret,
+        };
+        Ok(tonic::Response::new(res))
+    }
+
+    async fn list_api_tokens(
+        &self,
+        request: tonic::Request<schema::api_token::ListApiTokensRequest>,
+    ) -> Result<tonic::Response<schema::api_token::ListApiTokensResponse>, tonic::Status> {
+        let (meta, _, schema::api_token::ListApiTokensRequest {}) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let tokens = self
+            .state
+            .list_api_tokens(super::ListApiTokensParams { user_id })
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let res = schema::api_token::ListApiTokensResponse {
+            tokens: tokens.into_iter().map(Into::into).collect(),
+        };
+        Ok(tonic::Response::new(res))
+    }
+
+    async fn revoke_api_token(
+        &self,
+        request: tonic::Request<schema::api_token::RevokeApiTokenRequest>,
+    ) -> Result<tonic::Response<schema::api_token::RevokeApiTokenResponse>, tonic::Status> {
+        let (meta, _, schema::api_token::RevokeApiTokenRequest { id }) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let params = super::RevokeApiTokenParams {
+            user_id,
+            id: super::ApiTokenId(
+                Uuid::parse_str(&id)
+                    .map_err(|_| tonic::Status::invalid_argument("Invalid UUID"))?,
+            ),
+        };
+        self.state
+            .revoke_api_token(params)
+            .await
+            .map_err(IntoStatus::into_status)?;
+        Ok(tonic::Response::new(
+            schema::api_token::RevokeApiTokenResponse {},
+        ))
+    }
+}
@@ -0,0 +1,249 @@
+use chrono::{DateTime, Utc};
+use futures::{future::BoxFuture, FutureExt};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, MySqlPool};
+use uuid::Uuid;
+
+use crate::prelude::Timestamp;
+
+/// トークン本体の接頭辞; 漏洩したときに検出しやすいように
+const SECRET_PREFIX: &str = "h24w14_";
+
+/// トークン本体のランダムな部分のバイト数
+const SECRET_BYTES: usize = 32;
+
+/// 1ユーザーが持てるトークンの数
+const MAX_TOKENS_PER_USER: i64 = 20;
+
+/// `name`列の長さ
+const NAME_MAX_LEN: usize = 255;
+
+/// `last_used_at`を更新する間隔(秒)
+const LAST_USED_RESOLUTION: i64 = 60;
+
+impl<Context> super::ApiTokenService<Context> for super::ApiTokenServiceImpl
+where
+    Context: AsRef<MySqlPool>,
+{
+    type Error = super::Error;
+
+    fn create_api_token<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::CreateApiTokenParams,
+    ) -> BoxFuture<'a, Result<super::CreatedApiToken, Self::Error>> {
+        create_api_token(ctx.as_ref(), params).boxed()
+    }
+
+    fn list_api_tokens<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::ListApiTokensParams,
+    ) -> BoxFuture<'a, Result<Vec<super::ApiToken>, Self::Error>> {
+        list_api_tokens(ctx.as_ref(), params).boxed()
+    }
+
+    fn revoke_api_token<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::RevokeApiTokenParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>> {
+        revoke_api_token(ctx.as_ref(), params).boxed()
+    }
+
+    fn authenticate_api_token<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::AuthenticateApiTokenParams,
+    ) -> BoxFuture<'a, Result<Option<super::ApiToken>, Self::Error>> {
+        authenticate_api_token(ctx.as_ref(), params).boxed()
+    }
+}
+
+// MARK: secrets
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    format!("{SECRET_PREFIX}{}", hex::encode(bytes))
+}
+
+fn hash_secret(secret: &str) -> Vec<u8> {
+    Sha256::digest(secret.as_bytes()).to_vec()
+}
+
+fn format_scopes(scopes: &super::ApiTokenScopes) -> String {
+    scopes
+        .0
+        .iter()
+        .map(super::ApiTokenScope::as_str)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_scopes(scopes: &str) -> Result<super::ApiTokenScopes, super::Error> {
+    scopes
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map(super::ApiTokenScopes)
+}
+
+// MARK: DB operations
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
+struct ApiTokenRow {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_hash: Vec<u8>,
+    pub scopes: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<ApiTokenRow> for super::ApiToken {
+    type Error = super::Error;
+
+    fn try_from(value: ApiTokenRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: super::ApiTokenId(value.id),
+            user_id: crate::user::UserId(value.user_id),
+            name: value.name,
+            scopes: parse_scopes(&value.scopes)?,
+            created_at: Timestamp(value.created_at),
+            last_used_at: value.last_used_at.map(Timestamp),
+        })
+    }
+}
+
+async fn get_api_token(pool: &MySqlPool, id: Uuid) -> Result<super::ApiToken, super::Error> {
+    let row: Option<ApiTokenRow> = sqlx::query_as(r#"SELECT * FROM `api_tokens` WHERE `id` = ?"#)
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    row.ok_or(super::Error::NotFound)?.try_into()
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %params.user_id.0))]
+async fn create_api_token(
+    pool: &MySqlPool,
+    params: super::CreateApiTokenParams,
+) -> Result<super::CreatedApiToken, super::Error> {
+    let super::CreateApiTokenParams {
+        user_id,
+        name,
+        scopes,
+    } = params;
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > NAME_MAX_LEN {
+        return Err(super::Error::InvalidName);
+    }
+    if scopes.0.is_empty() {
+        return Err(super::Error::NoScopes);
+    }
+
+    let (count,): (i64,) =
+        sqlx::query_as(r#"SELECT COUNT(*) FROM `api_tokens` WHERE `user_id` = ?"#)
+            .bind(user_id.0)
+            .fetch_one(pool)
+            .await?;
+    if count >= MAX_TOKENS_PER_USER {
+        return Err(super::Error::TooManyTokens);
+    }
+
+    let id = Uuid::now_v7();
+    let secret = generate_secret();
+    sqlx::query(
+        r#"
+            INSERT INTO `api_tokens` (`id`, `user_id`, `name`, `token_hash`, `scopes`)
+            VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(id)
+    .bind(user_id.0)
+    .bind(name)
+    .bind(hash_secret(&secret))
+    .bind(format_scopes(&scopes))
+    .execute(pool)
+    .await?;
+    tracing::info!(id = %id, "Created an API token");
+
+    let token = get_api_token(pool, id).await?;
+    Ok(super::CreatedApiToken { token, secret })
+}
+
+async fn list_api_tokens(
+    pool: &MySqlPool,
+    params: super::ListApiTokensParams,
+) -> Result<Vec<super::ApiToken>, super::Error> {
+    let super::ListApiTokensParams { user_id } = params;
+    let rows: Vec<ApiTokenRow> = sqlx::query_as(
+        r#"SELECT * FROM `api_tokens` WHERE `user_id` = ? ORDER BY `created_at` DESC"#,
+    )
+    .bind(user_id.0)
+    .fetch_all(pool)
+    .await?;
+    rows.into_iter().map(TryInto::try_into).collect()
+}
+
+async fn revoke_api_token(
+    pool: &MySqlPool,
+    params: super::RevokeApiTokenParams,
+) -> Result<(), super::Error> {
+    let super::RevokeApiTokenParams { user_id, id } = params;
+    let result = sqlx::query(r#"DELETE FROM `api_tokens` WHERE `id` = ? AND `user_id` = ?"#)
+        .bind(id.0)
+        .bind(user_id.0)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(super::Error::NotFound);
+    }
+    tracing::info!(id = %id.0, user_id = %user_id.0, "Revoked an API token");
+    Ok(())
+}
+
+async fn authenticate_api_token(
+    pool: &MySqlPool,
+    params: super::AuthenticateApiTokenParams,
+) -> Result<Option<super::ApiToken>, super::Error> {
+    let super::AuthenticateApiTokenParams { secret } = params;
+    if !secret.starts_with(SECRET_PREFIX) {
+        return Ok(None);
+    }
+    let row: Option<ApiTokenRow> =
+        sqlx::query_as(r#"SELECT * FROM `api_tokens` WHERE `token_hash` = ?"#)
+            .bind(hash_secret(&secret))
+            .fetch_optional(pool)
+            .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let now = Utc::now();
+    let stale = row
+        .last_used_at
+        .is_none_or(|t| now - t >= chrono::TimeDelta::seconds(LAST_USED_RESOLUTION));
+    if stale {
+        sqlx::query(r#"UPDATE `api_tokens` SET `last_used_at` = ? WHERE `id` = ?"#)
+            .bind(now)
+            .bind(row.id)
+            .execute(pool)
+            .await?;
+    }
+    row.try_into().map(Some)
+}
+
+#[test]
+fn test_scopes_roundtrip() {
+    let scopes = parse_scopes("write,read").unwrap();
+    assert!(scopes.contains(super::ApiTokenScope::Read));
+    assert!(!scopes.contains(super::ApiTokenScope::Explore));
+    assert_eq!(format_scopes(&scopes), "read,write");
+    assert_eq!(parse_scopes("").unwrap(), super::ApiTokenScopes::default());
+    assert!(parse_scopes("read,admin").is_err());
+}
@@ -0,0 +1,55 @@
+//! traQを使わないローカル開発用の認証
+//!
+//! [`crate::session::AuthMode::Dev`]のときのみ`/dev/login`から使える
+
+pub mod error;
+mod r#impl;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::IntoStatus;
+
+pub use error::Error;
+
+/// 名前を指定しなかったときにログインするユーザー
+pub const DEFAULT_USER_NAME: &str = "dev";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct DevLoginParams {
+    pub name: String,
+}
+
+pub trait DevAuthService<Context>: Send + Sync + 'static {
+    type Error: axum::response::IntoResponse + IntoStatus;
+
+    /// `name`のユーザーを返す; いなければ作成する
+    fn dev_login<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: DevLoginParams,
+    ) -> BoxFuture<'a, Result<crate::user::User, Self::Error>>;
+}
+
+#[allow(clippy::type_complexity)]
+pub trait ProvideDevAuthService: Send + Sync + 'static {
+    type Context;
+    type DevAuthService: DevAuthService<Self::Context>;
+
+    fn context(&self) -> &Self::Context;
+    fn dev_auth_service(&self) -> &Self::DevAuthService;
+
+    fn dev_login(
+        &self,
+        params: DevLoginParams,
+    ) -> BoxFuture<
+        '_,
+        Result<crate::user::User, <Self::DevAuthService as DevAuthService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.dev_auth_service().dev_login(ctx, params)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DevAuthServiceImpl;
@@ -0,0 +1,41 @@
+use axum::response::{IntoResponse, Response};
+use http::StatusCode;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Dev auth is disabled")]
+    Disabled,
+    #[error("Invalid user name")]
+    InvalidName,
+    #[error(transparent)]
+    Status(#[from] tonic::Status),
+}
+
+impl From<Error> for tonic::Status {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Disabled => tonic::Status::not_found("Dev auth is disabled"),
+            Error::InvalidName => tonic::Status::invalid_argument("Invalid user name"),
+            Error::Status(s) => {
+                tracing::error!(error = &s as &dyn std::error::Error, "Unexpected");
+                s
+            }
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        match self {
+            // 本番では存在しないものとして扱う
+            Error::Disabled => StatusCode::NOT_FOUND.into_response(),
+            Error::InvalidName => (StatusCode::BAD_REQUEST, "Invalid user name").into_response(),
+            Error::Status(s) => {
+                tracing::error!(error = &s as &dyn std::error::Error, "Unexpected");
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,73 @@
+use futures::{future::BoxFuture, FutureExt};
+
+use crate::prelude::IntoStatus;
+use crate::session::AuthMode;
+
+/// `users.name`の長さ
+const USER_NAME_MAX_LEN: usize = 255;
+
+impl<Context> super::DevAuthService<Context> for super::DevAuthServiceImpl
+where
+    Context: AsRef<AuthMode> + crate::user::ProvideUserService,
+{
+    type Error = super::Error;
+
+    fn dev_login<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::DevLoginParams,
+    ) -> BoxFuture<'a, Result<crate::user::User, Self::Error>> {
+        dev_login(ctx.as_ref(), ctx, params).boxed()
+    }
+}
+
+#[tracing::instrument(skip_all, fields(name = %params.name))]
+async fn dev_login(
+    mode: &AuthMode,
+    user_service: &impl crate::user::ProvideUserService,
+    params: super::DevLoginParams,
+) -> Result<crate::user::User, super::Error> {
+    if *mode != AuthMode::Dev {
+        return Err(super::Error::Disabled);
+    }
+    let super::DevLoginParams { name } = params;
+    if !is_valid_name(&name) {
+        return Err(super::Error::InvalidName);
+    }
+
+    let user = user_service
+        .find_user_by_name(crate::user::FindUserByNameParams { name: name.clone() })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    if let Some(user) = user {
+        return Ok(user);
+    }
+    let user = user_service
+        .create_user(crate::user::CreateUserParams {
+            display_name: name.clone(),
+            name,
+        })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    tracing::info!(id = %user.id.0, "Created a dev user");
+    Ok(user)
+}
+
+/// traQのユーザー名と同じく、英数字と`-`, `_`のみ
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= USER_NAME_MAX_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[test]
+fn test_is_valid_name() {
+    assert!(is_valid_name("dev"));
+    assert!(is_valid_name("load-test_01"));
+    assert!(!is_valid_name(""));
+    assert!(!is_valid_name("a b"));
+    assert!(!is_valid_name("@dev"));
+    assert!(!is_valid_name(&"a".repeat(USER_NAME_MAX_LEN + 1)));
+}
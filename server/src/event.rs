@@ -1,6 +1,9 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
 use futures::{future::BoxFuture, stream::BoxStream};
 use serde::{Deserialize, Serialize};
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, watch};
 
 use crate::{message::Message, prelude::IntoStatus, speaker_phone::SpeakerPhone};
 
@@ -14,15 +17,54 @@ pub use error::Error;
 pub enum Event {
     Explorer(crate::explore::ExplorerAction),
     SpeakerPhone(SpeakerPhone),
+    /// SpeakerPhoneが移動・変更された
+    SpeakerPhoneUpdated {
+        before: SpeakerPhone,
+        after: SpeakerPhone,
+    },
+    /// SpeakerPhoneが削除された
+    SpeakerPhoneDeleted(SpeakerPhone),
     Message(Message),
+    /// メッセージが編集された
+    MessageUpdated(Message),
+    /// メッセージが削除された
+    MessageDeleted(Message),
+    /// メッセージが保持期間を過ぎた
+    MessageExpired(Message),
     Reaction(crate::reaction::Reaction),
+    /// リアクションが保持期間を過ぎた
+    ReactionExpired(crate::reaction::Reaction),
+}
+
+/// [`EventService::subscribe_area`]で購読する領域
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Area {
+    pub center: crate::world::Coordinate,
+    pub size: crate::world::Size,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubscribeAreaParams {
+    /// 購読する領域; 値が更新される度に購読するセルを変更する
+    pub area: watch::Receiver<Area>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AreaEvent {
+    Event(Event),
+    /// 受信が追いつかず、`n`個のイベントを取りこぼした
+    /// 購読は継続するので、必要であれば領域の状態を取得し直す
+    Lagged(u64),
 }
 
 #[derive(Debug, Clone)]
 pub struct EventChannels {
+    capacity: usize,
     message_tx: broadcast::Sender<Message>,
     speaker_phone_tx: broadcast::Sender<SpeakerPhone>,
     event_tx: broadcast::Sender<Event>,
+    /// セルごとのチャンネル; 購読者がいるセルのみ保持する
+    cell_txs: Arc<RwLock<HashMap<crate::world::grid::Cell, broadcast::Sender<Event>>>>,
 }
 
 pub trait EventService<Context>: Send + Sync + 'static {
@@ -40,6 +82,14 @@ pub trait EventService<Context>: Send + Sync + 'static {
         &'a self,
         ctx: &'a Context,
     ) -> BoxStream<'static, Result<Event, Self::Error>>;
+    /// 領域と重なるセルで発生したイベントのみを購読する
+    ///
+    /// セル単位で配信されるため、領域外のイベントも含まれうる
+    fn subscribe_area<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: SubscribeAreaParams,
+    ) -> BoxStream<'static, Result<AreaEvent, Self::Error>>;
     // 以下はおそらく不要なので書かない
     //     subscribe_explorers
     //     subscribe_reactions
@@ -84,6 +134,16 @@ pub trait ProvideEventService: Send + Sync + 'static {
         let ctx = self.context();
         self.event_service().subscribe_events(ctx)
     }
+    fn subscribe_area(
+        &self,
+        params: SubscribeAreaParams,
+    ) -> BoxStream<
+        'static,
+        Result<AreaEvent, <Self::EventService as EventService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.event_service().subscribe_area(ctx, params)
+    }
 
     fn publish_event(
         &self,
@@ -94,5 +154,59 @@ pub trait ProvideEventService: Send + Sync + 'static {
     }
 }
 
+impl Event {
+    /// イベントが発生した座標
+    pub fn position(&self) -> crate::world::Coordinate {
+        match self {
+            Self::Explorer(action) => action.explorer().position,
+            Self::SpeakerPhone(speaker_phone) | Self::SpeakerPhoneDeleted(speaker_phone) => {
+                speaker_phone.position
+            }
+            Self::SpeakerPhoneUpdated { after, .. } => after.position,
+            Self::Message(message)
+            | Self::MessageUpdated(message)
+            | Self::MessageDeleted(message)
+            | Self::MessageExpired(message) => message.position,
+            Self::Reaction(reaction) | Self::ReactionExpired(reaction) => reaction.position,
+        }
+    }
+
+    /// イベントを届ける範囲
+    ///
+    /// SpeakerPhoneは受信範囲全体で、移動したものは移動前の範囲も含む
+    /// 探索者の移動も移動前の座標を含む
+    pub fn reach(&self) -> Vec<Area> {
+        let point = |center| Area {
+            center,
+            size: crate::world::Size {
+                width: 0,
+                height: 0,
+            },
+        };
+        let reception = |speaker_phone: &SpeakerPhone| {
+            let diameter = speaker_phone.receive_range.saturating_mul(2);
+            Area {
+                center: speaker_phone.position,
+                size: crate::world::Size {
+                    width: diameter,
+                    height: diameter,
+                },
+            }
+        };
+        match self {
+            Self::SpeakerPhone(speaker_phone) | Self::SpeakerPhoneDeleted(speaker_phone) => {
+                vec![reception(speaker_phone)]
+            }
+            Self::SpeakerPhoneUpdated { before, after } => {
+                vec![reception(before), reception(after)]
+            }
+            Self::Explorer(crate::explore::ExplorerAction::Move { explorer, from }) => {
+                vec![point(*from), point(explorer.position)]
+            }
+            _ => vec![point(self.position())],
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct EventServiceImpl;
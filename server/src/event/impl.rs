@@ -1,6 +1,17 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::PoisonError;
+
 use futures::stream::{BoxStream, StreamExt, TryStreamExt};
 use tokio::sync::broadcast;
-use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    StreamMap,
+};
+
+use crate::world::grid::{self, Cell};
+
+/// イベントを配信するセルの一辺の長さ
+const EVENT_CELL_SIZE: u32 = 256;
 
 impl super::EventChannels {
     pub fn new(capacity: usize) -> Self {
@@ -8,13 +19,83 @@ impl super::EventChannels {
         let (speaker_phone_tx, _) = broadcast::channel(capacity);
         let (event_tx, _) = broadcast::channel(capacity);
         Self {
+            capacity,
             message_tx,
             speaker_phone_tx,
             event_tx,
+            cell_txs: Default::default(),
+        }
+    }
+
+    /// `streams`が`area`と重なるセルのみを購読するように更新する
+    fn resubscribe_cells(
+        &self,
+        streams: &mut StreamMap<Cell, BroadcastStream<super::Event>>,
+        area: super::Area,
+    ) {
+        let cells: HashSet<Cell> =
+            grid::cells_in_rect(EVENT_CELL_SIZE, area.center, area.size).collect();
+        let stale: Vec<Cell> = streams
+            .keys()
+            .filter(|cell| !cells.contains(cell))
+            .copied()
+            .collect();
+        for cell in &stale {
+            streams.remove(cell);
+        }
+
+        let mut cell_txs = self
+            .cell_txs
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        remove_unsubscribed_cells(&mut cell_txs, stale);
+        for cell in cells {
+            if streams.contains_key(&cell) {
+                continue;
+            }
+            let tx = cell_txs
+                .entry(cell)
+                .or_insert_with(|| broadcast::Sender::new(self.capacity));
+            streams.insert(cell, BroadcastStream::new(tx.subscribe()));
+        }
+    }
+}
+
+/// `cells`のうち、購読者がいなくなったセルのチャンネルを削除する
+fn remove_unsubscribed_cells(
+    cell_txs: &mut HashMap<Cell, broadcast::Sender<super::Event>>,
+    cells: impl IntoIterator<Item = Cell>,
+) {
+    for cell in cells {
+        if cell_txs
+            .get(&cell)
+            .is_some_and(|tx| tx.receiver_count() == 0)
+        {
+            cell_txs.remove(&cell);
         }
     }
 }
 
+/// 領域内のセルの購読; 破棄した時に購読者のいなくなったセルを掃除する
+struct CellSubscription {
+    channels: super::EventChannels,
+    streams: StreamMap<Cell, BroadcastStream<super::Event>>,
+}
+
+impl Drop for CellSubscription {
+    fn drop(&mut self) {
+        let cells: Vec<Cell> = self.streams.keys().copied().collect();
+        // 先に受信側を破棄して、購読者の数に反映させる
+        self.streams = StreamMap::new();
+        let mut cell_txs = self
+            .channels
+            .cell_txs
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        remove_unsubscribed_cells(&mut cell_txs, cells);
+    }
+}
+
 impl<Context> super::EventService<Context> for super::EventServiceImpl
 where
     Context: AsRef<super::EventChannels>,
@@ -48,6 +129,15 @@ where
         BroadcastStream::new(rx).map_err(super::Error::from).boxed()
     }
 
+    fn subscribe_area<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::SubscribeAreaParams,
+    ) -> BoxStream<'static, Result<super::AreaEvent, Self::Error>> {
+        let channels = ctx.as_ref().clone();
+        subscribe_area(channels, params).boxed()
+    }
+
     fn publish_event<'a>(
         &'a self,
         ctx: &'a Context,
@@ -60,6 +150,64 @@ where
     }
 }
 
+fn subscribe_area(
+    channels: super::EventChannels,
+    params: super::SubscribeAreaParams,
+) -> impl futures::Stream<Item = super::error::Result<super::AreaEvent>> + Send + 'static {
+    let super::SubscribeAreaParams { mut area } = params;
+    // 返り値がpollされる前のイベントも受け取れるよう、ここで購読しておく
+    let mut subscription = CellSubscription {
+        channels,
+        streams: StreamMap::new(),
+    };
+    let current = *area.borrow_and_update();
+    subscription
+        .channels
+        .resubscribe_cells(&mut subscription.streams, current);
+
+    async_stream::stream! {
+        loop {
+            tokio::select! {
+                changed = area.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    let current = *area.borrow_and_update();
+                    let CellSubscription { channels, streams } = &mut subscription;
+                    channels.resubscribe_cells(streams, current);
+                }
+                Some((cell, event)) = subscription.streams.next() => match event {
+                    Ok(event) => {
+                        // 複数のセルに配信されたイベントは、購読しているうち最初のセルからのみ受け取る
+                        let first = cells_of(&event)
+                            .into_iter()
+                            .find(|c| subscription.streams.contains_key(c));
+                        if first == Some(cell) {
+                            yield Ok(super::AreaEvent::Event(event));
+                        }
+                    }
+                    Err(BroadcastStreamRecvError::Lagged(n)) => {
+                        tracing::warn!(skipped = n, "Area subscription lagged");
+                        yield Ok(super::AreaEvent::Lagged(n));
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// `event`を配信するセル; 重複なしで昇順
+fn cells_of(event: &super::Event) -> Vec<Cell> {
+    let mut cells: Vec<Cell> = event
+        .reach()
+        .into_iter()
+        .flat_map(|area| grid::cells_in_rect(EVENT_CELL_SIZE, area.center, area.size))
+        .collect();
+    cells.sort_by_key(|cell| (cell.x, cell.y));
+    cells.dedup();
+    cells
+}
+
 #[tracing::instrument(skip_all)]
 async fn publish_event(
     channels: &super::EventChannels,
@@ -86,16 +234,110 @@ async fn publish_event(
             };
             tracing::trace!(subscribers, "Published speaker phone");
         }
-        super::Event::Explorer(_) | super::Event::Reaction(_) => {}
+        super::Event::Explorer(_)
+        | super::Event::SpeakerPhoneUpdated { .. }
+        | super::Event::SpeakerPhoneDeleted(_)
+        | super::Event::MessageUpdated(_)
+        | super::Event::MessageDeleted(_)
+        | super::Event::MessageExpired(_)
+        | super::Event::Reaction(_)
+        | super::Event::ReactionExpired(_) => {}
+    }
+
+    {
+        let cell_txs = channels
+            .cell_txs
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        for cell in cells_of(&event) {
+            if let Some(tx) = cell_txs.get(&cell) {
+                // 購読者がいなくなったセルは購読を止めた時に掃除される
+                let subscribers = tx.send(event.clone()).unwrap_or(0);
+                tracing::trace!(subscribers, ?cell, "Published event to cell");
+            }
+        }
     }
 
     let subscribers = match channels.event_tx.send(event) {
         Ok(subscribers) => subscribers,
         Err(broadcast::error::SendError(_)) => {
-            tracing::warn!("Failed to publish event: there may be no subscribers");
+            tracing::trace!("No subscribers for whole events");
             0
         }
     };
     tracing::trace!(subscribers, "Published event");
     Ok(())
 }
+
+#[tokio::test]
+async fn test_explorer_move_reaches_previous_cell() {
+    use crate::world::{Coordinate, Size};
+
+    let channels = super::EventChannels::new(16);
+    // 移動前のセルのみを含む領域
+    let area = super::Area {
+        center: Coordinate { x: 10, y: 10 },
+        size: Size {
+            width: 10,
+            height: 10,
+        },
+    };
+    let (_area_tx, area_rx) = tokio::sync::watch::channel(area);
+    let stream = subscribe_area(
+        channels.clone(),
+        super::SubscribeAreaParams { area: area_rx },
+    );
+    futures::pin_mut!(stream);
+
+    let now = crate::prelude::Timestamp(chrono::Utc::now());
+    let explorer = crate::explore::Explorer {
+        id: crate::explore::ExplorerId(uuid::Uuid::now_v7()),
+        inner: crate::user::User {
+            id: crate::user::UserId(uuid::Uuid::now_v7()),
+            name: "user".to_string(),
+            display_name: "user".to_string(),
+            created_at: now,
+            updated_at: now,
+        },
+        position: Coordinate {
+            x: EVENT_CELL_SIZE * 4,
+            y: EVENT_CELL_SIZE * 4,
+        },
+    };
+    let event = super::Event::Explorer(crate::explore::ExplorerAction::Move {
+        explorer,
+        from: Coordinate { x: 10, y: 10 },
+    });
+    publish_event(&channels, event.clone()).await.unwrap();
+
+    let received = tokio::time::timeout(std::time::Duration::from_secs(1), stream.next())
+        .await
+        .expect("event should reach the previous cell");
+    assert_eq!(received.unwrap().unwrap(), super::AreaEvent::Event(event));
+}
+
+#[test]
+fn test_dropped_subscription_removes_cells() {
+    use crate::world::{Coordinate, Size};
+
+    let channels = super::EventChannels::new(16);
+    let area = super::Area {
+        center: Coordinate {
+            x: EVENT_CELL_SIZE,
+            y: EVENT_CELL_SIZE,
+        },
+        size: Size {
+            width: 10,
+            height: 10,
+        },
+    };
+    let (_area_tx, area_rx) = tokio::sync::watch::channel(area);
+    let stream = subscribe_area(
+        channels.clone(),
+        super::SubscribeAreaParams { area: area_rx },
+    );
+    let cell_count = || channels.cell_txs.read().unwrap().len();
+    assert!(cell_count() > 0);
+    drop(stream);
+    assert_eq!(cell_count(), 0);
+}
@@ -10,9 +10,11 @@ use tokio::sync::RwLock;
 use crate::prelude::IntoStatus;
 
 pub mod error;
+pub mod grpc;
 mod r#impl;
 
 pub use error::Error;
+pub use schema::explore::explore_service_server::SERVICE_NAME;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(transparent)]
@@ -31,11 +33,25 @@ pub struct ExplorationField {
     pub size: crate::world::Size,
 }
 
+/// 探索者の移動に関する制限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct MovementLimits {
+    /// 1秒あたりに移動できる距離
+    pub max_speed: u32,
+    /// [`ExplorationField::size`]の上限
+    pub max_field_size: crate::world::Size,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ExplorerAction {
     Arrive(Explorer),
-    Move(Explorer),
+    Move {
+        #[serde(flatten)]
+        explorer: Explorer,
+        /// 移動前の座標; 移動前の位置を見ていたクライアントにも届けるため
+        from: crate::world::Coordinate,
+    },
     Leave(Explorer),
 }
 
@@ -43,19 +59,43 @@ impl ExplorerAction {
     pub fn explorer(&self) -> &Explorer {
         match self {
             Self::Arrive(explorer) => explorer,
-            Self::Move(explorer) => explorer,
+            Self::Move { explorer, .. } => explorer,
             Self::Leave(explorer) => explorer,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExplorationFieldEvents {
     pub messages: Vec<crate::message::Message>,
     pub speaker_phones: Vec<crate::speaker_phone::SpeakerPhone>,
     pub reactions: Vec<crate::reaction::Reaction>,
     pub explorer_actions: Vec<ExplorerAction>,
+    /// 領域から外れたメッセージ
+    pub removed_message_ids: Vec<crate::message::MessageId>,
+    /// 領域から外れたスピーカーフォン
+    pub removed_speaker_phone_ids: Vec<crate::speaker_phone::SpeakerPhoneId>,
+    /// 領域から外れた探索者; オフラインになった探索者は[`ExplorerAction::Leave`]で通知する
+    pub removed_explorer_ids: Vec<ExplorerId>,
+    /// 保持期間を過ぎたメッセージ
+    pub expired_message_ids: Vec<crate::message::MessageId>,
+    /// 保持期間を過ぎたリアクション
+    pub expired_reaction_ids: Vec<crate::reaction::ReactionId>,
+}
+
+impl ExplorationFieldEvents {
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+            && self.speaker_phones.is_empty()
+            && self.reactions.is_empty()
+            && self.explorer_actions.is_empty()
+            && self.removed_message_ids.is_empty()
+            && self.removed_speaker_phone_ids.is_empty()
+            && self.removed_explorer_ids.is_empty()
+            && self.expired_message_ids.is_empty()
+            && self.expired_reaction_ids.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -80,6 +120,9 @@ pub struct DeleteExplorerParams {
     pub id: ExplorerId,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct SaveAllExplorerPositionsParams {}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum GetExplorersInAreaParams {
@@ -87,11 +130,10 @@ pub enum GetExplorersInAreaParams {
         center: crate::world::Coordinate,
         size: crate::world::Size,
     },
-    // これ必要かわからん
-    // Circle {
-    //     center: crate::world::Coordinate,
-    //     radius: u32,
-    // },
+    Circle {
+        center: crate::world::Coordinate,
+        radius: u32,
+    },
 }
 
 pub struct ExploreParams<'a> {
@@ -99,9 +141,19 @@ pub struct ExploreParams<'a> {
     pub stream: BoxStream<'a, ExplorationField>,
 }
 
+/// Explorerのインメモリストア
+///
+/// 範囲検索のために位置を[`Grid`]でも管理する
+///
+/// [`Grid`]: crate::world::grid::Grid
 #[derive(Clone)]
 pub struct ExplorerStore {
-    explorers: Arc<RwLock<HashMap<ExplorerId, Explorer>>>,
+    inner: Arc<RwLock<ExplorerStoreInner>>,
+}
+
+struct ExplorerStoreInner {
+    explorers: HashMap<ExplorerId, Explorer>,
+    grid: crate::world::grid::Grid<ExplorerId>,
 }
 
 pub trait ExploreService<Context>: Send + Sync + 'static {
@@ -168,6 +220,12 @@ pub trait ExplorerService<Context>: Send + Sync + 'static {
         ctx: &'a Context,
         params: DeleteExplorerParams,
     ) -> BoxFuture<'a, Result<Explorer, Self::Error>>;
+    /// 現在の全Explorerの位置をユーザーの最後の位置として保存する
+    fn save_all_explorer_positions<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: SaveAllExplorerPositionsParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>>;
 }
 
 #[allow(clippy::type_complexity)]
@@ -231,7 +289,27 @@ pub trait ProvideExplorerService: Send + Sync + 'static {
         let ctx = self.context();
         self.explorer_service().delete_explorer(ctx, params)
     }
+    fn save_all_explorer_positions(
+        &self,
+        params: SaveAllExplorerPositionsParams,
+    ) -> BoxFuture<'_, Result<(), <Self::ExplorerService as ExplorerService<Self::Context>>::Error>>
+    {
+        let ctx = self.context();
+        self.explorer_service()
+            .save_all_explorer_positions(ctx, params)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct ExplorerServiceImpl;
+
+pub fn build_server<State>(state: Arc<State>) -> ExploreServiceServer<State>
+where
+    State: ProvideExploreService + crate::session::ProvideSessionService,
+{
+    let service = grpc::ServiceImpl::new(state);
+    ExploreServiceServer::new(service)
+}
+
+pub type ExploreServiceServer<State> =
+    schema::explore::explore_service_server::ExploreServiceServer<grpc::ServiceImpl<State>>;
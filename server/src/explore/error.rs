@@ -10,8 +10,11 @@ pub enum Error {
 
 impl From<Error> for tonic::Status {
     fn from(value: Error) -> Self {
-        // TODO: match書く
-        tonic::Status::internal(value.to_string())
+        match value {
+            Error::Status(status) => status,
+            // TODO: match書く
+            _ => tonic::Status::internal(value.to_string()),
+        }
     }
 }
 
@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use schema::explore as schema;
+
+use crate::prelude::IntoStatus;
+
+// MARK: type conversions
+
+impl From<super::Explorer> for schema::Explorer {
+    fn from(value: super::Explorer) -> Self {
+        let super::Explorer {
+            id,
+            inner,
+            position,
+        } = value;
+        Self {
+            id: id.0.to_string(),
+            user_id: inner.id.0.to_string(),
+            position: Some(position.into()),
+        }
+    }
+}
+
+impl From<super::ExplorerAction> for schema::ExplorerAction {
+    fn from(value: super::ExplorerAction) -> Self {
+        use schema::explorer_action::{Action, Arrive, Leave, Move};
+
+        let action = match value {
+            super::ExplorerAction::Arrive(explorer) => Action::Arrive(Arrive {
+                explorer: Some(explorer.into()),
+            }),
+            super::ExplorerAction::Move { explorer, .. } => Action::Move(Move {
+                explorer: Some(explorer.into()),
+            }),
+            super::ExplorerAction::Leave(explorer) => Action::Leave(Leave {
+                id: explorer.id.0.to_string(),
+            }),
+        };
+        Self {
+            action: Some(action),
+        }
+    }
+}
+
+impl From<super::ExplorationFieldEvents> for schema::ExplorationFieldEvents {
+    fn from(value: super::ExplorationFieldEvents) -> Self {
+        let super::ExplorationFieldEvents {
+            messages,
+            speaker_phones,
+            reactions,
+            explorer_actions,
+            removed_message_ids,
+            removed_speaker_phone_ids,
+            removed_explorer_ids,
+            expired_message_ids,
+            expired_reaction_ids,
+        } = value;
+        Self {
+            messages: messages.into_iter().map(Into::into).collect(),
+            speaker_phones: speaker_phones.into_iter().map(Into::into).collect(),
+            reactions: reactions.into_iter().map(Into::into).collect(),
+            explorer_actions: explorer_actions.into_iter().map(Into::into).collect(),
+            removed_message_ids: removed_message_ids
+                .into_iter()
+                .map(|id| id.0.to_string())
+                .collect(),
+            removed_speaker_phone_ids: removed_speaker_phone_ids
+                .into_iter()
+                .map(|id| id.0.to_string())
+                .collect(),
+            removed_explorer_ids: removed_explorer_ids
+                .into_iter()
+                .map(|id| id.0.to_string())
+                .collect(),
+            expired_message_ids: expired_message_ids
+                .into_iter()
+                .map(|id| id.0.to_string())
+                .collect(),
+            expired_reaction_ids: expired_reaction_ids
+                .into_iter()
+                .map(|id| id.0.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<schema::ExplorationField> for super::ExplorationField {
+    type Error = tonic::Status;
+
+    fn try_from(value: schema::ExplorationField) -> Result<Self, Self::Error> {
+        let schema::ExplorationField {
+            position: Some(position),
+            size: Some(size),
+        } = value
+        else {
+            return Err(tonic::Status::invalid_argument(
+                "Position and size are required",
+            ));
+        };
+        Ok(Self {
+            position: position.into(),
+            size: size.into(),
+        })
+    }
+}
+
+// MARK: ServiceImpl
+
+pub struct ServiceImpl<State> {
+    state: Arc<State>,
+}
+
+impl<State> Clone for ServiceImpl<State>
+where
+    State: super::ProvideExploreService,
+{
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<State> ServiceImpl<State>
+where
+    State: super::ProvideExploreService + crate::session::ProvideSessionService,
+{
+    pub(super) fn new(state: Arc<State>) -> Self {
+        Self { state }
+    }
+}
+
+#[async_trait::async_trait]
+impl<State> schema::explore_service_server::ExploreService for ServiceImpl<State>
+where
+    State: super::ProvideExploreService + crate::session::ProvideSessionService,
+{
+    type ExploreStream =
+        futures::stream::BoxStream<'static, Result<schema::ExplorationFieldEvents, tonic::Status>>;
+
+    async fn explore(
+        &self,
+        request: tonic::Request<tonic::Streaming<schema::ExplorationField>>,
+    ) -> Result<tonic::Response<Self::ExploreStream>, tonic::Status> {
+        let (meta, _, mut fields) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+
+        let fields = async_stream::stream! {
+            while let Some(field) = fields.next().await {
+                let field = field.and_then(super::ExplorationField::try_from);
+                match field {
+                    Ok(field) => yield field,
+                    Err(e) => {
+                        tracing::warn!(error = &e as &dyn std::error::Error, "Invalid exploration field");
+                        break;
+                    }
+                }
+            }
+        };
+        let state = Arc::clone(&self.state);
+        let events = async_stream::try_stream! {
+            let params = super::ExploreParams {
+                id: user_id,
+                stream: fields.boxed(),
+            };
+            let mut events = state.explore(params);
+            while let Some(e) = events.next().await {
+                yield e.map_err(IntoStatus::into_status)?.into();
+            }
+        };
+        Ok(tonic::Response::new(events.boxed()))
+    }
+}
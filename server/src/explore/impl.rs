@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use futures::future::{BoxFuture, FutureExt};
 use tokio::sync::RwLock;
@@ -8,20 +9,31 @@ use tokio::sync::RwLock;
 use futures::StreamExt;
 
 use crate::{
-    event::{Event, ProvideEventService},
+    event::{AreaEvent, Event, ProvideEventService},
     message::ProvideMessageService,
     prelude::IntoStatus,
     reaction::ProvideReactionService,
     speaker_phone::ProvideSpeakerPhoneService,
     user::ProvideUserService,
+    world::ProvideWorldService,
 };
 
 use super::ProvideExplorerService;
 
+/// [`super::ExplorerStore`]のセルの一辺の長さ
+const EXPLORER_GRID_CELL_SIZE: u32 = 128;
+
+/// 移動距離の計算で考慮する経過時間の上限; 長時間静止した後の瞬間移動を防ぐ
+const MAX_MOVEMENT_INTERVAL: Duration = Duration::from_secs(1);
+
 impl super::ExplorerStore {
     pub fn new() -> Self {
+        let inner = super::ExplorerStoreInner {
+            explorers: HashMap::new(),
+            grid: crate::world::grid::Grid::new(EXPLORER_GRID_CELL_SIZE),
+        };
         Self {
-            explorers: Arc::new(RwLock::new(HashMap::new())),
+            inner: Arc::new(RwLock::new(inner)),
         }
     }
 }
@@ -42,7 +54,7 @@ impl fmt::Debug for super::ExplorerStore {
 
 impl<Context> super::ExplorerService<Context> for super::ExplorerServiceImpl
 where
-    Context: ProvideEventService + AsRef<super::ExplorerStore>,
+    Context: ProvideEventService + ProvideUserService + AsRef<super::ExplorerStore>,
 {
     type Error = super::Error;
 
@@ -85,6 +97,14 @@ where
     ) -> BoxFuture<'a, Result<super::Explorer, Self::Error>> {
         delete_explorer(ctx, ctx.as_ref(), params).boxed()
     }
+
+    fn save_all_explorer_positions<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::SaveAllExplorerPositionsParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>> {
+        save_all_explorer_positions(ctx, ctx.as_ref(), params).boxed()
+    }
 }
 
 async fn get_explorer(
@@ -92,8 +112,12 @@ async fn get_explorer(
     params: super::GetExplorerParams,
 ) -> Result<super::Explorer, super::Error> {
     let super::GetExplorerParams { id } = params;
-    let explorers = store.explorers.read().await;
-    explorers.get(&id).ok_or(super::Error::NotFound).cloned()
+    let inner = store.inner.read().await;
+    inner
+        .explorers
+        .get(&id)
+        .ok_or(super::Error::NotFound)
+        .cloned()
 }
 
 async fn create_explorer<E: ProvideEventService>(
@@ -108,7 +132,11 @@ async fn create_explorer<E: ProvideEventService>(
         inner,
         position,
     };
-    store.explorers.write().await.insert(id, explorer.clone());
+    {
+        let mut inner = store.inner.write().await;
+        inner.grid.insert(id, position);
+        inner.explorers.insert(id, explorer.clone());
+    }
     let event = Event::Explorer(super::ExplorerAction::Arrive(explorer.clone()));
     event_service
         .publish_event(event)
@@ -117,32 +145,28 @@ async fn create_explorer<E: ProvideEventService>(
     Ok(explorer)
 }
 
-// FIXME
 async fn get_explorers_in_area(
     store: &super::ExplorerStore,
     params: super::GetExplorersInAreaParams,
 ) -> Result<Vec<super::Explorer>, super::Error> {
-    let f = match params {
-        super::GetExplorersInAreaParams::Rect { center, size } => {
-            let x_min = center.x.saturating_sub(size.width >> 1);
-            let x_max = center.x.saturating_add(size.width >> 1);
-            let y_min = center.y.saturating_sub(size.height >> 1);
-            let y_max = center.y.saturating_add(size.height >> 1);
-            move |e: &super::Explorer| {
-                if x_min < e.position.x
-                    && e.position.x < x_max
-                    && y_min < e.position.y
-                    && e.position.y < y_max
-                {
-                    Some(e.clone())
-                } else {
-                    None
-                }
-            }
-        }
+    let inner = store.inner.read().await;
+    let explorers = &inner.explorers;
+    let res = match params {
+        super::GetExplorersInAreaParams::Rect { center, size } => inner
+            .grid
+            .query_rect(center, size)
+            .filter_map(|id| explorers.get(id))
+            .filter(|e| e.position.is_inside_rect(center, size))
+            .cloned()
+            .collect(),
+        super::GetExplorersInAreaParams::Circle { center, radius } => inner
+            .grid
+            .query_circle(center, radius)
+            .filter_map(|id| explorers.get(id))
+            .filter(|e| e.position.is_inside_circle(center, radius))
+            .cloned()
+            .collect(),
     };
-    let explorers = store.explorers.read().await;
-    let res = explorers.iter().filter_map(|(_, e)| f(e)).collect();
     Ok(res)
 }
 
@@ -152,13 +176,19 @@ async fn update_explorer<E: ProvideEventService>(
     params: super::UpdateExplorerParams,
 ) -> Result<super::Explorer, super::Error> {
     let super::UpdateExplorerParams { id, position } = params;
-    let updated = {
-        let mut explorers = store.explorers.write().await;
+    let (updated, from) = {
+        let mut inner = store.inner.write().await;
+        let super::ExplorerStoreInner { explorers, grid } = &mut *inner;
         let explorer = explorers.get_mut(&id).ok_or(super::Error::NotFound)?;
+        let from = explorer.position;
+        grid.relocate(id, from, position);
         explorer.position = position;
-        explorer.clone()
+        (explorer.clone(), from)
     };
-    let event = Event::Explorer(super::ExplorerAction::Move(updated.clone()));
+    let event = Event::Explorer(super::ExplorerAction::Move {
+        explorer: updated.clone(),
+        from,
+    });
     event_service
         .publish_event(event)
         .await
@@ -166,24 +196,61 @@ async fn update_explorer<E: ProvideEventService>(
     Ok(updated)
 }
 
-async fn delete_explorer<E: ProvideEventService>(
-    event_service: &E,
+async fn delete_explorer<E: ProvideEventService + ProvideUserService>(
+    ctx: &E,
     store: &super::ExplorerStore,
     params: super::DeleteExplorerParams,
 ) -> Result<super::Explorer, super::Error> {
     let super::DeleteExplorerParams { id } = params;
     let deleted = {
-        let mut explorers = store.explorers.write().await;
-        explorers.remove(&id).ok_or(super::Error::NotFound)
-    }?;
+        let mut inner = store.inner.write().await;
+        let deleted = inner.explorers.remove(&id).ok_or(super::Error::NotFound)?;
+        inner.grid.remove(&id, deleted.position);
+        deleted
+    };
     let event = Event::Explorer(super::ExplorerAction::Leave(deleted.clone()));
-    event_service
-        .publish_event(event)
+    ctx.publish_event(event)
         .await
         .map_err(IntoStatus::into_status)?;
+    // 保存に失敗しても削除済みのExplorerは戻せないので、ログに残して続ける
+    let params = crate::user::SaveLastPositionParams {
+        id: deleted.inner.id,
+        position: deleted.position,
+    };
+    if let Err(e) = ctx.save_last_position(params).await {
+        let e = e.into_status();
+        tracing::error!(
+            error = &e as &dyn std::error::Error,
+            "Failed to save last position"
+        );
+    }
     Ok(deleted)
 }
 
+async fn save_all_explorer_positions<U: ProvideUserService>(
+    user_service: &U,
+    store: &super::ExplorerStore,
+    params: super::SaveAllExplorerPositionsParams,
+) -> Result<(), super::Error> {
+    let super::SaveAllExplorerPositionsParams {} = params;
+    // 同じユーザーが複数のExplorerを持つ場合はどれか1つの位置を保存する
+    let positions: HashMap<_, _> = {
+        let inner = store.inner.read().await;
+        inner
+            .explorers
+            .values()
+            .map(|e| (e.inner.id, e.position))
+            .collect()
+    };
+    for (id, position) in positions {
+        user_service
+            .save_last_position(crate::user::SaveLastPositionParams { id, position })
+            .await
+            .map_err(IntoStatus::into_status)?;
+    }
+    Ok(())
+}
+
 impl<Context> super::ExploreService<Context> for super::ExploreServiceImpl
 where
     Context: ProvideEventService
@@ -191,7 +258,9 @@ where
         + ProvideMessageService
         + ProvideSpeakerPhoneService
         + ProvideExplorerService
-        + ProvideReactionService,
+        + ProvideReactionService
+        + ProvideWorldService
+        + AsRef<super::MovementLimits>,
 {
     // type Error = super::error::Error;
     type Error = tonic::Status;
@@ -217,15 +286,38 @@ where
         + ProvideMessageService
         + ProvideSpeakerPhoneService
         + ProvideExplorerService
-        + ProvideReactionService,
+        + ProvideReactionService
+        + ProvideWorldService
+        + AsRef<super::MovementLimits>,
 {
     async_stream::try_stream! {
         let super::ExploreParams { id, stream: mut exploration_field_stream } = params;
-        let event_stream = ctx.subscribe_events();
+
+        let world_size = ctx.get_world_size(crate::world::GetWorldSizeParams {})
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let movement_limits: super::MovementLimits = *ctx.as_ref();
 
         let exploration_field_first_value = exploration_field_stream.next()
             .await
             .ok_or(super::error::Error::ExplorationFieldStreamClosed)?;
+        // 再接続による瞬間移動を防ぐため、最後の位置からの移動として扱う
+        let last_position = ctx.get_last_position(crate::user::GetLastPositionParams { id })
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let exploration_field_first_value = restrict_exploration_field(
+            exploration_field_first_value,
+            last_position.map(|position| (position, MAX_MOVEMENT_INTERVAL)),
+            movement_limits,
+            world_size,
+        );
+
+        // 取りこぼしを防ぐため、状態を取得する前に購読しておく
+        let (area_tx, area_rx) = tokio::sync::watch::channel(crate::event::Area {
+            center: exploration_field_first_value.position,
+            size: exploration_field_first_value.size,
+        });
+        let event_stream = ctx.subscribe_area(crate::event::SubscribeAreaParams { area: area_rx });
 
         // new explorer arrives
         let user = ctx.get_user(crate::user::GetUserParams { id }).await?;
@@ -236,33 +328,54 @@ where
         let exploration_field = exploration_field_first_value;
 
         // create status
-        let old_area_messages_cache = ctx.get_messages_in_area(
-            crate::message::GetMessagesInAreaParams {
-                center: exploration_field.position,
-                size: exploration_field.size,
-            },
-        ).await?;
-
-        let old_area_speaker_phones_cache = ctx.get_speaker_phones_in_area(
-            crate::speaker_phone::GetSpeakerPhonesInAreaParams {
-                center: exploration_field.position,
-                size: exploration_field.size,
-            },
-        ).await?;
-
-        let old_area_reactions_cache = ctx.get_reactions_in_area(
-            crate::reaction::GetReactionsInAreaParams {
-                center: exploration_field.position,
-                size: exploration_field.size,
-            },
-        ).await?;
-
-        let old_area_explorers_cache = ctx.get_explorers_in_area(
-            crate::explore::GetExplorersInAreaParams::Rect {
-                center: exploration_field.position,
-                size: exploration_field.size,
-            },
-        ).await?;
+        // 以降で失敗しても、作成したExplorerは必ず削除する
+        let caches = async {
+            let old_area_messages_cache = ctx.get_messages_in_area(
+                crate::message::GetMessagesInAreaParams {
+                    center: exploration_field.position,
+                    size: exploration_field.size,
+                },
+            ).await.map_err(IntoStatus::into_status)?;
+
+            let old_area_speaker_phones_cache = ctx.get_speaker_phones_in_area(
+                crate::speaker_phone::GetSpeakerPhonesInAreaParams {
+                    center: exploration_field.position,
+                    size: exploration_field.size,
+                },
+            ).await.map_err(IntoStatus::into_status)?;
+
+            let old_area_reactions_cache = ctx.get_reactions_in_area(
+                crate::reaction::GetReactionsInAreaParams {
+                    center: exploration_field.position,
+                    size: exploration_field.size,
+                },
+            ).await.map_err(IntoStatus::into_status)?;
+
+            let old_area_explorers_cache = ctx.get_explorers_in_area(
+                crate::explore::GetExplorersInAreaParams::Rect {
+                    center: exploration_field.position,
+                    size: exploration_field.size,
+                },
+            ).await.map_err(IntoStatus::into_status)?;
+            Ok::<_, tonic::Status>((
+                old_area_messages_cache,
+                old_area_speaker_phones_cache,
+                old_area_reactions_cache,
+                old_area_explorers_cache,
+            ))
+        }.await;
+        let (
+            old_area_messages_cache,
+            old_area_speaker_phones_cache,
+            old_area_reactions_cache,
+            old_area_explorers_cache,
+        ) = match caches {
+            Ok(caches) => caches,
+            Err(e) => {
+                leave(ctx, explorer.id).await;
+                Err(e)?
+            }
+        };
 
         yield super::ExplorationFieldEvents {
             messages: old_area_messages_cache.clone(),
@@ -271,12 +384,17 @@ where
             explorer_actions: old_area_explorers_cache.iter().map(|e| {
                 super::ExplorerAction::Arrive(e.clone())
             }).collect(),
+            ..Default::default()
         };
 
         let mut status = ExplorerStatus {
             ctx,
             explorer: explorer.clone(),
             exploration_field_size: exploration_field.size,
+            area_tx,
+            world_size,
+            movement_limits,
+            last_moved_at: Instant::now(),
             old_area_messages_cache,
             old_area_speaker_phones_cache,
             old_area_reactions_cache,
@@ -288,38 +406,52 @@ where
             SelectResult::ExplorationField,
         ), event_stream.map(
             |event| {
-                let event = match event {
-                    Ok(event) => event,
-                    Err(e) => {
-                        return SelectResult::EventStreamClosed(super::error::Error::Status(e.into()));
-                    },
-                };
-                SelectResult::Event(event)
+                match event {
+                    Ok(AreaEvent::Event(event)) => SelectResult::Event(event),
+                    Ok(AreaEvent::Lagged(skipped)) => SelectResult::Lagged(skipped),
+                    Err(e) => SelectResult::EventStreamClosed(super::error::Error::Status(e.into())),
+                }
             },
         ));
 
-        loop {
+        let res = loop {
             let Some(select_result) = select.next().await else {
                 // explore leaves
-                break;
+                break Ok(());
             };
 
-            if let Some(exploration_field_events) = event_handle(
-                select_result,
-                &mut status,
-            ).await? {
-                yield exploration_field_events;
+            match event_handle(select_result, &mut status).await {
+                Ok(Some(exploration_field_events)) => yield exploration_field_events,
+                Ok(None) => {}
+                Err(e) => break Err(e),
             }
-        }
+        };
 
         // explorer leaves
-        ctx.delete_explorer(crate::explore::DeleteExplorerParams { id: explorer.id }).await.map_err(IntoStatus::into_status)?;
+        leave(ctx, explorer.id).await;
+        res?;
+    }
+}
+
+/// 探索を終えたExplorerを削除する; 失敗しても探索の終了は止めない
+async fn leave<Context: ProvideExplorerService>(ctx: &Context, id: super::ExplorerId) {
+    if let Err(e) = ctx
+        .delete_explorer(crate::explore::DeleteExplorerParams { id })
+        .await
+    {
+        let e = e.into_status();
+        tracing::error!(
+            error = &e as &dyn std::error::Error,
+            "Failed to delete explorer"
+        );
     }
 }
 
 enum SelectResult {
     ExplorationField(super::ExplorationField),
     Event(crate::event::Event),
+    /// イベントを取りこぼした
+    Lagged(u64),
     EventStreamClosed(super::error::Error),
 }
 
@@ -334,6 +466,12 @@ where
     ctx: &'a Context,
     explorer: super::Explorer,
     exploration_field_size: crate::world::Size,
+    /// 購読する領域の更新先
+    area_tx: tokio::sync::watch::Sender<crate::event::Area>,
+    world_size: crate::world::Size,
+    movement_limits: super::MovementLimits,
+    /// 最後に移動した時刻
+    last_moved_at: Instant,
     old_area_messages_cache: Vec<crate::message::Message>,
     old_area_speaker_phones_cache: Vec<crate::speaker_phone::SpeakerPhone>,
     old_area_reactions_cache: Vec<crate::reaction::Reaction>,
@@ -358,6 +496,10 @@ where
             when_exploration_field_moved(exploration_field, status).await
         }
         SelectResult::Event(event) => when_received_event(event, status),
+        SelectResult::Lagged(skipped) => {
+            tracing::warn!(skipped, "Resynchronizing exploration field");
+            resync(status).await
+        }
     }
 }
 
@@ -373,119 +515,195 @@ where
         + ProvideExplorerService
         + ProvideReactionService,
 {
+    let now = Instant::now();
+    let new_exploration_field = restrict_exploration_field(
+        new_exploration_field,
+        Some((status.explorer.position, now - status.last_moved_at)),
+        status.movement_limits,
+        status.world_size,
+    );
+    status.last_moved_at = now;
+
     // publish explorer move event
     status
         .ctx
         .update_explorer(crate::explore::UpdateExplorerParams {
             id: status.explorer.id,
-            position: status.explorer.position,
+            position: new_exploration_field.position,
         })
         .await
         .map_err(IntoStatus::into_status)?;
 
-    // collect newly contained events
+    let events = diff_exploration_field(new_exploration_field, status).await?;
+    Ok(Some(events))
+}
+
+/// クライアントから送られた`exploration_field`を検証する
+///
+/// 大きさは上限内に、位置は世界の範囲内に、`previous`からの移動は速度の上限内に収める
+fn restrict_exploration_field(
+    exploration_field: super::ExplorationField,
+    previous: Option<(crate::world::Coordinate, Duration)>,
+    limits: super::MovementLimits,
+    world_size: crate::world::Size,
+) -> super::ExplorationField {
+    let super::ExplorationField { position, size } = exploration_field;
+    let size = crate::world::Size {
+        width: size.width.min(limits.max_field_size.width),
+        height: size.height.min(limits.max_field_size.height),
+    };
+    let mut position = position.clamp_into(world_size);
+    if let Some((from, elapsed)) = previous {
+        let elapsed = elapsed.min(MAX_MOVEMENT_INTERVAL);
+        let max_distance = limits.max_speed as f64 * elapsed.as_secs_f64();
+        position = from.step_towards(position, max_distance);
+    }
+    super::ExplorationField { position, size }
+}
 
-    // messages
-    let new_area_messages = status
+/// イベントを取りこぼした時に、現在の領域を取得し直してキャッシュとの差分を送る
+async fn resync<Context>(
+    status: &mut ExplorerStatus<'_, Context>,
+) -> Result<Option<super::ExplorationFieldEvents>, tonic::Status>
+where
+    Context: ProvideEventService
+        + ProvideUserService
+        + ProvideMessageService
+        + ProvideSpeakerPhoneService
+        + ProvideExplorerService
+        + ProvideReactionService,
+{
+    let exploration_field = super::ExplorationField {
+        position: status.explorer.position,
+        size: status.exploration_field_size,
+    };
+    let events = diff_exploration_field(exploration_field, status).await?;
+    Ok((!events.is_empty()).then_some(events))
+}
+
+/// `exploration_field`内の状態を取得し、キャッシュとの差分を返す
+///
+/// キャッシュと購読する領域は`exploration_field`のものに更新される
+async fn diff_exploration_field<Context>(
+    exploration_field: super::ExplorationField,
+    status: &mut ExplorerStatus<'_, Context>,
+) -> Result<super::ExplorationFieldEvents, tonic::Status>
+where
+    Context: ProvideEventService
+        + ProvideUserService
+        + ProvideMessageService
+        + ProvideSpeakerPhoneService
+        + ProvideExplorerService
+        + ProvideReactionService,
+{
+    let super::ExplorationField {
+        position: center,
+        size,
+    } = exploration_field;
+
+    let area_messages = status
         .ctx
-        .get_messages_in_area(crate::message::GetMessagesInAreaParams {
-            center: new_exploration_field.position,
-            size: new_exploration_field.size,
-        })
+        .get_messages_in_area(crate::message::GetMessagesInAreaParams { center, size })
         .await
         .map_err(IntoStatus::into_status)?;
-
-    let messages = new_area_messages
-        .iter()
-        .filter_map(|new_message| {
-            if !status.old_area_messages_cache.contains(new_message) {
-                Some(new_message.clone())
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>();
-
-    // speaker_phones
-    let new_area_speaker_phones = status
+    let area_speaker_phones = status
         .ctx
         .get_speaker_phones_in_area(crate::speaker_phone::GetSpeakerPhonesInAreaParams {
-            center: new_exploration_field.position,
-            size: new_exploration_field.size,
+            center,
+            size,
         })
         .await
         .map_err(IntoStatus::into_status)?;
-
-    let speaker_phones = new_area_speaker_phones
-        .iter()
-        .filter_map(|new_speaker_phone| {
-            if !status
-                .old_area_speaker_phones_cache
-                .contains(new_speaker_phone)
-            {
-                Some(new_speaker_phone.clone())
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>();
-
-    // reactions
-    let new_area_reactions = status
+    let area_reactions = status
         .ctx
-        .get_reactions_in_area(crate::reaction::GetReactionsInAreaParams {
-            center: new_exploration_field.position,
-            size: new_exploration_field.size,
-        })
+        .get_reactions_in_area(crate::reaction::GetReactionsInAreaParams { center, size })
         .await
         .map_err(IntoStatus::into_status)?;
-
-    let reactions = new_area_reactions
-        .iter()
-        .filter_map(|new_reaction| {
-            if !status.old_area_reactions_cache.contains(new_reaction) {
-                Some(new_reaction.clone())
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>();
-
-    // explorers
-    let new_area_explorers = status
+    let area_explorers = status
         .ctx
-        .get_explorers_in_area(crate::explore::GetExplorersInAreaParams::Rect {
-            center: new_exploration_field.position,
-            size: new_exploration_field.size,
-        })
+        .get_explorers_in_area(crate::explore::GetExplorersInAreaParams::Rect { center, size })
         .await
         .map_err(IntoStatus::into_status)?;
 
-    let explorer_actions = new_area_explorers
+    // messages
+    let messages = not_contained(&area_messages, &status.old_area_messages_cache);
+    let removed_message_ids =
+        removed_keys(&status.old_area_messages_cache, &area_messages, |m| m.id);
+
+    // speaker_phones
+    let speaker_phones = not_contained(&area_speaker_phones, &status.old_area_speaker_phones_cache);
+    let removed_speaker_phone_ids = removed_keys(
+        &status.old_area_speaker_phones_cache,
+        &area_speaker_phones,
+        |s| s.id,
+    );
+
+    // reactions
+    let reactions = not_contained(&area_reactions, &status.old_area_reactions_cache);
+
+    // explorers
+    let old_explorers: HashMap<_, _> = status
+        .old_area_explorers_cache
         .iter()
-        .filter_map(|new_explorer| {
-            if !status.old_area_explorers_cache.contains(new_explorer) {
-                Some(super::ExplorerAction::Arrive(new_explorer.clone()))
-            } else {
-                None
-            }
+        .map(|e| (e.id, e))
+        .collect();
+    let explorer_actions = area_explorers
+        .iter()
+        .filter_map(|explorer| match old_explorers.get(&explorer.id) {
+            None => Some(super::ExplorerAction::Arrive(explorer.clone())),
+            Some(old) if old.position != explorer.position => Some(super::ExplorerAction::Move {
+                explorer: explorer.clone(),
+                from: old.position,
+            }),
+            Some(_) => None,
         })
-        .collect::<Vec<_>>();
+        .collect();
+    let removed_explorer_ids =
+        removed_keys(&status.old_area_explorers_cache, &area_explorers, |e| e.id);
 
     // update exploration field / cache
-    status.explorer.position = new_exploration_field.position;
-    status.exploration_field_size = new_exploration_field.size;
-    status.old_area_messages_cache = new_area_messages;
-    status.old_area_speaker_phones_cache = new_area_speaker_phones;
-    status.old_area_explorers_cache = new_area_explorers;
-
-    // exploration field events
-    Ok(Some(super::ExplorationFieldEvents {
+    status.explorer.position = center;
+    status.exploration_field_size = size;
+    status
+        .area_tx
+        .send_replace(crate::event::Area { center, size });
+    status.old_area_messages_cache = area_messages;
+    status.old_area_speaker_phones_cache = area_speaker_phones;
+    status.old_area_reactions_cache = area_reactions;
+    status.old_area_explorers_cache = area_explorers;
+
+    Ok(super::ExplorationFieldEvents {
         messages,
         speaker_phones,
         reactions,
         explorer_actions,
-    }))
+        removed_message_ids,
+        removed_speaker_phone_ids,
+        removed_explorer_ids,
+        ..Default::default()
+    })
+}
+
+/// `new`のうち`old`に含まれないものを返す
+fn not_contained<T: PartialEq + Clone>(new: &[T], old: &[T]) -> Vec<T> {
+    new.iter().filter(|v| !old.contains(v)).cloned().collect()
+}
+
+/// `old`のうち`new`に同じキーが存在しないもののキーを返す
+fn removed_keys<T, K: PartialEq>(old: &[T], new: &[T], key: impl Fn(&T) -> K) -> Vec<K> {
+    old.iter()
+        .map(&key)
+        .filter(|k| new.iter().all(|v| key(v) != *k))
+        .collect()
+}
+
+/// `cache`内の同じキーを持つ要素を`value`で置き換える; 無ければ追加する
+fn upsert_by_key<T, K: PartialEq>(cache: &mut Vec<T>, value: T, key: impl Fn(&T) -> K) {
+    let k = key(&value);
+    match cache.iter_mut().find(|v| key(v) == k) {
+        Some(v) => *v = value,
+        None => cache.push(value),
+    }
 }
 
 fn when_received_event<Context>(
@@ -500,66 +718,149 @@ where
         + ProvideExplorerService
         + ProvideReactionService,
 {
+    let center = status.explorer.position;
+    let size = status.exploration_field_size;
     match event {
         crate::event::Event::Explorer(explorer_action) => {
-            if is_inside(
-                status.explorer.position,
-                status.exploration_field_size,
-                explorer_action.explorer().position,
-            ) {
+            let explorer = explorer_action.explorer().clone();
+            let inside = is_inside(center, size, explorer.position);
+            let cache = &mut status.old_area_explorers_cache;
+            let cached = cache.iter().any(|e| e.id == explorer.id);
+            cache.retain(|e| e.id != explorer.id);
+            match explorer_action {
+                super::ExplorerAction::Arrive(_) | super::ExplorerAction::Move { .. } if inside => {
+                    cache.push(explorer);
+                    Ok(Some(super::ExplorationFieldEvents {
+                        explorer_actions: vec![explorer_action],
+                        ..Default::default()
+                    }))
+                }
+                // 領域外へ移動した
+                super::ExplorerAction::Arrive(_) | super::ExplorerAction::Move { .. } if cached => {
+                    Ok(Some(super::ExplorationFieldEvents {
+                        removed_explorer_ids: vec![explorer.id],
+                        ..Default::default()
+                    }))
+                }
+                super::ExplorerAction::Leave(_) if inside || cached => {
+                    Ok(Some(super::ExplorationFieldEvents {
+                        explorer_actions: vec![explorer_action],
+                        ..Default::default()
+                    }))
+                }
+                _ => Ok(None),
+            }
+        }
+        crate::event::Event::SpeakerPhone(speaker_phone) => {
+            if is_covering(center, size, &speaker_phone) {
+                upsert_by_key(
+                    &mut status.old_area_speaker_phones_cache,
+                    speaker_phone.clone(),
+                    |s| s.id,
+                );
                 Ok(Some(super::ExplorationFieldEvents {
-                    messages: vec![],
-                    speaker_phones: vec![],
-                    reactions: vec![],
-                    explorer_actions: vec![explorer_action],
+                    speaker_phones: vec![speaker_phone],
+                    ..Default::default()
                 }))
             } else {
                 Ok(None)
             }
         }
-        crate::event::Event::SpeakerPhone(speaker_phone) => {
-            if is_inside(
-                status.explorer.position,
-                status.exploration_field_size,
-                speaker_phone.position,
-            ) {
+        crate::event::Event::SpeakerPhoneUpdated { after, .. } => {
+            let cache = &mut status.old_area_speaker_phones_cache;
+            let cached = cache.iter().any(|s| s.id == after.id);
+            if is_covering(center, size, &after) {
+                upsert_by_key(cache, after.clone(), |s| s.id);
                 Ok(Some(super::ExplorationFieldEvents {
-                    messages: vec![],
-                    speaker_phones: vec![speaker_phone],
-                    reactions: vec![],
-                    explorer_actions: vec![],
+                    speaker_phones: vec![after],
+                    ..Default::default()
+                }))
+            } else if cached {
+                // 領域外へ移動した
+                cache.retain(|s| s.id != after.id);
+                Ok(Some(super::ExplorationFieldEvents {
+                    removed_speaker_phone_ids: vec![after.id],
+                    ..Default::default()
+                }))
+            } else {
+                Ok(None)
+            }
+        }
+        crate::event::Event::SpeakerPhoneDeleted(speaker_phone) => {
+            let cache = &mut status.old_area_speaker_phones_cache;
+            let cached = cache.iter().any(|s| s.id == speaker_phone.id);
+            cache.retain(|s| s.id != speaker_phone.id);
+            if cached || is_covering(center, size, &speaker_phone) {
+                Ok(Some(super::ExplorationFieldEvents {
+                    removed_speaker_phone_ids: vec![speaker_phone.id],
+                    ..Default::default()
                 }))
             } else {
                 Ok(None)
             }
         }
-        crate::event::Event::Message(message) => {
-            if is_inside(
-                status.explorer.position,
-                status.exploration_field_size,
-                message.position,
-            ) {
+        crate::event::Event::Message(message) | crate::event::Event::MessageUpdated(message) => {
+            if is_inside(center, size, message.position) {
+                upsert_by_key(&mut status.old_area_messages_cache, message.clone(), |m| {
+                    m.id
+                });
                 Ok(Some(super::ExplorationFieldEvents {
                     messages: vec![message],
-                    speaker_phones: vec![],
-                    reactions: vec![],
-                    explorer_actions: vec![],
+                    ..Default::default()
+                }))
+            } else {
+                Ok(None)
+            }
+        }
+        crate::event::Event::MessageDeleted(message) => {
+            let cache = &mut status.old_area_messages_cache;
+            let cached = cache.iter().any(|m| m.id == message.id);
+            cache.retain(|m| m.id != message.id);
+            if cached || is_inside(center, size, message.position) {
+                Ok(Some(super::ExplorationFieldEvents {
+                    removed_message_ids: vec![message.id],
+                    ..Default::default()
+                }))
+            } else {
+                Ok(None)
+            }
+        }
+        crate::event::Event::MessageExpired(message) => {
+            let cache = &mut status.old_area_messages_cache;
+            let cached = cache.iter().any(|m| m.id == message.id);
+            cache.retain(|m| m.id != message.id);
+            if cached || is_inside(center, size, message.position) {
+                Ok(Some(super::ExplorationFieldEvents {
+                    expired_message_ids: vec![message.id],
+                    ..Default::default()
+                }))
+            } else {
+                Ok(None)
+            }
+        }
+        crate::event::Event::ReactionExpired(reaction) => {
+            let cache = &mut status.old_area_reactions_cache;
+            let cached = cache.iter().any(|r| r.id == reaction.id);
+            cache.retain(|r| r.id != reaction.id);
+            if cached || is_inside(center, size, reaction.position) {
+                Ok(Some(super::ExplorationFieldEvents {
+                    expired_reaction_ids: vec![reaction.id],
+                    ..Default::default()
                 }))
             } else {
                 Ok(None)
             }
         }
         crate::event::Event::Reaction(reaction) => {
-            if is_inside(
-                status.explorer.position,
-                status.exploration_field_size,
-                reaction.position,
-            ) {
+            if is_inside(center, size, reaction.position) {
+                upsert_by_key(
+                    &mut status.old_area_reactions_cache,
+                    reaction.clone(),
+                    |r| r.id,
+                );
                 Ok(Some(super::ExplorationFieldEvents {
-                    messages: vec![],
-                    speaker_phones: vec![],
                     reactions: vec![reaction],
-                    explorer_actions: vec![],
+                    ..Default::default()
                 }))
             } else {
                 Ok(None)
@@ -568,14 +869,21 @@ where
     }
 }
 
+/// SpeakerPhoneの受信範囲が領域と重なっているか
+fn is_covering(
+    center: crate::world::Coordinate,
+    size: crate::world::Size,
+    speaker_phone: &crate::speaker_phone::SpeakerPhone,
+) -> bool {
+    speaker_phone
+        .position
+        .is_circle_overlapping_rect(speaker_phone.receive_range, center, size)
+}
+
 fn is_inside(
     center: crate::world::Coordinate,
     size: crate::world::Size,
     position: crate::world::Coordinate,
 ) -> bool {
-    let x_min = center.x.saturating_sub(size.width >> 1);
-    let x_max = center.x.saturating_add(size.width >> 1);
-    let y_min = center.y.saturating_sub(size.height >> 1);
-    let y_max = center.y.saturating_add(size.height >> 1);
-    x_min < position.x && position.x < x_max && y_min < position.y && position.y < y_max
+    position.is_inside_rect(center, size)
 }
@@ -1,8 +1,12 @@
+pub mod api_token;
+pub mod dev_auth;
 pub mod event;
 pub mod explore;
 pub mod message;
+pub mod notification;
 pub mod prelude;
 pub mod reaction;
+pub mod retention;
 pub mod router;
 pub mod session;
 pub mod speaker_phone;
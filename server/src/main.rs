@@ -11,15 +11,25 @@ struct State {
     task_manager: lib::task::TaskManager,
     world_size: lib::world::WorldSize,
     event_channels: lib::event::EventChannels,
+    notification_channels: lib::notification::NotificationChannels,
     client: reqwest::Client,
     session_config: SessionConfig,
     explorer_store: lib::explore::ExplorerStore,
+    movement_limits: lib::explore::MovementLimits,
+    speaker_phone_limits: lib::speaker_phone::SpeakerPhoneLimits,
+    speaker_phone_relays: lib::speaker_phone::SpeakerPhoneRelays,
     services: Services,
     traq_oauth_client_config: TraqOauthClientConfig,
+    traq_token_key: lib::traq::auth::TraqTokenKey,
     traq_host: lib::traq::TraqHost,
     traq_bot_config: lib::traq::bot::TraqBotConfig,
     traq_bot_channels: lib::traq::bot::TraqBotChannels,
     traq_channels_cache: lib::traq::channel::TraqChannelsCache,
+    reaction_kinds: lib::reaction::kind::ReactionKindRegistry,
+    sync_traq_stamps: bool,
+    retention_policy: lib::retention::RetentionPolicy,
+    message_search_backend: lib::message::MessageSearchBackend,
+    admins: lib::user::Admins,
     frontend_dist_dir: lib::router::FrontendDistDir,
 }
 
@@ -29,8 +39,12 @@ struct Services {
     event_service: lib::event::EventServiceImpl,
     user_service: lib::user::UserServiceImpl,
     session_service: lib::session::SessionServiceImpl,
+    dev_auth_service: lib::dev_auth::DevAuthServiceImpl,
+    api_token_service: lib::api_token::ApiTokenServiceImpl,
     message_service: lib::message::MessageServiceImpl,
+    notification_service: lib::notification::NotificationServiceImpl,
     reaction_service: lib::reaction::ReactionServiceImpl,
+    reaction_kind_service: lib::reaction::kind::ReactionKindServiceImpl,
     speaker_phone_service: lib::speaker_phone::SpeakerPhoneServiceImpl,
     explore_service: lib::explore::ExploreServiceImpl,
     explorer_service: lib::explore::ExplorerServiceImpl,
@@ -39,6 +53,7 @@ struct Services {
     traq_bot_service: lib::traq::bot::TraqBotServiceImpl,
     traq_channel_service: lib::traq::channel::TraqChannelServiceImpl,
     traq_message_service: lib::traq::message::TraqMessageServiceImpl,
+    retention_service: lib::retention::RetentionServiceImpl,
 }
 
 #[tokio::main]
@@ -56,26 +71,45 @@ async fn main() -> anyhow::Result<()> {
     let task_manager = lib::task::TaskManager::new();
     let world_size = load::world_size()?;
     let event_channels = load::event_channels()?;
+    let notification_channels = load::notification_channels()?;
+    let movement_limits = load::movement_limits()?;
+    let speaker_phone_limits = load::speaker_phone_limits()?;
     let client = reqwest::Client::new();
     let session_config = load::session_config()?;
     let traq_oauth_client_config = load::traq_oauth_client_config()?;
+    let traq_token_key = load::traq_token_key(session_config.auth_mode)?;
     let traq_host = load::traq_host()?;
     let traq_bot_config = load::traq_bot_config()?;
+    let reaction_kinds = load::reaction_kinds()?;
+    let sync_traq_stamps = load::sync_traq_stamps()?;
+    let retention_policy = load::retention_policy()?;
+    let message_search_backend = load::message_search_backend(&pool).await?;
+    let admins = load::admins();
     let frontend_dist_dir = load::frontend_dist_dir()?;
     let state = Arc::new(State {
         pool,
         task_manager,
         world_size,
         event_channels,
+        notification_channels,
         client,
         session_config,
         explorer_store: lib::explore::ExplorerStore::new(),
+        movement_limits,
+        speaker_phone_limits,
+        speaker_phone_relays: lib::speaker_phone::SpeakerPhoneRelays::default(),
         services: Services::default(),
         traq_oauth_client_config,
+        traq_token_key,
         traq_host,
         traq_bot_config,
         traq_bot_channels: lib::traq::bot::TraqBotChannels::default(),
         traq_channels_cache: Default::default(),
+        reaction_kinds,
+        sync_traq_stamps,
+        retention_policy,
+        message_search_backend,
+        admins,
         frontend_dist_dir,
     });
     state.migrate().await?;
@@ -98,6 +132,9 @@ struct SessionConfig {
     key: axum_extra::extract::cookie::Key,
     name: lib::session::SessionName,
     domain: lib::session::CookieDomain,
+    lifetime: lib::session::SessionLifetime,
+    secure: lib::session::CookieSecure,
+    auth_mode: lib::session::AuthMode,
 }
 
 mod load {
@@ -174,6 +211,63 @@ mod load {
         Ok(lib::event::EventChannels::new(capacity))
     }
 
+    /// 容量はイベントと同じ
+    pub fn notification_channels() -> anyhow::Result<lib::notification::NotificationChannels> {
+        let capacity = env_var!("EVENT_CHANNELS_CAPACITY")?
+            .parse()
+            .context("Failed to parse EVENT_CHANNELS_CAPACITY")?;
+        Ok(lib::notification::NotificationChannels::new(capacity))
+    }
+
+    pub fn movement_limits() -> anyhow::Result<lib::explore::MovementLimits> {
+        let max_speed = env_var!("EXPLORER_MAX_SPEED")?
+            .parse()
+            .context("Failed to parse EXPLORER_MAX_SPEED as u32")?;
+        let width = env_var!("EXPLORATION_FIELD_MAX_WIDTH")?
+            .parse()
+            .context("Failed to parse EXPLORATION_FIELD_MAX_WIDTH as u32")?;
+        let height = env_var!("EXPLORATION_FIELD_MAX_HEIGHT")?
+            .parse()
+            .context("Failed to parse EXPLORATION_FIELD_MAX_HEIGHT as u32")?;
+        Ok(lib::explore::MovementLimits {
+            max_speed,
+            max_field_size: lib::world::Size { width, height },
+        })
+    }
+
+    pub fn speaker_phone_limits() -> anyhow::Result<lib::speaker_phone::SpeakerPhoneLimits> {
+        let max_per_user = env_var!("SPEAKER_PHONE_MAX_PER_USER")?
+            .parse()
+            .context("Failed to parse SPEAKER_PHONE_MAX_PER_USER as u32")?;
+        let max_per_area = env_var!("SPEAKER_PHONE_MAX_PER_AREA")?
+            .parse()
+            .context("Failed to parse SPEAKER_PHONE_MAX_PER_AREA as u32")?;
+        let area_radius = env_var!("SPEAKER_PHONE_AREA_RADIUS")?
+            .parse()
+            .context("Failed to parse SPEAKER_PHONE_AREA_RADIUS as u32")?;
+        let min_receive_range = env_var!("SPEAKER_PHONE_MIN_RECEIVE_RANGE")?
+            .parse()
+            .context("Failed to parse SPEAKER_PHONE_MIN_RECEIVE_RANGE as u32")?;
+        let max_receive_range = env_var!("SPEAKER_PHONE_MAX_RECEIVE_RANGE")?
+            .parse()
+            .context("Failed to parse SPEAKER_PHONE_MAX_RECEIVE_RANGE as u32")?;
+        let default_receive_range = env_var!("SPEAKER_PHONE_DEFAULT_RECEIVE_RANGE")?
+            .parse()
+            .context("Failed to parse SPEAKER_PHONE_DEFAULT_RECEIVE_RANGE as u32")?;
+        anyhow::ensure!(
+            min_receive_range <= default_receive_range && default_receive_range <= max_receive_range,
+            "SPEAKER_PHONE_DEFAULT_RECEIVE_RANGE must be between SPEAKER_PHONE_MIN_RECEIVE_RANGE and SPEAKER_PHONE_MAX_RECEIVE_RANGE"
+        );
+        Ok(lib::speaker_phone::SpeakerPhoneLimits {
+            max_per_user,
+            max_per_area,
+            area_radius,
+            min_receive_range,
+            max_receive_range,
+            default_receive_range,
+        })
+    }
+
     #[tracing::instrument]
     pub fn session_config() -> anyhow::Result<SessionConfig> {
         use axum_extra::extract::cookie::Key as SessionKey;
@@ -191,14 +285,40 @@ mod load {
                 SessionKey::try_generate().context("Could not generate session key")
             })?;
         let name = env_var!("SESSION_NAME")?;
+        let auth_mode = auth_mode()?;
         let domain = env_var!("COOKIE_ATTR_DOMAIN")?;
+        // 既定は30日
+        let lifetime = std::env::var("SESSION_LIFETIME")
+            .ok()
+            .map(|l| l.parse().context("Failed to parse SESSION_LIFETIME"))
+            .transpose()?
+            .unwrap_or(60 * 60 * 24 * 30);
         Ok(SessionConfig {
             key,
             name: lib::session::SessionName(name),
             domain: lib::session::CookieDomain(domain),
+            lifetime: lib::session::SessionLifetime(lifetime),
+            // 開発用の認証はlocalhostに平文のHTTPで繋ぐ
+            secure: lib::session::CookieSecure(auth_mode != lib::session::AuthMode::Dev),
+            auth_mode,
         })
     }
 
+    /// `AUTH_MODE`は`traq`(既定)か`dev`
+    fn auth_mode() -> anyhow::Result<lib::session::AuthMode> {
+        use lib::session::AuthMode;
+
+        let mode = match std::env::var("AUTH_MODE").as_deref() {
+            Err(_) | Ok("traq") => AuthMode::Traq,
+            Ok("dev") => {
+                tracing::warn!("Dev auth is enabled; anyone can log in as any user");
+                AuthMode::Dev
+            }
+            Ok(mode) => anyhow::bail!("Unknown AUTH_MODE {mode}"),
+        };
+        Ok(mode)
+    }
+
     pub fn traq_oauth_client_config() -> anyhow::Result<lib::traq::auth::TraqOauthClientConfig> {
         let client_id = env_var!("TRAQ_OAUTH_CLIENT_ID")?;
         let client_secret = env_var!("TRAQ_OAUTH_CLIENT_SECRET")?;
@@ -208,6 +328,45 @@ mod load {
         })
     }
 
+    /// 開発用の認証でのみ省略でき、起動の度に生成するので再起動すると全員認可し直しになる
+    #[tracing::instrument]
+    pub fn traq_token_key(
+        auth_mode: lib::session::AuthMode,
+    ) -> anyhow::Result<lib::traq::auth::TraqTokenKey> {
+        use lib::traq::auth::TraqTokenKey;
+
+        let key = match std::env::var("TRAQ_TOKEN_KEY") {
+            Ok(k) => k,
+            Err(std::env::VarError::NotPresent) if auth_mode == lib::session::AuthMode::Dev => {
+                tracing::warn!("Generating traQ token key");
+                return Ok(TraqTokenKey::generate());
+            }
+            Err(e) => return Err(e).context("Failed to read TRAQ_TOKEN_KEY"),
+        };
+        let key = hex::decode(&key).context("Failed to decode TRAQ_TOKEN_KEY value as hex")?;
+        TraqTokenKey::new(&key).context("TRAQ_TOKEN_KEY must be 32 bytes")
+    }
+
+    /// MariaDBはngramパーサーに対応していないので、アプリで区切った本文のインデックスを使う
+    #[tracing::instrument(skip_all)]
+    pub async fn message_search_backend(
+        pool: &MySqlPool,
+    ) -> anyhow::Result<lib::message::MessageSearchBackend> {
+        use lib::message::MessageSearchBackend;
+
+        let (version,): (String,) = sqlx::query_as("SELECT VERSION()")
+            .fetch_one(pool)
+            .await
+            .context("Failed to get database version")?;
+        let backend = if version.contains("MariaDB") {
+            MessageSearchBackend::Bigram
+        } else {
+            MessageSearchBackend::Ngram
+        };
+        tracing::info!(version, ?backend, "Selected message search backend");
+        Ok(backend)
+    }
+
     pub fn traq_host() -> anyhow::Result<lib::traq::TraqHost> {
         let traq_host = env_var!("TRAQ_HOST")?;
         Ok(lib::traq::TraqHost(traq_host))
@@ -223,6 +382,48 @@ mod load {
         Ok(config)
     }
 
+    /// `REACTION_KINDS_FILE`が無ければクライアント同梱の種類のみを使う
+    pub fn reaction_kinds() -> anyhow::Result<lib::reaction::kind::ReactionKindRegistry> {
+        let Ok(path) = std::env::var("REACTION_KINDS_FILE") else {
+            return Ok(Default::default());
+        };
+        let json = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read REACTION_KINDS_FILE {path}"))?;
+        lib::reaction::kind::ReactionKindRegistry::from_json(&json)
+            .context("Failed to parse REACTION_KINDS_FILE")
+    }
+
+    pub fn sync_traq_stamps() -> anyhow::Result<bool> {
+        let Ok(v) = std::env::var("REACTION_KINDS_SYNC_TRAQ_STAMPS") else {
+            return Ok(false);
+        };
+        v.parse()
+            .context("Failed to parse REACTION_KINDS_SYNC_TRAQ_STAMPS as bool")
+    }
+
+    /// `RETENTION_POLICY_FILE`が無ければ既定の保持期間を使う
+    pub fn retention_policy() -> anyhow::Result<lib::retention::RetentionPolicy> {
+        let Ok(path) = std::env::var("RETENTION_POLICY_FILE") else {
+            return Ok(Default::default());
+        };
+        let json = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read RETENTION_POLICY_FILE {path}"))?;
+        lib::retention::RetentionPolicy::from_json(&json)
+            .context("Failed to parse RETENTION_POLICY_FILE")
+    }
+
+    /// `ADMIN_USER_NAMES`はカンマ区切りのユーザー名; 無ければ管理者はいない
+    pub fn admins() -> lib::user::Admins {
+        let names = std::env::var("ADMIN_USER_NAMES").unwrap_or_default();
+        let names = names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        lib::user::Admins(names)
+    }
+
     pub fn frontend_dist_dir() -> anyhow::Result<lib::router::FrontendDistDir> {
         let v = env_var!("FRONTEND_DIST_DIR")?;
         Ok(lib::router::FrontendDistDir(v))
@@ -261,6 +462,18 @@ impl State {
     }
 
     async fn graceful_shutdown(&self) -> anyhow::Result<()> {
+        use lib::explore::{ProvideExplorerService, SaveAllExplorerPositionsParams};
+
+        // 保存に失敗しても実行中のタスクは止める
+        if let Err(e) = self
+            .save_all_explorer_positions(SaveAllExplorerPositionsParams {})
+            .await
+        {
+            tracing::error!(
+                error = &e as &dyn std::error::Error,
+                "Failed to save explorer positions"
+            );
+        }
         let duration = std::time::Duration::from_secs(5);
         let fut = self.task_manager.graceful_shutdown();
         tokio::time::timeout(duration, fut).await?;
@@ -269,12 +482,34 @@ impl State {
 
     #[tracing::instrument(skip_all)]
     async fn load(self: Arc<Self>) -> anyhow::Result<()> {
+        use lib::message::{FillSearchIndexParams, ProvideMessageService};
+        use lib::reaction::kind::{ProvideReactionKindService, SyncTraqStampsParams};
+        use lib::retention::{RetentionService, StartJanitorParams};
         use lib::speaker_phone::{LoadAllSpeakerPhonesParams, SpeakerPhoneService};
 
+        if self.sync_traq_stamps {
+            // 同期に失敗しても設定済みの種類は使えるので起動は続ける
+            if let Err(e) = self.sync_traq_stamps(SyncTraqStampsParams {}).await {
+                tracing::warn!(
+                    error = &e as &dyn std::error::Error,
+                    "Failed to sync traQ stamps"
+                );
+            }
+        }
+
+        let filled = self.fill_search_index(FillSearchIndexParams {}).await?;
+        if filled > 0 {
+            tracing::info!(filled, "Filled message search index");
+        }
+
         self.services
             .speaker_phone_service
             .load_all_speaker_phones(Arc::clone(&self), LoadAllSpeakerPhonesParams {})
             .await?;
+        self.services
+            .retention_service
+            .start_janitor(Arc::clone(&self), StartJanitorParams {})
+            .await?;
         Ok(())
     }
 }
@@ -297,6 +532,54 @@ impl AsRef<lib::event::EventChannels> for State {
     }
 }
 
+impl AsRef<lib::notification::NotificationChannels> for State {
+    fn as_ref(&self) -> &lib::notification::NotificationChannels {
+        &self.notification_channels
+    }
+}
+
+impl AsRef<lib::reaction::kind::ReactionKindRegistry> for State {
+    fn as_ref(&self) -> &lib::reaction::kind::ReactionKindRegistry {
+        &self.reaction_kinds
+    }
+}
+
+impl AsRef<lib::message::MessageSearchBackend> for State {
+    fn as_ref(&self) -> &lib::message::MessageSearchBackend {
+        &self.message_search_backend
+    }
+}
+
+impl AsRef<lib::retention::RetentionPolicy> for State {
+    fn as_ref(&self) -> &lib::retention::RetentionPolicy {
+        &self.retention_policy
+    }
+}
+
+impl AsRef<lib::user::Admins> for State {
+    fn as_ref(&self) -> &lib::user::Admins {
+        &self.admins
+    }
+}
+
+impl AsRef<lib::explore::MovementLimits> for State {
+    fn as_ref(&self) -> &lib::explore::MovementLimits {
+        &self.movement_limits
+    }
+}
+
+impl AsRef<lib::speaker_phone::SpeakerPhoneLimits> for State {
+    fn as_ref(&self) -> &lib::speaker_phone::SpeakerPhoneLimits {
+        &self.speaker_phone_limits
+    }
+}
+
+impl AsRef<lib::speaker_phone::SpeakerPhoneRelays> for State {
+    fn as_ref(&self) -> &lib::speaker_phone::SpeakerPhoneRelays {
+        &self.speaker_phone_relays
+    }
+}
+
 impl AsRef<reqwest::Client> for State {
     fn as_ref(&self) -> &reqwest::Client {
         &self.client
@@ -321,12 +604,36 @@ impl AsRef<lib::session::CookieDomain> for State {
     }
 }
 
+impl AsRef<lib::session::CookieSecure> for State {
+    fn as_ref(&self) -> &lib::session::CookieSecure {
+        &self.session_config.secure
+    }
+}
+
+impl AsRef<lib::session::AuthMode> for State {
+    fn as_ref(&self) -> &lib::session::AuthMode {
+        &self.session_config.auth_mode
+    }
+}
+
+impl AsRef<lib::session::SessionLifetime> for State {
+    fn as_ref(&self) -> &lib::session::SessionLifetime {
+        &self.session_config.lifetime
+    }
+}
+
 impl AsRef<lib::explore::ExplorerStore> for State {
     fn as_ref(&self) -> &lib::explore::ExplorerStore {
         &self.explorer_store
     }
 }
 
+impl AsRef<lib::traq::auth::TraqTokenKey> for State {
+    fn as_ref(&self) -> &lib::traq::auth::TraqTokenKey {
+        &self.traq_token_key
+    }
+}
+
 impl AsRef<TraqOauthClientConfig> for State {
     fn as_ref(&self) -> &TraqOauthClientConfig {
         &self.traq_oauth_client_config
@@ -401,6 +708,30 @@ impl lib::user::ProvideUserService for State {
     }
 }
 
+impl lib::dev_auth::ProvideDevAuthService for State {
+    type Context = Self;
+    type DevAuthService = lib::dev_auth::DevAuthServiceImpl;
+
+    fn context(&self) -> &Self::Context {
+        self
+    }
+    fn dev_auth_service(&self) -> &Self::DevAuthService {
+        &self.services.dev_auth_service
+    }
+}
+
+impl lib::api_token::ProvideApiTokenService for State {
+    type Context = Self;
+    type ApiTokenService = lib::api_token::ApiTokenServiceImpl;
+
+    fn context(&self) -> &Self::Context {
+        self
+    }
+    fn api_token_service(&self) -> &Self::ApiTokenService {
+        &self.services.api_token_service
+    }
+}
+
 impl lib::session::ProvideSessionService for State {
     type Context = Self;
     type SessionService = lib::session::SessionServiceImpl;
@@ -425,6 +756,18 @@ impl lib::message::ProvideMessageService for State {
     }
 }
 
+impl lib::notification::ProvideNotificationService for State {
+    type Context = Self;
+    type NotificationService = lib::notification::NotificationServiceImpl;
+
+    fn context(&self) -> &Self::Context {
+        self
+    }
+    fn notification_service(&self) -> &Self::NotificationService {
+        &self.services.notification_service
+    }
+}
+
 impl lib::reaction::ProvideReactionService for State {
     type Context = Self;
     type ReactionService = lib::reaction::ReactionServiceImpl;
@@ -437,6 +780,18 @@ impl lib::reaction::ProvideReactionService for State {
     }
 }
 
+impl lib::reaction::kind::ProvideReactionKindService for State {
+    type Context = Self;
+    type ReactionKindService = lib::reaction::kind::ReactionKindServiceImpl;
+
+    fn context(&self) -> &Self::Context {
+        self
+    }
+    fn reaction_kind_service(&self) -> &Self::ReactionKindService {
+        &self.services.reaction_kind_service
+    }
+}
+
 impl lib::speaker_phone::ProvideSpeakerPhoneService for State {
     type Context = Self;
     type SpeakerPhoneService = lib::speaker_phone::SpeakerPhoneServiceImpl;
@@ -22,6 +22,8 @@ pub struct MessageId(pub uuid::Uuid);
 pub struct Message {
     pub id: MessageId,
     pub user_id: crate::user::UserId,
+    /// 返信先; スレッドの起点のメッセージを指す
+    pub parent_id: Option<MessageId>,
     pub position: crate::world::Coordinate,
     pub content: String,
     pub created_at: Timestamp,
@@ -42,9 +44,129 @@ pub struct GetMessagesInAreaParams {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct CreateMessageParams {
+    /// 指定しなければ生成する; 公開前に外部との対応付けを保存するため
+    pub id: Option<MessageId>,
     pub user_id: crate::user::UserId,
     pub position: crate::world::Coordinate,
     pub content: String,
+    pub parent_id: Option<MessageId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct UpdateMessageParams {
+    pub id: MessageId,
+    /// 編集するユーザー; 送信者でなければならない
+    pub user_id: crate::user::UserId,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct DeleteMessageParams {
+    pub id: MessageId,
+    /// 削除するユーザー; 送信者でなければならない
+    pub user_id: crate::user::UserId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct GetMessageThreadParams {
+    /// スレッド内のいずれかのメッセージ
+    pub id: MessageId,
+    /// 閲覧するユーザー; 期限切れのメッセージは送信者と管理者のみが見られる
+    pub user_id: crate::user::UserId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct MessageThread {
+    pub root: Message,
+    /// 古い順
+    pub replies: Vec<Message>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct GetMessageHistoryParams {
+    /// 閲覧するユーザー; 期限切れのメッセージは送信者と管理者のみが見られる
+    pub user_id: crate::user::UserId,
+    pub center: crate::world::Coordinate,
+    pub size: crate::world::Size,
+    /// この日時以降に送信されたもの
+    pub since: Timestamp,
+    /// この日時より前に送信されたもの
+    pub until: Timestamp,
+    /// 前のページの`next_cursor`; このIDより後のメッセージから返す
+    pub cursor: Option<MessageId>,
+    pub limit: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct MessageHistory {
+    /// 古い順
+    pub messages: Vec<Message>,
+    /// 続きがあれば次のページのカーソル
+    pub next_cursor: Option<MessageId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct SearchArea {
+    pub center: crate::world::Coordinate,
+    pub size: crate::world::Size,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct SearchMessagesParams {
+    /// 検索するユーザー; 期限切れのメッセージは送信者と管理者のみが見られる
+    pub user_id: crate::user::UserId,
+    /// 本文に含まれる文字列
+    pub query: String,
+    /// 送信者で絞り込む
+    pub author_id: Option<crate::user::UserId>,
+    /// この日時以降に送信されたもの
+    pub since: Option<Timestamp>,
+    /// この日時より前に送信されたもの
+    pub until: Option<Timestamp>,
+    pub area: Option<SearchArea>,
+    /// 前のページの`next_cursor`; このIDより前のメッセージから返す
+    pub cursor: Option<MessageId>,
+    pub limit: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct MessageSearchResult {
+    /// 新しい順
+    pub messages: Vec<Message>,
+    /// 続きがあれば次のページのカーソル
+    pub next_cursor: Option<MessageId>,
+}
+
+/// メッセージの全文検索に使うインデックス; 起動時にデータベースから判定する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum MessageSearchBackend {
+    /// ngramパーサーで作った`content`のインデックス(MySQL)
+    Ngram,
+    /// アプリで2文字ずつに区切った`content_bigram`のインデックス(MariaDB)
+    Bigram,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct FillSearchIndexParams {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct MarkMessageRelayedParams {
+    pub id: MessageId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct GetExpiredMessagesParams {
+    /// この日時より後に期限切れになったもの
+    pub since: Timestamp,
+    /// この日時以前に期限切れになったもの
+    pub until: Timestamp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct PurgeExpiredMessagesParams {
+    /// この日時以前に期限切れ、または論理削除されたもの
+    pub expired_before: Timestamp,
+    pub limit: u32,
 }
 
 pub trait MessageService<Context>: Send + Sync + 'static {
@@ -65,6 +187,58 @@ pub trait MessageService<Context>: Send + Sync + 'static {
         ctx: &'a Context,
         params: CreateMessageParams,
     ) -> BoxFuture<'a, Result<Message, Self::Error>>;
+    fn get_message_thread<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: GetMessageThreadParams,
+    ) -> BoxFuture<'a, Result<MessageThread, Self::Error>>;
+    /// 内容を更新して, `Event::MessageUpdated`を発行する
+    fn update_message<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: UpdateMessageParams,
+    ) -> BoxFuture<'a, Result<Message, Self::Error>>;
+    /// 論理削除して, `Event::MessageDeleted`を発行する
+    fn delete_message<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: DeleteMessageParams,
+    ) -> BoxFuture<'a, Result<Message, Self::Error>>;
+    /// 領域内で期間中に送信されたメッセージを、IDの順にページングして取得する
+    fn get_message_history<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: GetMessageHistoryParams,
+    ) -> BoxFuture<'a, Result<MessageHistory, Self::Error>>;
+    /// 本文を全文検索する
+    fn search_messages<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: SearchMessagesParams,
+    ) -> BoxFuture<'a, Result<MessageSearchResult, Self::Error>>;
+    /// 検索用に区切った本文が無いメッセージを埋めて、その件数を返す
+    fn fill_search_index<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: FillSearchIndexParams,
+    ) -> BoxFuture<'a, Result<u64, Self::Error>>;
+    /// traQと同期されたメッセージの保持期間に更新する; イベントは発行しない
+    fn mark_message_relayed<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: MarkMessageRelayedParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>>;
+    fn get_expired_messages<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: GetExpiredMessagesParams,
+    ) -> BoxFuture<'a, Result<Vec<Message>, Self::Error>>;
+    /// 削除した件数を返す
+    fn purge_expired_messages<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: PurgeExpiredMessagesParams,
+    ) -> BoxFuture<'a, Result<u64, Self::Error>>;
 }
 
 #[allow(clippy::type_complexity)]
@@ -105,6 +279,90 @@ pub trait ProvideMessageService: Send + Sync + 'static {
         let ctx = self.context();
         self.message_service().get_messages_in_area(ctx, params)
     }
+    fn get_message_thread(
+        &self,
+        params: GetMessageThreadParams,
+    ) -> BoxFuture<
+        '_,
+        Result<MessageThread, <Self::MessageService as MessageService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.message_service().get_message_thread(ctx, params)
+    }
+    fn update_message(
+        &self,
+        params: UpdateMessageParams,
+    ) -> BoxFuture<
+        '_,
+        Result<Message, <Self::MessageService as MessageService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.message_service().update_message(ctx, params)
+    }
+    fn delete_message(
+        &self,
+        params: DeleteMessageParams,
+    ) -> BoxFuture<
+        '_,
+        Result<Message, <Self::MessageService as MessageService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.message_service().delete_message(ctx, params)
+    }
+    fn get_message_history(
+        &self,
+        params: GetMessageHistoryParams,
+    ) -> BoxFuture<
+        '_,
+        Result<MessageHistory, <Self::MessageService as MessageService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.message_service().get_message_history(ctx, params)
+    }
+    fn search_messages(
+        &self,
+        params: SearchMessagesParams,
+    ) -> BoxFuture<
+        '_,
+        Result<MessageSearchResult, <Self::MessageService as MessageService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.message_service().search_messages(ctx, params)
+    }
+    fn fill_search_index(
+        &self,
+        params: FillSearchIndexParams,
+    ) -> BoxFuture<'_, Result<u64, <Self::MessageService as MessageService<Self::Context>>::Error>>
+    {
+        let ctx = self.context();
+        self.message_service().fill_search_index(ctx, params)
+    }
+    fn mark_message_relayed(
+        &self,
+        params: MarkMessageRelayedParams,
+    ) -> BoxFuture<'_, Result<(), <Self::MessageService as MessageService<Self::Context>>::Error>>
+    {
+        let ctx = self.context();
+        self.message_service().mark_message_relayed(ctx, params)
+    }
+    fn get_expired_messages(
+        &self,
+        params: GetExpiredMessagesParams,
+    ) -> BoxFuture<
+        '_,
+        Result<Vec<Message>, <Self::MessageService as MessageService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.message_service().get_expired_messages(ctx, params)
+    }
+    fn purge_expired_messages(
+        &self,
+        params: PurgeExpiredMessagesParams,
+    ) -> BoxFuture<'_, Result<u64, <Self::MessageService as MessageService<Self::Context>>::Error>>
+    {
+        let ctx = self.context();
+        self.message_service().purge_expired_messages(ctx, params)
+    }
 }
 
 pub fn build_server<State>(state: Arc<State>) -> MessageServiceServer<State>
@@ -4,6 +4,14 @@ pub enum Error {
     NotFound,
     #[error("Message not in world")]
     MessageNotInWorld,
+    #[error("Parent message not found")]
+    ParentNotFound,
+    #[error("Only the author can modify the message")]
+    Forbidden,
+    #[error("Bad time range provided")]
+    BadTimeRange,
+    #[error("Empty search query")]
+    EmptyQuery,
     #[error("Database error")]
     Sqlx(#[from] sqlx::Error),
     #[error(transparent)]
@@ -15,6 +23,12 @@ impl From<Error> for tonic::Status {
         match value {
             Error::NotFound => tonic::Status::not_found("Not found"),
             Error::MessageNotInWorld => tonic::Status::invalid_argument("Message not in world"),
+            Error::ParentNotFound => tonic::Status::invalid_argument("Parent message not found"),
+            Error::Forbidden => {
+                tonic::Status::permission_denied("Only the author can modify the message")
+            }
+            Error::BadTimeRange => tonic::Status::invalid_argument("Bad time range provided"),
+            Error::EmptyQuery => tonic::Status::invalid_argument("Empty search query"),
             Error::Sqlx(e) => {
                 tracing::error!(error = &e as &dyn std::error::Error);
                 tonic::Status::internal("Database error")
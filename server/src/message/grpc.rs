@@ -11,6 +11,7 @@ impl From<super::Message> for schema::msg::Message {
         let super::Message {
             id,
             user_id,
+            parent_id,
             position,
             content,
             created_at,
@@ -20,6 +21,7 @@ impl From<super::Message> for schema::msg::Message {
         Self {
             id: id.0.to_string(),
             user_id: user_id.0.to_string(),
+            parent_id: parent_id.map(|id| id.0.to_string()),
             position: Some(position.into()),
             content,
             created_at: Some(created_at.into()),
@@ -29,6 +31,18 @@ impl From<super::Message> for schema::msg::Message {
     }
 }
 
+/// `From<prost_types::Timestamp>`は不正な値でpanicするので、リクエストの値はこれで変換する
+fn parse_timestamp(
+    value: prost_types::Timestamp,
+) -> Result<crate::prelude::Timestamp, tonic::Status> {
+    let prost_types::Timestamp { seconds, nanos } = value;
+    u32::try_from(nanos)
+        .ok()
+        .and_then(|nanos| chrono::DateTime::from_timestamp(seconds, nanos))
+        .map(crate::prelude::Timestamp)
+        .ok_or_else(|| tonic::Status::invalid_argument("Invalid timestamp"))
+}
+
 // MARK: ServiceImpl
 
 pub struct ServiceImpl<State> {
@@ -93,6 +107,7 @@ where
             schema::msg::CreateMessageRequest {
                 content,
                 position: Some(position),
+                parent_id,
             },
         ) = request.into_parts()
         else {
@@ -107,10 +122,16 @@ where
             .map_err(IntoStatus::into_status)?
             .user_id;
 
+        let parent_id = parent_id
+            .map(|id| Uuid::parse_str(&id).map(super::MessageId))
+            .transpose()
+            .map_err(|_| tonic::Status::invalid_argument("Invalid UUID"))?;
         let params = super::CreateMessageParams {
+            id: None,
             user_id,
             position: position.into(),
             content,
+            parent_id,
         };
 
         let message = self
@@ -125,4 +146,217 @@ where
 
         Ok(tonic::Response::new(res))
     }
+
+    async fn get_message_thread(
+        &self,
+        request: tonic::Request<schema::msg::GetMessageThreadRequest>,
+    ) -> Result<tonic::Response<schema::msg::GetMessageThreadResponse>, tonic::Status> {
+        let (meta, _, schema::msg::GetMessageThreadRequest { id }) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let params = super::GetMessageThreadParams {
+            id: super::MessageId(
+                Uuid::parse_str(&id)
+                    .map_err(|_| tonic::Status::invalid_argument("Invalid UUID"))?,
+            ),
+            user_id,
+        };
+        let super::MessageThread { root, replies } = self
+            .state
+            .get_message_thread(params)
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let res = schema::msg::GetMessageThreadResponse {
+            root: Some(root.into()),
+            replies: replies.into_iter().map(Into::into).collect(),
+        };
+        Ok(tonic::Response::new(res))
+    }
+
+    async fn update_message(
+        &self,
+        request: tonic::Request<schema::msg::UpdateMessageRequest>,
+    ) -> Result<tonic::Response<schema::msg::UpdateMessageResponse>, tonic::Status> {
+        let (meta, _, schema::msg::UpdateMessageRequest { id, content }) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let params = super::UpdateMessageParams {
+            id: super::MessageId(
+                Uuid::parse_str(&id)
+                    .map_err(|_| tonic::Status::invalid_argument("Invalid UUID"))?,
+            ),
+            user_id,
+            content,
+        };
+        let message = self
+            .state
+            .update_message(params)
+            .await
+            .map_err(IntoStatus::into_status)?
+            .into();
+        let res = schema::msg::UpdateMessageResponse {
+            message: Some(message),
+        };
+        Ok(tonic::Response::new(res))
+    }
+
+    async fn delete_message(
+        &self,
+        request: tonic::Request<schema::msg::DeleteMessageRequest>,
+    ) -> Result<tonic::Response<schema::msg::DeleteMessageResponse>, tonic::Status> {
+        let (meta, _, schema::msg::DeleteMessageRequest { id }) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let params = super::DeleteMessageParams {
+            id: super::MessageId(
+                Uuid::parse_str(&id)
+                    .map_err(|_| tonic::Status::invalid_argument("Invalid UUID"))?,
+            ),
+            user_id,
+        };
+        self.state
+            .delete_message(params)
+            .await
+            .map_err(IntoStatus::into_status)?;
+        Ok(tonic::Response::new(schema::msg::DeleteMessageResponse {}))
+    }
+
+    async fn get_message_history(
+        &self,
+        request: tonic::Request<schema::msg::GetMessageHistoryRequest>,
+    ) -> Result<tonic::Response<schema::msg::GetMessageHistoryResponse>, tonic::Status> {
+        let (
+            meta,
+            _,
+            schema::msg::GetMessageHistoryRequest {
+                position: Some(position),
+                size: Some(size),
+                since: Some(since),
+                until: Some(until),
+                cursor,
+                limit,
+            },
+        ) = request.into_parts()
+        else {
+            return Err(tonic::Status::invalid_argument("Invalid request"));
+        };
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let cursor = cursor
+            .map(|id| Uuid::parse_str(&id).map(super::MessageId))
+            .transpose()
+            .map_err(|_| tonic::Status::invalid_argument("Invalid cursor"))?;
+        let params = super::GetMessageHistoryParams {
+            user_id,
+            center: position.into(),
+            size: size.into(),
+            since: parse_timestamp(since)?,
+            until: parse_timestamp(until)?,
+            cursor,
+            limit,
+        };
+        let super::MessageHistory {
+            messages,
+            next_cursor,
+        } = self
+            .state
+            .get_message_history(params)
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let res = schema::msg::GetMessageHistoryResponse {
+            messages: messages.into_iter().map(Into::into).collect(),
+            next_cursor: next_cursor.map(|id| id.0.to_string()),
+        };
+        Ok(tonic::Response::new(res))
+    }
+
+    async fn search_messages(
+        &self,
+        request: tonic::Request<schema::msg::SearchMessagesRequest>,
+    ) -> Result<tonic::Response<schema::msg::SearchMessagesResponse>, tonic::Status> {
+        let (
+            meta,
+            _,
+            schema::msg::SearchMessagesRequest {
+                query,
+                author_id,
+                since,
+                until,
+                position,
+                size,
+                cursor,
+                limit,
+            },
+        ) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let author_id = author_id
+            .map(|id| Uuid::parse_str(&id).map(crate::user::UserId))
+            .transpose()
+            .map_err(|_| tonic::Status::invalid_argument("Invalid UUID"))?;
+        let area = match (position, size) {
+            (Some(position), Some(size)) => Some(super::SearchArea {
+                center: position.into(),
+                size: size.into(),
+            }),
+            (None, None) => None,
+            _ => {
+                return Err(tonic::Status::invalid_argument(
+                    "position and size must be specified together",
+                ))
+            }
+        };
+        let cursor = cursor
+            .map(|id| Uuid::parse_str(&id).map(super::MessageId))
+            .transpose()
+            .map_err(|_| tonic::Status::invalid_argument("Invalid cursor"))?;
+        let params = super::SearchMessagesParams {
+            user_id,
+            query,
+            author_id,
+            since: since.map(parse_timestamp).transpose()?,
+            until: until.map(parse_timestamp).transpose()?,
+            area,
+            cursor,
+            limit,
+        };
+        let super::MessageSearchResult {
+            messages,
+            next_cursor,
+        } = self
+            .state
+            .search_messages(params)
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let res = schema::msg::SearchMessagesResponse {
+            messages: messages.into_iter().map(Into::into).collect(),
+            next_cursor: next_cursor.map(|id| id.0.to_string()),
+        };
+        Ok(tonic::Response::new(res))
+    }
 }
@@ -5,15 +5,21 @@ use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, MySqlPool};
 use uuid::Uuid;
 
-// TODO: 値はてきとう
-fn calculate_expires_at(created_at: DateTime<Utc>) -> DateTime<Utc> {
-    created_at + chrono::Duration::days(1)
-}
+/// 返信を配置できる、スレッドの起点からの最大距離
+const REPLY_MAX_DISTANCE: u32 = 50;
+
+/// 履歴・検索結果の1ページの最大件数
+const PAGE_MAX_LIMIT: u32 = 100;
 
 impl<Context> super::MessageService<Context> for super::MessageServiceImpl
 where
-    Context:
-        AsRef<MySqlPool> + crate::event::ProvideEventService + crate::world::ProvideWorldService,
+    Context: AsRef<MySqlPool>
+        + AsRef<crate::retention::RetentionPolicy>
+        + AsRef<super::MessageSearchBackend>
+        + crate::event::ProvideEventService
+        + crate::notification::ProvideNotificationService
+        + crate::user::ProvideUserService
+        + crate::world::ProvideWorldService,
 {
     type Error = super::Error;
 
@@ -42,8 +48,91 @@ where
     ) -> futures::future::BoxFuture<'a, Result<super::Message, Self::Error>> {
         let event_service = ctx;
         let world_service = ctx;
+        let notification_service = ctx;
+        let policy = ctx.as_ref();
         let pool = ctx.as_ref();
-        create_message(event_service, world_service, pool, params).boxed()
+        create_message(
+            event_service,
+            world_service,
+            notification_service,
+            policy,
+            pool,
+            params,
+        )
+        .boxed()
+    }
+
+    fn get_message_thread<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::GetMessageThreadParams,
+    ) -> futures::future::BoxFuture<'a, Result<super::MessageThread, Self::Error>> {
+        get_message_thread(ctx, ctx.as_ref(), params).boxed()
+    }
+
+    fn update_message<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::UpdateMessageParams,
+    ) -> futures::future::BoxFuture<'a, Result<super::Message, Self::Error>> {
+        update_message(ctx, ctx, ctx.as_ref(), ctx.as_ref(), params).boxed()
+    }
+
+    fn delete_message<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::DeleteMessageParams,
+    ) -> futures::future::BoxFuture<'a, Result<super::Message, Self::Error>> {
+        delete_message(ctx, ctx.as_ref(), params).boxed()
+    }
+
+    fn get_message_history<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::GetMessageHistoryParams,
+    ) -> futures::future::BoxFuture<'a, Result<super::MessageHistory, Self::Error>> {
+        get_message_history(ctx, ctx.as_ref(), params).boxed()
+    }
+
+    fn search_messages<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::SearchMessagesParams,
+    ) -> futures::future::BoxFuture<'a, Result<super::MessageSearchResult, Self::Error>> {
+        let backend: &super::MessageSearchBackend = ctx.as_ref();
+        search_messages(ctx, ctx.as_ref(), *backend, params).boxed()
+    }
+
+    fn fill_search_index<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::FillSearchIndexParams,
+    ) -> futures::future::BoxFuture<'a, Result<u64, Self::Error>> {
+        fill_search_index(ctx.as_ref(), params).boxed()
+    }
+
+    fn mark_message_relayed<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::MarkMessageRelayedParams,
+    ) -> futures::future::BoxFuture<'a, Result<(), Self::Error>> {
+        mark_message_relayed(ctx.as_ref(), ctx.as_ref(), params).boxed()
+    }
+
+    fn get_expired_messages<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::GetExpiredMessagesParams,
+    ) -> futures::future::BoxFuture<'a, Result<Vec<super::Message>, Self::Error>> {
+        get_expired_messages(ctx.as_ref(), params).boxed()
+    }
+
+    fn purge_expired_messages<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::PurgeExpiredMessagesParams,
+    ) -> futures::future::BoxFuture<'a, Result<u64, Self::Error>> {
+        purge_expired_messages(ctx.as_ref(), params).boxed()
     }
 }
 
@@ -53,18 +142,21 @@ where
 pub struct MessageRow {
     pub id: Uuid,
     pub user_id: Uuid,
+    pub parent_id: Option<Uuid>,
     pub content: String,
     pub position_x: i32,
     pub position_y: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 impl From<MessageRow> for super::Message {
     fn from(row: MessageRow) -> Self {
         Self {
             id: super::MessageId(row.id),
             user_id: crate::user::UserId(row.user_id),
+            parent_id: row.parent_id.map(super::MessageId),
             position: crate::world::Coordinate {
                 x: row.position_x as u32,
                 y: row.position_y as u32,
@@ -82,13 +174,15 @@ async fn get_message(
     params: super::GetMessageParams,
 ) -> Result<super::Message, super::Error> {
     let super::GetMessageParams { id } = params;
-    sqlx::query_as::<_, MessageRow>("SELECT * FROM `messages` WHERE `id` = ?")
-        .bind(id.0)
-        .fetch_optional(pool)
-        .await
-        .map_err(super::Error::Sqlx)?
-        .ok_or(super::Error::NotFound)
-        .map(|row| row.into())
+    sqlx::query_as::<_, MessageRow>(
+        "SELECT * FROM `messages` WHERE `id` = ? AND `deleted_at` IS NULL",
+    )
+    .bind(id.0)
+    .fetch_optional(pool)
+    .await
+    .map_err(super::Error::Sqlx)?
+    .ok_or(super::Error::NotFound)
+    .map(|row| row.into())
 }
 
 async fn get_messages_in_area(
@@ -104,6 +198,8 @@ async fn get_messages_in_area(
                 `position_y` BETWEEN ? AND ?
             AND
                 `expires_at` > NOW()
+            AND
+                `deleted_at` IS NULL
             ORDER BY `created_at` DESC
         "#,
     )
@@ -120,12 +216,34 @@ async fn get_messages_in_area(
 async fn create_message<
     P: crate::event::ProvideEventService,
     W: crate::world::ProvideWorldService,
+    N: crate::notification::ProvideNotificationService,
 >(
     event_service: &P,
     world_service: &W,
+    notification_service: &N,
+    policy: &crate::retention::RetentionPolicy,
     pool: &MySqlPool,
-    params: super::CreateMessageParams,
+    mut params: super::CreateMessageParams,
 ) -> Result<super::Message, super::Error> {
+    // 返信はスレッドの起点にまとめ、起点の近くに配置する
+    if let Some(parent_id) = params.parent_id {
+        let parent = match get_message(pool, super::GetMessageParams { id: parent_id }).await {
+            Ok(parent) => parent,
+            Err(super::Error::NotFound) => return Err(super::Error::ParentNotFound),
+            Err(e) => return Err(e),
+        };
+        let root_id = parent.parent_id.unwrap_or(parent.id);
+        let root = if root_id == parent.id {
+            parent
+        } else {
+            get_message(pool, super::GetMessageParams { id: root_id }).await?
+        };
+        params.parent_id = Some(root.id);
+        params.position = root
+            .position
+            .step_towards(params.position, REPLY_MAX_DISTANCE as f64);
+    }
+
     // check if the position is in the world.
     if let crate::world::CheckCoordinateAnswer::Invalid = world_service
         .check_coordinate(crate::world::CheckCoordinateParams {
@@ -137,14 +255,19 @@ async fn create_message<
         return Err(super::Error::MessageNotInWorld);
     }
 
-    let id = Uuid::now_v7();
-    sqlx::query("INSERT INTO `messages` (`id`, `user_id`, `content`, `position_x`, `position_y`, `expires_at`) VALUES (?, ?, ?, ?, ?, ?)")
+    let id = params.id.map_or_else(Uuid::now_v7, |id| id.0);
+    let now = Utc::now();
+    let expires_at = policy.messages.expires_at(now, &params.content, false);
+    sqlx::query("INSERT INTO `messages` (`id`, `user_id`, `parent_id`, `content`, `content_bigram`, `position_x`, `position_y`, `created_at`, `expires_at`) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")
         .bind(id)
         .bind(params.user_id.0)
-        .bind(params.content)
+        .bind(params.parent_id.map(|id| id.0))
+        .bind(&params.content)
+        .bind(bigram_content(&params.content))
         .bind(params.position.x as i32)
         .bind(params.position.y as i32)
-        .bind(calculate_expires_at(Utc::now()))
+        .bind(now)
+        .bind(expires_at)
         .execute(pool)
         .await
         .map_err(super::Error::Sqlx)?;
@@ -162,5 +285,590 @@ async fn create_message<
         .await
         .map_err(crate::prelude::IntoStatus::into_status)?;
 
+    let mentions = extract_mentions(&message.content);
+    notify_mentions(notification_service, pool, &message, mentions).await;
+
     Ok(message)
 }
+
+async fn get_message_thread<U: crate::user::ProvideUserService>(
+    user_service: &U,
+    pool: &MySqlPool,
+    params: super::GetMessageThreadParams,
+) -> Result<super::MessageThread, super::Error> {
+    let super::GetMessageThreadParams { id, user_id } = params;
+    let admin = user_service
+        .check_admin(crate::user::CheckAdminParams { id: user_id })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    // 期限切れのメッセージは送信者と管理者のみが見られる
+    let visible = |message: &super::Message| {
+        message.expires_at.0 > Utc::now() || message.user_id == user_id || admin
+    };
+
+    let message = get_message(pool, super::GetMessageParams { id }).await?;
+    if !visible(&message) {
+        return Err(super::Error::NotFound);
+    }
+    let root = match message.parent_id {
+        Some(root_id) => get_message(pool, super::GetMessageParams { id: root_id }).await?,
+        None => message,
+    };
+    if !visible(&root) {
+        return Err(super::Error::NotFound);
+    }
+    let replies = sqlx::query_as::<_, MessageRow>(
+        r#"
+            SELECT * FROM `messages`
+            WHERE
+                `parent_id` = ? AND `deleted_at` IS NULL
+            AND
+                (`expires_at` > NOW() OR `user_id` = ? OR ?)
+            ORDER BY `created_at` ASC, `id` ASC
+        "#,
+    )
+    .bind(root.id.0)
+    .bind(user_id.0)
+    .bind(admin)
+    .fetch_all(pool)
+    .await
+    .map_err(super::Error::Sqlx)?
+    .into_iter()
+    .map(Into::into)
+    .collect();
+    Ok(super::MessageThread { root, replies })
+}
+
+/// 送信者本人のメッセージを取得する
+async fn get_own_message(
+    pool: &MySqlPool,
+    id: super::MessageId,
+    user_id: crate::user::UserId,
+) -> Result<super::Message, super::Error> {
+    let message = get_message(pool, super::GetMessageParams { id }).await?;
+    if message.user_id != user_id {
+        return Err(super::Error::Forbidden);
+    }
+    Ok(message)
+}
+
+async fn update_message<
+    P: crate::event::ProvideEventService,
+    N: crate::notification::ProvideNotificationService,
+>(
+    event_service: &P,
+    notification_service: &N,
+    policy: &crate::retention::RetentionPolicy,
+    pool: &MySqlPool,
+    params: super::UpdateMessageParams,
+) -> Result<super::Message, super::Error> {
+    let super::UpdateMessageParams {
+        id,
+        user_id,
+        content,
+    } = params;
+    let before = get_own_message(pool, id, user_id).await?;
+    // 保持期間は本文の長さで変わる
+    let relayed = is_relayed(pool, id).await?;
+    let expires_at = policy
+        .messages
+        .expires_at(before.created_at.0, &content, relayed);
+    sqlx::query("UPDATE `messages` SET `content` = ?, `content_bigram` = ?, `expires_at` = ? WHERE `id` = ? AND `deleted_at` IS NULL")
+        .bind(&content)
+        .bind(bigram_content(&content))
+        .bind(expires_at)
+        .bind(id.0)
+        .execute(pool)
+        .await
+        .map_err(super::Error::Sqlx)?;
+    let message = get_message(pool, super::GetMessageParams { id }).await?;
+    tracing::info!(id = %id.0, "Updated a message");
+
+    event_service
+        .publish_event(crate::event::Event::MessageUpdated(message.clone()))
+        .await
+        .map_err(IntoStatus::into_status)?;
+
+    // 編集で新しくメンションされたユーザーのみに通知する
+    let before_mentions = extract_mentions(&before.content);
+    let mentions = extract_mentions(&message.content)
+        .into_iter()
+        .filter(|name| !before_mentions.contains(name))
+        .collect();
+    notify_mentions(notification_service, pool, &message, mentions).await;
+    Ok(message)
+}
+
+async fn delete_message<P: crate::event::ProvideEventService>(
+    event_service: &P,
+    pool: &MySqlPool,
+    params: super::DeleteMessageParams,
+) -> Result<super::Message, super::Error> {
+    let super::DeleteMessageParams { id, user_id } = params;
+    let message = get_own_message(pool, id, user_id).await?;
+    sqlx::query(
+        "UPDATE `messages` SET `deleted_at` = NOW() WHERE `id` = ? AND `deleted_at` IS NULL",
+    )
+    .bind(id.0)
+    .execute(pool)
+    .await
+    .map_err(super::Error::Sqlx)?;
+    tracing::info!(id = %id.0, "Deleted a message");
+
+    event_service
+        .publish_event(crate::event::Event::MessageDeleted(message.clone()))
+        .await
+        .map_err(IntoStatus::into_status)?;
+    Ok(message)
+}
+
+async fn get_message_history<U: crate::user::ProvideUserService>(
+    user_service: &U,
+    pool: &MySqlPool,
+    params: super::GetMessageHistoryParams,
+) -> Result<super::MessageHistory, super::Error> {
+    let super::GetMessageHistoryParams {
+        user_id,
+        center,
+        size,
+        since,
+        until,
+        cursor,
+        limit,
+    } = params;
+    if since >= until {
+        return Err(super::Error::BadTimeRange);
+    }
+    let limit = limit.clamp(1, PAGE_MAX_LIMIT);
+    let admin = user_service
+        .check_admin(crate::user::CheckAdminParams { id: user_id })
+        .await
+        .map_err(IntoStatus::into_status)?;
+
+    // UUIDv7は生成順に並ぶので、IDをカーソルにする
+    // 1件多く取得して続きがあるか判定する
+    let mut rows = sqlx::query_as::<_, MessageRow>(
+        r#"
+            SELECT * FROM `messages`
+            WHERE
+                `position_x` BETWEEN ? AND ?
+            AND
+                `position_y` BETWEEN ? AND ?
+            AND
+                `created_at` >= ? AND `created_at` < ?
+            AND
+                `id` > ?
+            AND
+                `deleted_at` IS NULL
+            AND
+                (`expires_at` > NOW() OR `user_id` = ? OR ?)
+            ORDER BY `id` ASC
+            LIMIT ?
+        "#,
+    )
+    .bind(center.x.saturating_sub(size.width / 2) as i32)
+    .bind(center.x.saturating_add(size.width / 2) as i32)
+    .bind(center.y.saturating_sub(size.height / 2) as i32)
+    .bind(center.y.saturating_add(size.height / 2) as i32)
+    .bind(since.0)
+    .bind(until.0)
+    .bind(cursor.map_or(Uuid::nil(), |id| id.0))
+    .bind(user_id.0)
+    .bind(admin)
+    .bind(limit + 1)
+    .fetch_all(pool)
+    .await
+    .map_err(super::Error::Sqlx)?;
+
+    let next_cursor = if rows.len() > limit as usize {
+        rows.truncate(limit as usize);
+        rows.last().map(|row| super::MessageId(row.id))
+    } else {
+        None
+    };
+    Ok(super::MessageHistory {
+        messages: rows.into_iter().map(Into::into).collect(),
+        next_cursor,
+    })
+}
+
+async fn search_messages<U: crate::user::ProvideUserService>(
+    user_service: &U,
+    pool: &MySqlPool,
+    backend: super::MessageSearchBackend,
+    params: super::SearchMessagesParams,
+) -> Result<super::MessageSearchResult, super::Error> {
+    let super::SearchMessagesParams {
+        user_id,
+        query,
+        author_id,
+        since,
+        until,
+        area,
+        cursor,
+        limit,
+    } = params;
+    // BOOLEAN MODEのフレーズ検索にするので`"`は除く
+    let phrase = query.replace('"', " ");
+    let phrase = phrase.trim();
+    if phrase.is_empty() {
+        return Err(super::Error::EmptyQuery);
+    }
+    if let (Some(since), Some(until)) = (since, until) {
+        if since >= until {
+            return Err(super::Error::BadTimeRange);
+        }
+    }
+    let limit = limit.clamp(1, PAGE_MAX_LIMIT);
+    let admin = user_service
+        .check_admin(crate::user::CheckAdminParams { id: user_id })
+        .await
+        .map_err(IntoStatus::into_status)?;
+
+    let mut builder = sqlx::QueryBuilder::new("SELECT * FROM `messages` WHERE ");
+    match backend {
+        super::MessageSearchBackend::Ngram => builder
+            .push("MATCH (`content`) AGAINST (")
+            .push_bind(format!("\"{phrase}\"")),
+        super::MessageSearchBackend::Bigram => builder
+            .push("MATCH (`content_bigram`) AGAINST (")
+            .push_bind(bigram_query(phrase)),
+    };
+    builder.push(" IN BOOLEAN MODE)");
+    builder
+        .push(" AND `deleted_at` IS NULL")
+        .push(" AND (`expires_at` > NOW() OR `user_id` = ")
+        .push_bind(user_id.0)
+        .push(" OR ")
+        .push_bind(admin)
+        .push(")");
+    if let Some(author_id) = author_id {
+        builder.push(" AND `user_id` = ").push_bind(author_id.0);
+    }
+    if let Some(since) = since {
+        builder.push(" AND `created_at` >= ").push_bind(since.0);
+    }
+    if let Some(until) = until {
+        builder.push(" AND `created_at` < ").push_bind(until.0);
+    }
+    if let Some(super::SearchArea { center, size }) = area {
+        builder
+            .push(" AND `position_x` BETWEEN ")
+            .push_bind(center.x.saturating_sub(size.width / 2) as i32)
+            .push(" AND ")
+            .push_bind(center.x.saturating_add(size.width / 2) as i32)
+            .push(" AND `position_y` BETWEEN ")
+            .push_bind(center.y.saturating_sub(size.height / 2) as i32)
+            .push(" AND ")
+            .push_bind(center.y.saturating_add(size.height / 2) as i32);
+    }
+    if let Some(cursor) = cursor {
+        builder.push(" AND `id` < ").push_bind(cursor.0);
+    }
+    // 1件多く取得して続きがあるか判定する
+    builder
+        .push(" ORDER BY `id` DESC LIMIT ")
+        .push_bind(limit + 1);
+    let mut rows: Vec<MessageRow> = builder
+        .build_query_as()
+        .fetch_all(pool)
+        .await
+        .map_err(super::Error::Sqlx)?;
+
+    let next_cursor = if rows.len() > limit as usize {
+        rows.truncate(limit as usize);
+        rows.last().map(|row| super::MessageId(row.id))
+    } else {
+        None
+    };
+    Ok(super::MessageSearchResult {
+        messages: rows.into_iter().map(Into::into).collect(),
+        next_cursor,
+    })
+}
+
+async fn fill_search_index(
+    pool: &MySqlPool,
+    params: super::FillSearchIndexParams,
+) -> Result<u64, super::Error> {
+    const BATCH_SIZE: u32 = 500;
+
+    let super::FillSearchIndexParams {} = params;
+    let mut filled = 0;
+    loop {
+        let rows: Vec<(Uuid, String)> = sqlx::query_as(
+            "SELECT `id`, `content` FROM `messages` WHERE `content_bigram` IS NULL LIMIT ?",
+        )
+        .bind(BATCH_SIZE)
+        .fetch_all(pool)
+        .await
+        .map_err(super::Error::Sqlx)?;
+        if rows.is_empty() {
+            break;
+        }
+        for (id, content) in rows {
+            sqlx::query(
+                "UPDATE `messages` SET `content_bigram` = ?, `updated_at` = `updated_at` WHERE `id` = ?",
+            )
+            .bind(bigram_content(&content))
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(super::Error::Sqlx)?;
+            filled += 1;
+        }
+    }
+    Ok(filled)
+}
+
+// MARK: search index
+
+/// 標準のパーサーで1語として扱われ、最短の長さを下回らないように文字列を符号化する
+fn bigram_token(s: &str) -> String {
+    format!("g{}", hex::encode(s.as_bytes()))
+}
+
+/// 本文を2文字ずつ区切ったもの; 最後の1文字も1語にして、1文字での前方一致で探せるようにする
+fn bigram_content(content: &str) -> String {
+    let chars: Vec<char> = content.to_lowercase().chars().collect();
+    (0..chars.len())
+        .map(|i| {
+            let end = (i + 2).min(chars.len());
+            bigram_token(&chars[i..end].iter().collect::<String>())
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `content_bigram`をBOOLEAN MODEで探す検索式
+fn bigram_query(phrase: &str) -> String {
+    let chars: Vec<char> = phrase.to_lowercase().chars().collect();
+    if let [c] = chars[..] {
+        return format!("{}*", bigram_token(&c.to_string()));
+    }
+    let tokens: Vec<_> = chars
+        .windows(2)
+        .map(|w| bigram_token(&w.iter().collect::<String>()))
+        .collect();
+    format!("\"{}\"", tokens.join(" "))
+}
+
+// MARK: mentions
+
+/// ユーザー名に使える文字
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// 本文中の`@name`のユーザー名; 重複なしで出現順
+///
+/// メールアドレスのように直前に名前の文字があるものは除く
+fn extract_mentions(content: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = vec![];
+    let mut prev = None;
+    for (i, c) in content.char_indices() {
+        if c == '@' && !prev.is_some_and(is_name_char) {
+            let rest = &content[i + 1..];
+            let len = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+            let name = &rest[..len];
+            if !name.is_empty() && !mentions.iter().any(|m| m == name) {
+                mentions.push(name.to_string());
+            }
+        }
+        prev = Some(c);
+    }
+    mentions
+}
+
+/// メンションされたユーザーに通知する
+///
+/// メッセージは既に作成されているので、失敗してもログに残すのみ
+async fn notify_mentions<N: crate::notification::ProvideNotificationService>(
+    notification_service: &N,
+    pool: &MySqlPool,
+    message: &super::Message,
+    names: Vec<String>,
+) {
+    if names.is_empty() {
+        return;
+    }
+    let mut query = sqlx::QueryBuilder::new("SELECT `id` FROM `users` WHERE `name` IN (");
+    let mut separated = query.separated(", ");
+    for name in &names {
+        separated.push_bind(name);
+    }
+    separated.push_unseparated(")");
+    let user_ids: Vec<(Uuid,)> = match query.build_query_as().fetch_all(pool).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::error!(
+                error = &e as &dyn std::error::Error,
+                "Failed to resolve mentions"
+            );
+            return;
+        }
+    };
+    let params = crate::notification::CreateMentionNotificationsParams {
+        message: message.clone(),
+        user_ids: user_ids
+            .into_iter()
+            .map(|(id,)| crate::user::UserId(id))
+            .collect(),
+    };
+    if let Err(e) = notification_service
+        .create_mention_notifications(params)
+        .await
+    {
+        tracing::error!(
+            error = &e as &dyn std::error::Error,
+            "Failed to create mention notifications"
+        );
+    }
+}
+
+/// traQと同期されているかどうか
+async fn is_relayed(pool: &MySqlPool, id: super::MessageId) -> Result<bool, super::Error> {
+    let (relayed,): (bool,) =
+        sqlx::query_as("SELECT EXISTS (SELECT 1 FROM `traq_messages` WHERE `message_id` = ?)")
+            .bind(id.0)
+            .fetch_one(pool)
+            .await
+            .map_err(super::Error::Sqlx)?;
+    Ok(relayed)
+}
+
+async fn mark_message_relayed(
+    policy: &crate::retention::RetentionPolicy,
+    pool: &MySqlPool,
+    params: super::MarkMessageRelayedParams,
+) -> Result<(), super::Error> {
+    let super::MarkMessageRelayedParams { id } = params;
+    let message = match get_message(pool, super::GetMessageParams { id }).await {
+        Ok(message) => message,
+        // 同期中に削除された
+        Err(super::Error::NotFound) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let expires_at = policy
+        .messages
+        .expires_at(message.created_at.0, &message.content, true);
+    // 編集されたように見えないよう`updated_at`は維持する
+    sqlx::query(
+        "UPDATE `messages` SET `expires_at` = ?, `updated_at` = `updated_at` WHERE `id` = ?",
+    )
+    .bind(expires_at)
+    .bind(id.0)
+    .execute(pool)
+    .await
+    .map_err(super::Error::Sqlx)?;
+    Ok(())
+}
+
+async fn get_expired_messages(
+    pool: &MySqlPool,
+    params: super::GetExpiredMessagesParams,
+) -> Result<Vec<super::Message>, super::Error> {
+    let super::GetExpiredMessagesParams { since, until } = params;
+    sqlx::query_as::<_, MessageRow>(
+        r#"
+            SELECT * FROM `messages`
+            WHERE
+                `expires_at` > ?
+            AND
+                `expires_at` <= ?
+            AND
+                `deleted_at` IS NULL
+            ORDER BY `expires_at` ASC
+        "#,
+    )
+    .bind(since.0)
+    .bind(until.0)
+    .fetch_all(pool)
+    .await
+    .map(|rows| rows.into_iter().map(Into::into).collect())
+    .map_err(super::Error::Sqlx)
+}
+
+async fn purge_expired_messages(
+    pool: &MySqlPool,
+    params: super::PurgeExpiredMessagesParams,
+) -> Result<u64, super::Error> {
+    let super::PurgeExpiredMessagesParams {
+        expired_before,
+        limit,
+    } = params;
+    let mut tx = pool.begin().await.map_err(super::Error::Sqlx)?;
+    let ids: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+            SELECT `id` FROM `messages`
+            WHERE `expires_at` <= ? OR `deleted_at` <= ?
+            LIMIT ?
+            FOR UPDATE
+        "#,
+    )
+    .bind(expired_before.0)
+    .bind(expired_before.0)
+    .bind(limit)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(super::Error::Sqlx)?;
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    // traQのメッセージとの対応も消す
+    let mut query = sqlx::QueryBuilder::new("DELETE FROM `traq_messages` WHERE `message_id` IN ");
+    push_ids(&mut query, &ids);
+    query
+        .build()
+        .execute(&mut *tx)
+        .await
+        .map_err(super::Error::Sqlx)?;
+    let mut query = sqlx::QueryBuilder::new("DELETE FROM `messages` WHERE `id` IN ");
+    push_ids(&mut query, &ids);
+    let result = query
+        .build()
+        .execute(&mut *tx)
+        .await
+        .map_err(super::Error::Sqlx)?;
+    tx.commit().await.map_err(super::Error::Sqlx)?;
+    Ok(result.rows_affected())
+}
+
+/// `(?, ?, ...)`
+fn push_ids(query: &mut sqlx::QueryBuilder<'_, sqlx::MySql>, ids: &[(Uuid,)]) {
+    query.push("(");
+    let mut separated = query.separated(", ");
+    for (id,) in ids {
+        separated.push_bind(*id);
+    }
+    separated.push_unseparated(")");
+}
+
+#[test]
+fn test_extract_mentions() {
+    assert_eq!(
+        extract_mentions("@alice こんにちは @bob_2 と@alice"),
+        vec!["alice".to_string(), "bob_2".to_string()]
+    );
+    assert_eq!(
+        extract_mentions("mail@example.com @ @-x"),
+        vec!["-x".to_string()]
+    );
+    assert!(extract_mentions("メンションなし").is_empty());
+}
+
+#[test]
+fn test_bigram_query_matches_content() {
+    let content = bigram_content("今日はSearch日和");
+    let tokens: Vec<&str> = content.split(' ').collect();
+    assert_eq!(tokens.len(), 11);
+
+    // フレーズの語が本文の連続した語になる
+    let query = bigram_query("はsearch日");
+    assert!(content.contains(query.trim_matches('"')));
+    assert!(!content.contains(bigram_query("は今").trim_matches('"')));
+
+    let query = bigram_query("和");
+    assert_eq!(tokens.last(), Some(&query.trim_end_matches('*')));
+}
@@ -0,0 +1,178 @@
+//! `notification.proto`
+
+pub mod error;
+pub mod grpc;
+mod r#impl;
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use futures::{future::BoxFuture, stream::BoxStream};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::prelude::{IntoStatus, Timestamp};
+
+pub use error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct NotificationId(pub uuid::Uuid);
+
+/// メンションの通知
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub id: NotificationId,
+    /// 通知先
+    pub user_id: crate::user::UserId,
+    pub message_id: crate::message::MessageId,
+    /// メンションしたユーザー
+    pub sender_id: crate::user::UserId,
+    pub position: crate::world::Coordinate,
+    pub created_at: Timestamp,
+    /// 未読なら`None`
+    pub read_at: Option<Timestamp>,
+}
+
+/// ユーザーごとの通知のチャンネル; 購読者がいるユーザーのみ保持する
+#[derive(Debug, Clone)]
+pub struct NotificationChannels {
+    capacity: usize,
+    txs: Arc<RwLock<HashMap<crate::user::UserId, broadcast::Sender<Notification>>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct CreateMentionNotificationsParams {
+    pub message: crate::message::Message,
+    /// メンションされたユーザー; 送信者自身は除かれる
+    pub user_ids: Vec<crate::user::UserId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct GetNotificationsParams {
+    pub user_id: crate::user::UserId,
+    pub unread_only: bool,
+    /// このIDより前の通知を返す
+    pub before: Option<NotificationId>,
+    pub limit: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct MarkNotificationsReadParams {
+    pub user_id: crate::user::UserId,
+    /// このIDまでの通知を既読にする
+    pub until: NotificationId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct SubscribeNotificationsParams {
+    pub user_id: crate::user::UserId,
+}
+
+pub trait NotificationService<Context>: Send + Sync + 'static {
+    type Error: IntoStatus;
+
+    /// 通知を保存して、購読中のユーザーに配信する
+    fn create_mention_notifications<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: CreateMentionNotificationsParams,
+    ) -> BoxFuture<'a, Result<Vec<Notification>, Self::Error>>;
+    /// 新しい順
+    fn get_notifications<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: GetNotificationsParams,
+    ) -> BoxFuture<'a, Result<Vec<Notification>, Self::Error>>;
+    fn mark_notifications_read<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: MarkNotificationsReadParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>>;
+    /// 受信が追いつかず取りこぼした通知は、`get_notifications`で取得し直す
+    fn subscribe_notifications<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: SubscribeNotificationsParams,
+    ) -> BoxStream<'static, Result<Notification, Self::Error>>;
+}
+
+#[allow(clippy::type_complexity)]
+pub trait ProvideNotificationService: Send + Sync + 'static {
+    type Context;
+    type NotificationService: NotificationService<Self::Context>;
+
+    fn context(&self) -> &Self::Context;
+    fn notification_service(&self) -> &Self::NotificationService;
+
+    fn create_mention_notifications(
+        &self,
+        params: CreateMentionNotificationsParams,
+    ) -> BoxFuture<
+        '_,
+        Result<
+            Vec<Notification>,
+            <Self::NotificationService as NotificationService<Self::Context>>::Error,
+        >,
+    > {
+        let ctx = self.context();
+        self.notification_service()
+            .create_mention_notifications(ctx, params)
+    }
+    fn get_notifications(
+        &self,
+        params: GetNotificationsParams,
+    ) -> BoxFuture<
+        '_,
+        Result<
+            Vec<Notification>,
+            <Self::NotificationService as NotificationService<Self::Context>>::Error,
+        >,
+    > {
+        let ctx = self.context();
+        self.notification_service().get_notifications(ctx, params)
+    }
+    fn mark_notifications_read(
+        &self,
+        params: MarkNotificationsReadParams,
+    ) -> BoxFuture<
+        '_,
+        Result<(), <Self::NotificationService as NotificationService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.notification_service()
+            .mark_notifications_read(ctx, params)
+    }
+    fn subscribe_notifications(
+        &self,
+        params: SubscribeNotificationsParams,
+    ) -> BoxStream<
+        'static,
+        Result<
+            Notification,
+            <Self::NotificationService as NotificationService<Self::Context>>::Error,
+        >,
+    > {
+        let ctx = self.context();
+        self.notification_service()
+            .subscribe_notifications(ctx, params)
+    }
+}
+
+pub fn build_server<State>(state: Arc<State>) -> NotificationServiceServer<State>
+where
+    State: ProvideNotificationService + crate::session::ProvideSessionService,
+{
+    NotificationServiceServer::new(grpc::ServiceImpl::new(state))
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NotificationServiceImpl;
+
+pub type NotificationServiceServer<State> =
+    schema::notification::notification_service_server::NotificationServiceServer<
+        grpc::ServiceImpl<State>,
+    >;
+
+pub use schema::notification::notification_service_server::SERVICE_NAME;
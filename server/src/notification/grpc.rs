@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use uuid::Uuid;
+
+use crate::prelude::IntoStatus;
+
+// MARK: type conversions
+
+impl From<super::Notification> for schema::notification::Notification {
+    fn from(value: super::Notification) -> Self {
+        let super::Notification {
+            id,
+            user_id,
+            message_id,
+            sender_id,
+            position,
+            created_at,
+            read_at,
+        } = value;
+        Self {
+            id: id.0.to_string(),
+            user_id: user_id.0.to_string(),
+            message_id: message_id.0.to_string(),
+            sender_id: sender_id.0.to_string(),
+            position: Some(position.into()),
+            created_at: Some(created_at.into()),
+            read_at: read_at.map(Into::into),
+        }
+    }
+}
+
+// MARK: ServiceImpl
+
+pub struct ServiceImpl<State> {
+    state: Arc<State>,
+}
+
+impl<State> Clone for ServiceImpl<State>
+where
+    State: super::ProvideNotificationService,
+{
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<State> ServiceImpl<State>
+where
+    State: super::ProvideNotificationService + crate::session::ProvideSessionService,
+{
+    pub(super) fn new(state: Arc<State>) -> Self {
+        Self { state }
+    }
+}
+
+#[async_trait::async_trait]
+impl<State> schema::notification::notification_service_server::NotificationService
+    for ServiceImpl<State>
+where
+    State: super::ProvideNotificationService + crate::session::ProvideSessionService,
+{
+    type SubscribeNotificationsStream = futures::stream::BoxStream<
+        'static,
+        Result<schema::notification::SubscribeNotificationsResponse, tonic::Status>,
+    >;
+
+    async fn get_notifications(
+        &self,
+        request: tonic::Request<schema::notification::GetNotificationsRequest>,
+    ) -> Result<tonic::Response<schema::notification::GetNotificationsResponse>, tonic::Status>
+    {
+        let (
+            meta,
+            _,
+            schema::notification::GetNotificationsRequest {
+                unread_only,
+                before,
+                limit,
+            },
+        ) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let before = before
+            .map(|id| Uuid::parse_str(&id).map(super::NotificationId))
+            .transpose()
+            .map_err(|_| tonic::Status::invalid_argument("Invalid UUID"))?;
+        let params = super::GetNotificationsParams {
+            user_id,
+            unread_only,
+            before,
+            limit,
+        };
+        let notifications = self
+            .state
+            .get_notifications(params)
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let res = schema::notification::GetNotificationsResponse {
+            notifications: notifications.into_iter().map(Into::into).collect(),
+        };
+        Ok(tonic::Response::new(res))
+    }
+
+    async fn mark_notifications_read(
+        &self,
+        request: tonic::Request<schema::notification::MarkNotificationsReadRequest>,
+    ) -> Result<tonic::Response<schema::notification::MarkNotificationsReadResponse>, tonic::Status>
+    {
+        let (meta, _, schema::notification::MarkNotificationsReadRequest { until }) =
+            request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let params = super::MarkNotificationsReadParams {
+            user_id,
+            until: super::NotificationId(
+                Uuid::parse_str(&until)
+                    .map_err(|_| tonic::Status::invalid_argument("Invalid UUID"))?,
+            ),
+        };
+        self.state
+            .mark_notifications_read(params)
+            .await
+            .map_err(IntoStatus::into_status)?;
+        Ok(tonic::Response::new(
+            schema::notification::MarkNotificationsReadResponse {},
+        ))
+    }
+
+    async fn subscribe_notifications(
+        &self,
+        request: tonic::Request<schema::notification::SubscribeNotificationsRequest>,
+    ) -> Result<tonic::Response<Self::SubscribeNotificationsStream>, tonic::Status> {
+        let (meta, _, schema::notification::SubscribeNotificationsRequest {}) =
+            request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let notifications = self
+            .state
+            .subscribe_notifications(super::SubscribeNotificationsParams { user_id })
+            .map(|notification| {
+                let notification = notification.map_err(IntoStatus::into_status)?;
+                Ok(schema::notification::SubscribeNotificationsResponse {
+                    notification: Some(notification.into()),
+                })
+            });
+        Ok(tonic::Response::new(notifications.boxed()))
+    }
+}
@@ -0,0 +1,223 @@
+use std::sync::PoisonError;
+
+use chrono::{DateTime, Utc};
+use futures::{
+    future::BoxFuture,
+    stream::{BoxStream, StreamExt},
+    FutureExt,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, MySqlPool};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use uuid::Uuid;
+
+use crate::prelude::Timestamp;
+
+/// 1ページの最大件数
+const PAGE_MAX_LIMIT: u32 = 100;
+
+impl super::NotificationChannels {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            txs: Default::default(),
+        }
+    }
+
+    fn subscribe(&self, user_id: crate::user::UserId) -> broadcast::Receiver<super::Notification> {
+        let mut txs = self.txs.write().unwrap_or_else(PoisonError::into_inner);
+        txs.retain(|_, tx| tx.receiver_count() > 0);
+        txs.entry(user_id)
+            .or_insert_with(|| broadcast::Sender::new(self.capacity))
+            .subscribe()
+    }
+
+    /// 購読者がいなければ何もしない
+    fn send(&self, notification: super::Notification) {
+        let txs = self.txs.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(tx) = txs.get(&notification.user_id) {
+            let _ = tx.send(notification);
+        }
+    }
+}
+
+impl<Context> super::NotificationService<Context> for super::NotificationServiceImpl
+where
+    Context: AsRef<MySqlPool> + AsRef<super::NotificationChannels>,
+{
+    type Error = super::Error;
+
+    fn create_mention_notifications<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::CreateMentionNotificationsParams,
+    ) -> BoxFuture<'a, Result<Vec<super::Notification>, Self::Error>> {
+        create_mention_notifications(ctx.as_ref(), ctx.as_ref(), params).boxed()
+    }
+
+    fn get_notifications<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::GetNotificationsParams,
+    ) -> BoxFuture<'a, Result<Vec<super::Notification>, Self::Error>> {
+        get_notifications(ctx.as_ref(), params).boxed()
+    }
+
+    fn mark_notifications_read<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::MarkNotificationsReadParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>> {
+        mark_notifications_read(ctx.as_ref(), params).boxed()
+    }
+
+    fn subscribe_notifications<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::SubscribeNotificationsParams,
+    ) -> BoxStream<'static, Result<super::Notification, Self::Error>> {
+        let channels: &super::NotificationChannels = ctx.as_ref();
+        let super::SubscribeNotificationsParams { user_id } = params;
+        BroadcastStream::new(channels.subscribe(user_id))
+            .filter_map(|notification| async move {
+                match notification {
+                    Ok(notification) => Some(Ok(notification)),
+                    Err(BroadcastStreamRecvError::Lagged(n)) => {
+                        tracing::warn!(skipped = n, "Notification subscription lagged");
+                        None
+                    }
+                }
+            })
+            .boxed()
+    }
+}
+
+// MARK: DB operations
+
+#[derive(Debug, Clone, Hash, Deserialize, Serialize, FromRow)]
+struct NotificationRow {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub message_id: Uuid,
+    pub sender_id: Uuid,
+    pub position_x: u32,
+    pub position_y: u32,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+impl From<NotificationRow> for super::Notification {
+    fn from(value: NotificationRow) -> Self {
+        Self {
+            id: super::NotificationId(value.id),
+            user_id: crate::user::UserId(value.user_id),
+            message_id: crate::message::MessageId(value.message_id),
+            sender_id: crate::user::UserId(value.sender_id),
+            position: crate::world::Coordinate {
+                x: value.position_x,
+                y: value.position_y,
+            },
+            created_at: Timestamp(value.created_at),
+            read_at: value.read_at.map(Timestamp),
+        }
+    }
+}
+
+async fn create_mention_notifications(
+    channels: &super::NotificationChannels,
+    pool: &MySqlPool,
+    params: super::CreateMentionNotificationsParams,
+) -> Result<Vec<super::Notification>, super::Error> {
+    let super::CreateMentionNotificationsParams {
+        message,
+        mut user_ids,
+    } = params;
+    user_ids.retain(|id| *id != message.user_id);
+    user_ids.sort_by_key(|id| id.0);
+    user_ids.dedup();
+
+    let now = Utc::now();
+    let notifications: Vec<super::Notification> = user_ids
+        .into_iter()
+        .map(|user_id| super::Notification {
+            id: super::NotificationId(Uuid::now_v7()),
+            user_id,
+            message_id: message.id,
+            sender_id: message.user_id,
+            position: message.position,
+            created_at: Timestamp(now),
+            read_at: None,
+        })
+        .collect();
+    if notifications.is_empty() {
+        return Ok(notifications);
+    }
+
+    let mut query = sqlx::QueryBuilder::new(
+        "INSERT INTO `notifications` (`id`, `user_id`, `message_id`, `sender_id`, `position_x`, `position_y`, `created_at`) ",
+    );
+    query.push_values(&notifications, |mut b, n| {
+        b.push_bind(n.id.0)
+            .push_bind(n.user_id.0)
+            .push_bind(n.message_id.0)
+            .push_bind(n.sender_id.0)
+            .push_bind(n.position.x)
+            .push_bind(n.position.y)
+            .push_bind(n.created_at.0);
+    });
+    query.build().execute(pool).await?;
+    tracing::info!(
+        message_id = %message.id.0,
+        count = notifications.len(),
+        "Created mention notifications"
+    );
+
+    for notification in &notifications {
+        channels.send(notification.clone());
+    }
+    Ok(notifications)
+}
+
+async fn get_notifications(
+    pool: &MySqlPool,
+    params: super::GetNotificationsParams,
+) -> Result<Vec<super::Notification>, super::Error> {
+    let super::GetNotificationsParams {
+        user_id,
+        unread_only,
+        before,
+        limit,
+    } = params;
+    let mut query = sqlx::QueryBuilder::new("SELECT * FROM `notifications` WHERE `user_id` = ");
+    query.push_bind(user_id.0);
+    if unread_only {
+        query.push(" AND `read_at` IS NULL");
+    }
+    if let Some(before) = before {
+        query.push(" AND `id` < ").push_bind(before.0);
+    }
+    query
+        .push(" ORDER BY `id` DESC LIMIT ")
+        .push_bind(limit.clamp(1, PAGE_MAX_LIMIT));
+    let rows: Vec<NotificationRow> = query.build_query_as().fetch_all(pool).await?;
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+async fn mark_notifications_read(
+    pool: &MySqlPool,
+    params: super::MarkNotificationsReadParams,
+) -> Result<(), super::Error> {
+    let super::MarkNotificationsReadParams { user_id, until } = params;
+    sqlx::query(
+        r#"
+            UPDATE `notifications` SET `read_at` = NOW()
+            WHERE `user_id` = ? AND `id` <= ? AND `read_at` IS NULL
+        "#,
+    )
+    .bind(user_id.0)
+    .bind(until.0)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
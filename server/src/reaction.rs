@@ -3,6 +3,7 @@
 pub mod error;
 pub mod grpc;
 mod r#impl;
+pub mod kind;
 
 use std::sync::Arc;
 
@@ -47,6 +48,20 @@ pub struct CreateReactionParams {
     pub kind: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct GetExpiredReactionsParams {
+    /// この日時より後に期限切れになったもの
+    pub since: Timestamp,
+    /// この日時以前に期限切れになったもの
+    pub until: Timestamp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct PurgeExpiredReactionsParams {
+    pub expired_before: Timestamp,
+    pub limit: u32,
+}
+
 pub trait ReactionService<Context>: Send + Sync + 'static {
     type Error: IntoStatus;
 
@@ -65,6 +80,17 @@ pub trait ReactionService<Context>: Send + Sync + 'static {
         ctx: &'a Context,
         params: CreateReactionParams,
     ) -> BoxFuture<'a, Result<Reaction, Self::Error>>;
+    fn get_expired_reactions<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: GetExpiredReactionsParams,
+    ) -> BoxFuture<'a, Result<Vec<Reaction>, Self::Error>>;
+    /// 削除した件数を返す
+    fn purge_expired_reactions<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: PurgeExpiredReactionsParams,
+    ) -> BoxFuture<'a, Result<u64, Self::Error>>;
 }
 
 #[allow(clippy::type_complexity)]
@@ -105,11 +131,31 @@ pub trait ProvideReactionService: Send + Sync + 'static {
         let ctx = self.context();
         self.reaction_service().create_reaction(ctx, params)
     }
+    fn get_expired_reactions(
+        &self,
+        params: GetExpiredReactionsParams,
+    ) -> BoxFuture<
+        '_,
+        Result<Vec<Reaction>, <Self::ReactionService as ReactionService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.reaction_service().get_expired_reactions(ctx, params)
+    }
+    fn purge_expired_reactions(
+        &self,
+        params: PurgeExpiredReactionsParams,
+    ) -> BoxFuture<'_, Result<u64, <Self::ReactionService as ReactionService<Self::Context>>::Error>>
+    {
+        let ctx = self.context();
+        self.reaction_service().purge_expired_reactions(ctx, params)
+    }
 }
 
 pub fn build_server<State>(this: Arc<State>) -> ReactionServiceServer<State>
 where
-    State: ProvideReactionService + crate::session::ProvideSessionService,
+    State: ProvideReactionService
+        + kind::ProvideReactionKindService
+        + crate::session::ProvideSessionService,
 {
     let service = grpc::ServiceImpl::new(this);
     ReactionServiceServer::new(service)
@@ -4,6 +4,8 @@ pub enum Error {
     NotFound,
     #[error("Reaction not in world")]
     ReactionNotInWorld,
+    #[error("Unknown reaction kind")]
+    UnknownKind,
     #[error("Database error")]
     Sqlx(#[from] sqlx::Error),
     #[error(transparent)]
@@ -15,6 +17,7 @@ impl From<Error> for tonic::Status {
         match value {
             Error::NotFound => tonic::Status::not_found("Not found"),
             Error::ReactionNotInWorld => tonic::Status::invalid_argument("Reaction not in world"),
+            Error::UnknownKind => tonic::Status::invalid_argument("Unknown reaction kind"),
             Error::Sqlx(e) => {
                 tracing::error!(error = &e as &dyn std::error::Error);
                 tonic::Status::internal("Database error")
@@ -28,6 +28,13 @@ impl From<super::Reaction> for schema::Reaction {
     }
 }
 
+impl From<super::kind::ReactionKind> for schema::ReactionKind {
+    fn from(value: super::kind::ReactionKind) -> Self {
+        let super::kind::ReactionKind { name, image_url } = value;
+        Self { name, image_url }
+    }
+}
+
 // MARK: ServiceImpl
 
 pub struct ServiceImpl<State> {
@@ -47,7 +54,9 @@ where
 
 impl<State> ServiceImpl<State>
 where
-    State: super::ProvideReactionService + crate::session::ProvideSessionService,
+    State: super::ProvideReactionService
+        + super::kind::ProvideReactionKindService
+        + crate::session::ProvideSessionService,
 {
     pub(super) fn new(state: Arc<State>) -> Self {
         Self { state }
@@ -57,7 +66,9 @@ where
 #[async_trait::async_trait]
 impl<State> schema::reaction_service_server::ReactionService for ServiceImpl<State>
 where
-    State: super::ProvideReactionService + crate::session::ProvideSessionService,
+    State: super::ProvideReactionService
+        + super::kind::ProvideReactionKindService
+        + crate::session::ProvideSessionService,
 {
     async fn get_reaction(
         &self,
@@ -115,4 +126,20 @@ where
         };
         Ok(tonic::Response::new(res))
     }
+
+    async fn list_reaction_kinds(
+        &self,
+        request: tonic::Request<schema::ListReactionKindsRequest>,
+    ) -> Result<tonic::Response<schema::ListReactionKindsResponse>, tonic::Status> {
+        let (_, _, schema::ListReactionKindsRequest {}) = request.into_parts();
+        let kinds = self
+            .state
+            .list_reaction_kinds(super::kind::ListReactionKindsParams {})
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let res = schema::ListReactionKindsResponse {
+            kinds: kinds.into_iter().map(Into::into).collect(),
+        };
+        Ok(tonic::Response::new(res))
+    }
 }
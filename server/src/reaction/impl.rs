@@ -6,8 +6,11 @@ use crate::prelude::IntoStatus;
 
 impl<Context> super::ReactionService<Context> for super::ReactionServiceImpl
 where
-    Context:
-        AsRef<MySqlPool> + crate::event::ProvideEventService + crate::world::ProvideWorldService,
+    Context: AsRef<MySqlPool>
+        + AsRef<crate::retention::RetentionPolicy>
+        + crate::event::ProvideEventService
+        + crate::world::ProvideWorldService
+        + super::kind::ProvideReactionKindService,
 {
     type Error = super::Error;
 
@@ -32,7 +35,23 @@ where
         ctx: &'a Context,
         params: super::CreateReactionParams,
     ) -> future::BoxFuture<'a, Result<super::Reaction, Self::Error>> {
-        create_reaction(ctx, ctx, ctx.as_ref(), params).boxed()
+        create_reaction(ctx, ctx, ctx, ctx.as_ref(), ctx.as_ref(), params).boxed()
+    }
+
+    fn get_expired_reactions<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::GetExpiredReactionsParams,
+    ) -> future::BoxFuture<'a, Result<Vec<super::Reaction>, Self::Error>> {
+        get_expired_reactions(ctx.as_ref(), params).boxed()
+    }
+
+    fn purge_expired_reactions<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::PurgeExpiredReactionsParams,
+    ) -> future::BoxFuture<'a, Result<u64, Self::Error>> {
+        purge_expired_reactions(ctx.as_ref(), params).boxed()
     }
 }
 
@@ -110,9 +129,12 @@ async fn get_reactions_in_area(
 async fn create_reaction<
     P: crate::event::ProvideEventService,
     W: crate::world::ProvideWorldService,
+    K: super::kind::ProvideReactionKindService,
 >(
     event_service: &P,
     world_service: &W,
+    reaction_kind_service: &K,
+    policy: &crate::retention::RetentionPolicy,
     pool: &MySqlPool,
     params: super::CreateReactionParams,
 ) -> Result<super::Reaction, super::Error> {
@@ -122,6 +144,14 @@ async fn create_reaction<
         kind,
     } = params;
 
+    let known = reaction_kind_service
+        .check_reaction_kind(super::kind::CheckReactionKindParams { kind: kind.clone() })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    if !known {
+        return Err(super::Error::UnknownKind);
+    }
+
     // check if the position is in the world
     let crate::world::CheckCoordinateAnswer::Valid(position) = world_service
         .check_coordinate(crate::world::CheckCoordinateParams {
@@ -133,15 +163,16 @@ async fn create_reaction<
         return Err(super::Error::ReactionNotInWorld);
     };
 
+    let now = chrono::Utc::now();
     let reaction = ReactionRow {
         id: uuid::Uuid::now_v7(),
         user_id: user_id.0,
         position_x: position.x,
         position_y: position.y,
         kind,
-        created_at: chrono::Utc::now(),
-        updated_at: chrono::Utc::now(),
-        expires_at: chrono::Utc::now() + chrono::Duration::seconds(10),
+        created_at: now,
+        updated_at: now,
+        expires_at: policy.reactions.expires_at(now),
     };
     sqlx::query(
         r#"
@@ -176,3 +207,38 @@ async fn create_reaction<
 
     Ok(reaction)
 }
+
+async fn get_expired_reactions(
+    pool: &MySqlPool,
+    params: super::GetExpiredReactionsParams,
+) -> Result<Vec<super::Reaction>, super::Error> {
+    let super::GetExpiredReactionsParams { since, until } = params;
+    let reactions: Vec<ReactionRow> = sqlx::query_as(
+        r#"
+            SELECT * FROM `reactions`
+            WHERE `expires_at` > ? AND `expires_at` <= ?
+            ORDER BY `expires_at` ASC
+        "#,
+    )
+    .bind(since.0)
+    .bind(until.0)
+    .fetch_all(pool)
+    .await?;
+    Ok(reactions.into_iter().map(Into::into).collect())
+}
+
+async fn purge_expired_reactions(
+    pool: &MySqlPool,
+    params: super::PurgeExpiredReactionsParams,
+) -> Result<u64, super::Error> {
+    let super::PurgeExpiredReactionsParams {
+        expired_before,
+        limit,
+    } = params;
+    let result = sqlx::query(r#"DELETE FROM `reactions` WHERE `expires_at` <= ? LIMIT ?"#)
+        .bind(expired_before.0)
+        .bind(limit)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
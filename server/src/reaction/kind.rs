@@ -0,0 +1,109 @@
+//! リアクションの種類の登録簿
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::prelude::IntoStatus;
+
+pub mod error;
+mod r#impl;
+
+pub use error::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionKind {
+    pub name: String,
+    /// 画像のURL; クライアントが画像を持っている種類では`None`
+    #[serde(default)]
+    pub image_url: Option<String>,
+}
+
+/// 使用できるリアクションの種類
+#[derive(Debug, Clone)]
+pub struct ReactionKindRegistry(Arc<RwLock<BTreeMap<String, ReactionKind>>>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ListReactionKindsParams {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct CheckReactionKindParams {
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct SyncTraqStampsParams {}
+
+pub trait ReactionKindService<Context>: Send + Sync + 'static {
+    type Error: IntoStatus;
+
+    fn list_reaction_kinds<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: ListReactionKindsParams,
+    ) -> BoxFuture<'a, Result<Vec<ReactionKind>, Self::Error>>;
+    /// 登録されている種類であれば`true`
+    fn check_reaction_kind<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: CheckReactionKindParams,
+    ) -> BoxFuture<'a, Result<bool, Self::Error>>;
+    /// traQのスタンプを登録簿に追加する; 既に登録されている種類は上書きしない
+    fn sync_traq_stamps<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: SyncTraqStampsParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>>;
+}
+
+#[allow(clippy::type_complexity)]
+pub trait ProvideReactionKindService: Send + Sync + 'static {
+    type Context;
+    type ReactionKindService: ReactionKindService<Self::Context>;
+
+    fn context(&self) -> &Self::Context;
+    fn reaction_kind_service(&self) -> &Self::ReactionKindService;
+
+    fn list_reaction_kinds(
+        &self,
+        params: ListReactionKindsParams,
+    ) -> BoxFuture<
+        '_,
+        Result<
+            Vec<ReactionKind>,
+            <Self::ReactionKindService as ReactionKindService<Self::Context>>::Error,
+        >,
+    > {
+        let ctx = self.context();
+        self.reaction_kind_service()
+            .list_reaction_kinds(ctx, params)
+    }
+    fn check_reaction_kind(
+        &self,
+        params: CheckReactionKindParams,
+    ) -> BoxFuture<
+        '_,
+        Result<bool, <Self::ReactionKindService as ReactionKindService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.reaction_kind_service()
+            .check_reaction_kind(ctx, params)
+    }
+    fn sync_traq_stamps(
+        &self,
+        params: SyncTraqStampsParams,
+    ) -> BoxFuture<
+        '_,
+        Result<(), <Self::ReactionKindService as ReactionKindService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.reaction_kind_service().sync_traq_stamps(ctx, params)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReactionKindServiceImpl;
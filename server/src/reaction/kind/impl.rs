@@ -0,0 +1,159 @@
+use futures::{future::BoxFuture, FutureExt};
+use serde::Deserialize;
+
+use crate::{
+    prelude::IntoStatus,
+    traq::{
+        bot::{BuildRequestAsBotParams, ProvideTraqBotService},
+        TraqHost,
+    },
+};
+
+/// クライアントに画像が同梱されている種類
+const BUILTIN_REACTION_KINDS: &[&str] = &[
+    "iine",
+    "kusa",
+    "pro",
+    "fire",
+    "smile",
+    "hetareneko_iyaaa",
+    "abao_iyaaaa",
+    "eyes",
+];
+
+impl super::ReactionKindRegistry {
+    pub fn new(kinds: impl IntoIterator<Item = super::ReactionKind>) -> Self {
+        let kinds = kinds.into_iter().map(|k| (k.name.clone(), k)).collect();
+        Self(std::sync::Arc::new(tokio::sync::RwLock::new(kinds)))
+    }
+
+    /// JSONの配列`[{"name": "iine"}, ...]`から読み込む
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let kinds: Vec<super::ReactionKind> = serde_json::from_str(json)?;
+        Ok(Self::new(kinds))
+    }
+}
+
+impl Default for super::ReactionKindRegistry {
+    fn default() -> Self {
+        Self::new(
+            BUILTIN_REACTION_KINDS
+                .iter()
+                .map(|name| super::ReactionKind {
+                    name: name.to_string(),
+                    image_url: None,
+                }),
+        )
+    }
+}
+
+impl<Context> super::ReactionKindService<Context> for super::ReactionKindServiceImpl
+where
+    Context: ProvideTraqBotService + AsRef<TraqHost> + AsRef<super::ReactionKindRegistry>,
+{
+    type Error = super::Error;
+
+    fn list_reaction_kinds<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::ListReactionKindsParams,
+    ) -> BoxFuture<'a, Result<Vec<super::ReactionKind>, Self::Error>> {
+        list_reaction_kinds(ctx.as_ref(), params).boxed()
+    }
+
+    fn check_reaction_kind<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::CheckReactionKindParams,
+    ) -> BoxFuture<'a, Result<bool, Self::Error>> {
+        check_reaction_kind(ctx.as_ref(), params).boxed()
+    }
+
+    fn sync_traq_stamps<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::SyncTraqStampsParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>> {
+        sync_traq_stamps(ctx, params).boxed()
+    }
+}
+
+async fn list_reaction_kinds(
+    registry: &super::ReactionKindRegistry,
+    params: super::ListReactionKindsParams,
+) -> Result<Vec<super::ReactionKind>, super::Error> {
+    let super::ListReactionKindsParams {} = params;
+    let kinds = registry.0.read().await;
+    Ok(kinds.values().cloned().collect())
+}
+
+async fn check_reaction_kind(
+    registry: &super::ReactionKindRegistry,
+    params: super::CheckReactionKindParams,
+) -> Result<bool, super::Error> {
+    let super::CheckReactionKindParams { kind } = params;
+    let kinds = registry.0.read().await;
+    Ok(kinds.contains_key(&kind))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TraqStamp {
+    name: String,
+    file_id: uuid::Uuid,
+}
+
+#[tracing::instrument(skip_all)]
+async fn sync_traq_stamps<Context>(
+    ctx: &Context,
+    params: super::SyncTraqStampsParams,
+) -> Result<(), super::Error>
+where
+    Context: ProvideTraqBotService + AsRef<TraqHost> + AsRef<super::ReactionKindRegistry>,
+{
+    let super::SyncTraqStampsParams {} = params;
+    let traq_host: &TraqHost = ctx.as_ref();
+    let params = BuildRequestAsBotParams {
+        method: http::Method::GET,
+        uri: &format!("https://{traq_host}/api/v3/stamps?include-unicode=false"),
+    };
+    let stamps: Vec<TraqStamp> = ctx
+        .build_request_as_bot(params)
+        .await
+        .map_err(IntoStatus::into_status)?
+        .send()
+        .await
+        .map_err(|e| {
+            tracing::error!(error = &e as &dyn std::error::Error);
+            super::Error::RequestSendError
+        })?
+        .error_for_status()
+        .map_err(|e| {
+            tracing::error!(
+                error = &e as &dyn std::error::Error,
+                "traQ rejected stamps request"
+            );
+            super::Error::RequestSendError
+        })?
+        .json()
+        .await
+        .map_err(|e| {
+            tracing::warn!(error = &e as &dyn std::error::Error);
+            super::Error::ParseError
+        })?;
+
+    let registry: &super::ReactionKindRegistry = ctx.as_ref();
+    let mut kinds = registry.0.write().await;
+    let before = kinds.len();
+    for stamp in stamps {
+        let image_url = format!("https://{traq_host}/api/v3/files/{}", stamp.file_id);
+        kinds
+            .entry(stamp.name.clone())
+            .or_insert_with(|| super::ReactionKind {
+                name: stamp.name,
+                image_url: Some(image_url),
+            });
+    }
+    tracing::info!(added = kinds.len() - before, "Synced traQ stamps");
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! メッセージ・リアクションの保持期間と、期限切れの掃除
+
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::IntoStatus;
+
+pub mod error;
+mod r#impl;
+
+pub use error::Error;
+
+/// 保持期間
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lifetime {
+    /// 期限切れにならない
+    Forever,
+    Seconds(u64),
+}
+
+/// エンティティごとの保持期間の設定
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionPolicy {
+    pub messages: MessageRetention,
+    pub reactions: ReactionRetention,
+    pub purge: PurgePolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageRetention {
+    pub lifetime: Lifetime,
+    /// 本文1文字ごとに延長する秒数
+    #[serde(default)]
+    pub extension_per_char: u64,
+    /// 本文による延長の上限(秒)
+    #[serde(default)]
+    pub max_extension: u64,
+    /// traQと同期されたメッセージの保持期間; `None`なら`lifetime`と同じ
+    #[serde(default)]
+    pub relayed_lifetime: Option<Lifetime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionRetention {
+    pub lifetime: Lifetime,
+}
+
+/// 期限切れの行の削除
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgePolicy {
+    /// 期限切れから削除までの猶予(秒); この間は履歴から参照できる
+    pub grace_period: u64,
+    /// 掃除の間隔(秒)
+    pub interval: u64,
+    /// 1回のクエリで削除する最大件数
+    pub batch_size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct StartJanitorParams {}
+
+pub trait RetentionService<Context>: Send + Sync + 'static {
+    type Error: IntoStatus;
+
+    /// 定期的に期限切れを通知し、猶予を過ぎたものを削除するタスクをspawnする
+    /// 通知は`Event::MessageExpired`, `Event::ReactionExpired`
+    fn start_janitor(
+        &self,
+        ctx: Arc<Context>,
+        params: StartJanitorParams,
+    ) -> BoxFuture<'_, Result<(), Self::Error>>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetentionServiceImpl;
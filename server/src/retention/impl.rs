@@ -0,0 +1,261 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    event::ProvideEventService, message::ProvideMessageService, prelude::IntoStatus,
+    reaction::ProvideReactionService,
+};
+
+/// MySQLの`TIMESTAMP`で表せる最大の日時; 期限切れにならないものに使う
+fn forever() -> DateTime<Utc> {
+    DateTime::from_timestamp(i32::MAX as i64, 0).expect("valid timestamp")
+}
+
+impl super::Lifetime {
+    /// `created_at`から`extension`秒延長した期限
+    fn expires_at(self, created_at: DateTime<Utc>, extension: u64) -> DateTime<Utc> {
+        let Self::Seconds(seconds) = self else {
+            return forever();
+        };
+        let seconds = seconds.saturating_add(extension);
+        i64::try_from(seconds)
+            .ok()
+            .and_then(chrono::TimeDelta::try_seconds)
+            .and_then(|delta| created_at.checked_add_signed(delta))
+            .map_or_else(forever, |expires_at| expires_at.min(forever()))
+    }
+}
+
+impl super::MessageRetention {
+    /// `relayed`はtraQと同期されたかどうか
+    pub fn expires_at(
+        &self,
+        created_at: DateTime<Utc>,
+        content: &str,
+        relayed: bool,
+    ) -> DateTime<Utc> {
+        let lifetime = match self.relayed_lifetime {
+            Some(lifetime) if relayed => lifetime,
+            _ => self.lifetime,
+        };
+        let extension = (content.chars().count() as u64)
+            .saturating_mul(self.extension_per_char)
+            .min(self.max_extension);
+        lifetime.expires_at(created_at, extension)
+    }
+}
+
+impl super::ReactionRetention {
+    pub fn expires_at(&self, created_at: DateTime<Utc>) -> DateTime<Utc> {
+        self.lifetime.expires_at(created_at, 0)
+    }
+}
+
+impl super::RetentionPolicy {
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+impl Default for super::RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            messages: super::MessageRetention {
+                lifetime: super::Lifetime::Seconds(24 * 60 * 60),
+                extension_per_char: 0,
+                max_extension: 0,
+                relayed_lifetime: None,
+            },
+            reactions: super::ReactionRetention {
+                lifetime: super::Lifetime::Seconds(10),
+            },
+            purge: super::PurgePolicy {
+                grace_period: 7 * 24 * 60 * 60,
+                interval: 10,
+                batch_size: 1000,
+            },
+        }
+    }
+}
+
+impl<Context> super::RetentionService<Context> for super::RetentionServiceImpl
+where
+    Context: AsRef<super::RetentionPolicy>
+        + AsRef<crate::task::TaskManager>
+        + ProvideEventService
+        + ProvideMessageService
+        + ProvideReactionService,
+{
+    type Error = super::Error;
+
+    fn start_janitor(
+        &self,
+        ctx: Arc<Context>,
+        params: super::StartJanitorParams,
+    ) -> futures::future::BoxFuture<'_, Result<(), Self::Error>> {
+        start_janitor(ctx, params).boxed()
+    }
+}
+
+async fn start_janitor<Context>(
+    ctx: Arc<Context>,
+    _params: super::StartJanitorParams,
+) -> Result<(), super::Error>
+where
+    Context: AsRef<super::RetentionPolicy>
+        + AsRef<crate::task::TaskManager>
+        + ProvideEventService
+        + ProvideMessageService
+        + ProvideReactionService,
+{
+    let ctx_clone = Arc::clone(&ctx);
+    let task_manager: &crate::task::TaskManager = (*ctx_clone).as_ref();
+    task_manager
+        .spawn(|cancellation_token| run_janitor(ctx, cancellation_token))
+        .await;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn run_janitor<Context>(ctx: Arc<Context>, cancellation_token: CancellationToken)
+where
+    Context: AsRef<super::RetentionPolicy>
+        + ProvideEventService
+        + ProvideMessageService
+        + ProvideReactionService,
+{
+    let policy: &super::RetentionPolicy = (*ctx).as_ref();
+    let mut interval = tokio::time::interval(Duration::from_secs(policy.purge.interval.max(1)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // 起動前に期限切れになったものは、購読者がいないので通知しない
+    let mut notified_until = Utc::now();
+
+    loop {
+        tokio::select! {
+            () = cancellation_token.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+        let now = Utc::now();
+        match notify_expired(&*ctx, notified_until, now).await {
+            Ok(()) => notified_until = now,
+            Err(err) => tracing::error!(error = %err, "Failed to notify expired entities"),
+        }
+        if let Err(err) = purge_expired(&*ctx, &policy.purge, now).await {
+            tracing::error!(error = %err, "Failed to purge expired entities");
+        }
+    }
+}
+
+/// `since`から`until`までに期限切れになったものを通知する
+async fn notify_expired<Context>(
+    ctx: &Context,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<(), super::Error>
+where
+    Context: ProvideEventService + ProvideMessageService + ProvideReactionService,
+{
+    let messages = ctx
+        .get_expired_messages(crate::message::GetExpiredMessagesParams {
+            since: since.into(),
+            until: until.into(),
+        })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    let reactions = ctx
+        .get_expired_reactions(crate::reaction::GetExpiredReactionsParams {
+            since: since.into(),
+            until: until.into(),
+        })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    let events = messages
+        .into_iter()
+        .map(crate::event::Event::MessageExpired)
+        .chain(
+            reactions
+                .into_iter()
+                .map(crate::event::Event::ReactionExpired),
+        );
+    for event in events {
+        ctx.publish_event(event)
+            .await
+            .map_err(IntoStatus::into_status)?;
+    }
+    Ok(())
+}
+
+/// 猶予を過ぎたものを`batch_size`件ずつ削除する
+async fn purge_expired<Context>(
+    ctx: &Context,
+    policy: &super::PurgePolicy,
+    now: DateTime<Utc>,
+) -> Result<(), super::Error>
+where
+    Context: ProvideMessageService + ProvideReactionService,
+{
+    let grace_period = i64::try_from(policy.grace_period)
+        .ok()
+        .and_then(chrono::TimeDelta::try_seconds)
+        .unwrap_or(chrono::TimeDelta::MAX);
+    let Some(expired_before) = now.checked_sub_signed(grace_period) else {
+        return Ok(());
+    };
+    let limit = policy.batch_size.max(1);
+
+    let mut messages = 0;
+    loop {
+        let purged = ctx
+            .purge_expired_messages(crate::message::PurgeExpiredMessagesParams {
+                expired_before: expired_before.into(),
+                limit,
+            })
+            .await
+            .map_err(IntoStatus::into_status)?;
+        messages += purged;
+        if purged < u64::from(limit) {
+            break;
+        }
+    }
+    let mut reactions = 0;
+    loop {
+        let purged = ctx
+            .purge_expired_reactions(crate::reaction::PurgeExpiredReactionsParams {
+                expired_before: expired_before.into(),
+                limit,
+            })
+            .await
+            .map_err(IntoStatus::into_status)?;
+        reactions += purged;
+        if purged < u64::from(limit) {
+            break;
+        }
+    }
+    if messages > 0 || reactions > 0 {
+        tracing::info!(messages, reactions, "Purged expired entities");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_message_expires_at() {
+    let retention = super::MessageRetention {
+        lifetime: super::Lifetime::Seconds(60),
+        extension_per_char: 10,
+        max_extension: 30,
+        relayed_lifetime: Some(super::Lifetime::Forever),
+    };
+    let created_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+    let after = |seconds| created_at + chrono::TimeDelta::seconds(seconds);
+    assert_eq!(retention.expires_at(created_at, "", false), after(60));
+    assert_eq!(retention.expires_at(created_at, "あい", false), after(80));
+    assert_eq!(
+        retention.expires_at(created_at, "long message", false),
+        after(90)
+    );
+    assert_eq!(retention.expires_at(created_at, "", true), forever());
+}
@@ -17,6 +17,26 @@ pub mod other;
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FrontendDistDir(pub String);
 
+/// `/ws`でprotobufのバイナリフレームを使う場合のサブプロトコル
+///
+/// 指定されなかった場合はJSONのテキストフレームを使う
+pub const WS_PROTOBUF_PROTOCOL: &str = "protobuf";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum WsEncoding {
+    Json,
+    Protobuf,
+}
+
+impl WsEncoding {
+    fn negotiated(ws: &ws::WebSocket) -> Self {
+        match ws.protocol() {
+            Some(p) if p == WS_PROTOBUF_PROTOCOL => Self::Protobuf,
+            _ => Self::Json,
+        }
+    }
+}
+
 pub fn make<State>(state: Arc<State>) -> Router<()>
 where
     State: grpc::Requirements + other::Requirements,
@@ -74,7 +94,7 @@ fn grpc_routes<State: grpc::Requirements>(state: Arc<State>) -> Router<()> {
         };
     }
 
-    services! { world; user; reaction; message; speaker_phone; }
+    services! { world; user; reaction; message; notification; speaker_phone; explore; session; api_token; }
     let traq_auth = tonic_web::enable(crate::traq::auth::build_server(Arc::clone(&state)))
         .map_request(|r: http::Request<AxumBody>| {
             r.map(|b| {
@@ -84,7 +104,7 @@ fn grpc_routes<State: grpc::Requirements>(state: Arc<State>) -> Router<()> {
         });
     let trace_layer = TraceLayer::new_for_grpc();
     let session_layer = crate::session::build_grpc_layer(state);
-    route_services!(Router::new(); [ world, user, reaction, message, speaker_phone ])
+    route_services!(Router::new(); [ world, user, reaction, message, notification, speaker_phone, explore, session, api_token ])
         .layer(session_layer)
         .route_service(
             &format!("/{}/{{*res}}", crate::traq::auth::SERVICE_NAME),
@@ -104,11 +124,16 @@ fn other_routes<State: other::Requirements>(state: Arc<State>) -> Router<()> {
     let serve_dir: &FrontendDistDir = (*state).as_ref();
     let serve_dir = tower_http::services::ServeDir::new(&serve_dir.0);
     let layer = ServiceBuilder::new().layer(TraceLayer::new_for_http());
-    Router::new()
+    let mut router = Router::new()
         .route("/ping", routing::get(|| async { "pong".to_string() }))
         .route("/oauth2/redirect", routing::get(handle_redirect))
         .route("/ws", routing::get(handle_ws))
-        .route_service("/bot", bot)
+        .route_service("/bot", bot);
+    let auth_mode: &crate::session::AuthMode = (*state).as_ref();
+    if *auth_mode == crate::session::AuthMode::Dev {
+        router = router.route("/dev/login", routing::get(handle_dev_login));
+    }
+    router
         .with_state(state)
         .fallback_service(serve_dir)
         .layer(layer)
@@ -123,8 +148,11 @@ where
     AppState: other::Requirements,
 {
     let req = req.map(|_| ());
-    let res = match state.oauth2_handle_redirect(&req).await {
-        Ok(user) => user,
+    let crate::traq::auth::OAuth2Redirected {
+        user: res,
+        cookies: state_cookies,
+    } = match state.oauth2_handle_redirect(&req).await {
+        Ok(redirected) => redirected,
         Err(e) => return e.into_response(),
     };
 
@@ -155,6 +183,49 @@ where
         }
     };
 
+    let headers: http::HeaderMap = [(http::header::LOCATION, "/".parse().unwrap())]
+        .into_iter()
+        .collect();
+    (http::StatusCode::FOUND, headers, state_cookies, jar).into_response()
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct DevLoginQuery {
+    name: Option<String>,
+}
+
+/// `/dev/login?name=...`; traQを使わずにログインする
+#[tracing::instrument(skip_all)]
+async fn handle_dev_login<AppState>(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<DevLoginQuery>,
+    headers: http::HeaderMap,
+) -> Response
+where
+    AppState: other::Requirements,
+{
+    let name = query
+        .name
+        .unwrap_or_else(|| crate::dev_auth::DEFAULT_USER_NAME.to_string());
+    let user = match state
+        .dev_login(crate::dev_auth::DevLoginParams { name })
+        .await
+    {
+        Ok(u) => u,
+        Err(e) => return e.into_response(),
+    };
+    let save_params = crate::session::SaveParams {
+        header_map: &headers,
+        user_id: user.id,
+    };
+    let jar = match state.save(save_params).await {
+        Ok(j) => j,
+        Err(e) => {
+            tracing::error!(error = &e as &dyn std::error::Error);
+            return http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
     let headers: http::HeaderMap = [(http::header::LOCATION, "/".parse().unwrap())]
         .into_iter()
         .collect();
@@ -171,6 +242,12 @@ where
 {
     let user_id = state.extract(ExtractParams(&headers)).await;
     let user_id = match user_id {
+        Ok(crate::session::Session {
+            scopes: Some(scopes),
+            ..
+        }) if !scopes.contains(crate::api_token::ApiTokenScope::Explore) => {
+            return http::StatusCode::FORBIDDEN.into_response();
+        }
         Ok(session) => session.user_id,
         Err(e) => {
             tracing::warn!(
@@ -180,7 +257,8 @@ where
             return http::StatusCode::UNAUTHORIZED.into_response();
         }
     };
-    ws.on_upgrade(move |socket| handle_websocket(socket, user_id, state))
+    ws.protocols([WS_PROTOBUF_PROTOCOL])
+        .on_upgrade(move |socket| handle_websocket(socket, user_id, state))
 }
 
 #[tracing::instrument(skip_all)]
@@ -191,6 +269,8 @@ async fn handle_websocket<AppState: other::Requirements>(
 ) {
     use futures::StreamExt;
 
+    let encoding = WsEncoding::negotiated(&ws);
+    tracing::debug!(?encoding, "Negotiated websocket encoding");
     let (ws_tx, ws_rx) = ws.split();
     let (field_tx, field_rx) = tokio::sync::mpsc::channel(2);
     let field_sink = tokio_util::sync::PollSender::new(field_tx);
@@ -202,11 +282,11 @@ async fn handle_websocket<AppState: other::Requirements>(
     let close = Arc::new(Notify::new());
     let close2 = Arc::clone(&close);
     let send = async move {
-        let r = ws_message_to_field(ws_rx, field_sink).await;
+        let r = ws_message_to_field(ws_rx, field_sink, encoding).await;
         close.notify_one();
         r
     };
-    let recv = events_to_ws_message(events_stream, ws_tx, close2);
+    let recv = events_to_ws_message(events_stream, ws_tx, close2, encoding);
     match tokio::join!(send, recv) {
         (Ok(()), Ok(())) => tracing::info!("Finish websocket session cleanly"),
         (Err(e), Ok(())) => tracing::error!(error = ?e, "Sending error"),
@@ -219,7 +299,11 @@ async fn handle_websocket<AppState: other::Requirements>(
 }
 
 #[tracing::instrument(skip_all)]
-async fn ws_message_to_field<M, F>(mut message: M, mut field: F) -> anyhow::Result<()>
+async fn ws_message_to_field<M, F>(
+    mut message: M,
+    mut field: F,
+    encoding: WsEncoding,
+) -> anyhow::Result<()>
 where
     M: futures::TryStream<Ok = ws::Message> + Send + Unpin,
     M::Error: std::error::Error + Send + Sync + 'static,
@@ -234,16 +318,30 @@ where
         .await
         .context("Failed to receive WebSocket message")?
     {
-        match msg {
-            ws::Message::Text(text) => {
+        match (msg, encoding) {
+            (ws::Message::Text(text), WsEncoding::Json) => {
                 let text = text.as_str();
                 let f: crate::explore::ExplorationField =
                     serde_json::from_str(text).context("Failed to parse message")?;
                 field.send(f).await.context("Failed to send message")?;
             }
-            ws::Message::Binary(_) => anyhow::bail!("Received unexpected binary message"),
-            ws::Message::Ping(_) | ws::Message::Pong(_) => continue,
-            ws::Message::Close(_) => break,
+            (ws::Message::Binary(data), WsEncoding::Protobuf) => {
+                use prost::Message;
+
+                let f = schema::explore::ExplorationField::decode(data)
+                    .context("Failed to decode message")?;
+                let f = crate::explore::ExplorationField::try_from(f)
+                    .context("Failed to parse message")?;
+                field.send(f).await.context("Failed to send message")?;
+            }
+            (ws::Message::Text(_), WsEncoding::Protobuf) => {
+                anyhow::bail!("Received unexpected text message")
+            }
+            (ws::Message::Binary(_), WsEncoding::Json) => {
+                anyhow::bail!("Received unexpected binary message")
+            }
+            (ws::Message::Ping(_) | ws::Message::Pong(_), _) => continue,
+            (ws::Message::Close(_), _) => break,
         }
     }
     tracing::debug!("Finish");
@@ -255,6 +353,7 @@ async fn events_to_ws_message<E, M>(
     mut events: E,
     mut message: M,
     close: Arc<Notify>,
+    encoding: WsEncoding,
 ) -> anyhow::Result<()>
 where
     E: futures::TryStream<Ok = crate::explore::ExplorationFieldEvents> + Send + Unpin,
@@ -276,12 +375,20 @@ where
         let Some(events) = events else {
             break;
         };
-        let msg_text = serde_json::to_string(&events).context("Failed to serialize JSON")?;
-        let msg = ws::Message::text(msg_text);
-        message
-            .send(msg)
-            .await
-            .context("Failed to send text message")?;
+        let msg = match encoding {
+            WsEncoding::Json => {
+                let msg_text =
+                    serde_json::to_string(&events).context("Failed to serialize JSON")?;
+                ws::Message::text(msg_text)
+            }
+            WsEncoding::Protobuf => {
+                use prost::Message;
+
+                let events = schema::explore::ExplorationFieldEvents::from(events);
+                ws::Message::binary(events.encode_to_vec())
+            }
+        };
+        message.send(msg).await.context("Failed to send message")?;
     }
 
     message
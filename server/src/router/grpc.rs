@@ -1,21 +1,33 @@
 pub trait Requirements:
     crate::world::ProvideWorldService
     + crate::traq::auth::ProvideTraqAuthService
+    + crate::traq::user::ProvideTraqUserService
+    + AsRef<crate::session::AuthMode>
     + crate::user::ProvideUserService
     + crate::session::ProvideSessionService
+    + crate::api_token::ProvideApiTokenService
     + crate::reaction::ProvideReactionService
+    + crate::reaction::kind::ProvideReactionKindService
     + crate::message::ProvideMessageService
+    + crate::notification::ProvideNotificationService
     + crate::speaker_phone::ProvideSpeakerPhoneService
+    + crate::explore::ProvideExploreService
 {
 }
 
 impl<T> Requirements for T where
     T: crate::world::ProvideWorldService
         + crate::traq::auth::ProvideTraqAuthService
+        + crate::traq::user::ProvideTraqUserService
+        + AsRef<crate::session::AuthMode>
         + crate::user::ProvideUserService
         + crate::session::ProvideSessionService
+        + crate::api_token::ProvideApiTokenService
         + crate::reaction::ProvideReactionService
+        + crate::reaction::kind::ProvideReactionKindService
         + crate::message::ProvideMessageService
+        + crate::notification::ProvideNotificationService
         + crate::speaker_phone::ProvideSpeakerPhoneService
+        + crate::explore::ProvideExploreService
 {
 }
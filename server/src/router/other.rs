@@ -6,6 +6,8 @@ pub trait Requirements:
     + crate::traq::bot::ProvideTraqBotService
     + AsRef<crate::traq::bot::TraqBotConfig>
     + AsRef<super::FrontendDistDir>
+    + crate::dev_auth::ProvideDevAuthService
+    + AsRef<crate::session::AuthMode>
 {
 }
 
@@ -17,5 +19,7 @@ impl<T> Requirements for T where
         + crate::traq::bot::ProvideTraqBotService
         + AsRef<crate::traq::bot::TraqBotConfig>
         + AsRef<super::FrontendDistDir>
+        + crate::dev_auth::ProvideDevAuthService
+        + AsRef<crate::session::AuthMode>
 {
 }
@@ -1,6 +1,7 @@
 //! HTTP セッション管理
 
 pub mod error;
+pub mod grpc;
 pub mod r#impl;
 pub mod layer;
 
@@ -9,7 +10,7 @@ use std::sync::Arc;
 use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 
-use crate::prelude::IntoStatus;
+use crate::prelude::{IntoStatus, Timestamp};
 
 pub use axum_extra::extract::cookie::PrivateCookieJar;
 pub use error::Error;
@@ -20,11 +21,50 @@ pub struct SessionName(pub String);
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct CookieDomain(pub String);
 
+/// cookieに`Secure`属性を付けるか; 平文のHTTPで開発するときのみ`false`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct CookieSecure(pub bool);
+
+/// ログインに使う認証の方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuthMode {
+    #[default]
+    Traq,
+    /// traQを使わず、名前だけでローカルのユーザーとしてログインする; 開発用
+    Dev,
+}
+
+/// セッションの有効期間(秒); 最後のアクセスから数える
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct SessionLifetime(pub u64);
+
+/// cookieに保存する推測できないID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct SessionId(pub uuid::Uuid);
+
 pub struct ExtractParams<'a>(pub &'a http::HeaderMap);
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct Session {
+    /// APIトークンで認証された場合はトークンのID
+    pub id: SessionId,
+    pub user_id: crate::user::UserId,
+    /// APIトークンで認証された場合のスコープ; cookieなら`None`
+    pub scopes: Option<crate::api_token::ApiTokenScopes>,
+}
+
+/// 一覧に表示するセッションの情報
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub id: SessionId,
     pub user_id: crate::user::UserId,
+    pub user_agent: String,
+    pub created_at: Timestamp,
+    pub last_seen_at: Timestamp,
+    pub expires_at: Timestamp,
 }
 
 #[derive(Debug, Clone)]
@@ -33,19 +73,70 @@ pub struct SaveParams<'a> {
     pub header_map: &'a http::HeaderMap,
 }
 
+#[derive(Debug, Clone)]
+pub struct LogoutParams<'a> {
+    pub header_map: &'a http::HeaderMap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ListSessionsParams {
+    pub user_id: crate::user::UserId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct RevokeSessionParams {
+    /// セッションの持ち主でなければならない
+    pub user_id: crate::user::UserId,
+    pub id: SessionId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct RevokeOtherSessionsParams {
+    pub user_id: crate::user::UserId,
+    /// 無効にしないセッション
+    pub current: SessionId,
+}
+
 pub trait SessionService<Context>: Send + Sync + 'static {
     type Error: IntoStatus;
 
+    /// `Authorization: Bearer`があればAPIトークンで、なければcookieで認証する
+    ///
+    /// 無効にされた・期限切れのセッションは`Unauthorized`
     fn extract<'a>(
         &'a self,
         ctx: &'a Context,
         params: ExtractParams<'a>,
     ) -> BoxFuture<'a, Result<Session, Self::Error>>;
+    /// 新しいセッションを作成して、cookieに保存する
     fn save<'a>(
         &'a self,
         ctx: &'a Context,
         params: SaveParams,
     ) -> BoxFuture<'a, Result<PrivateCookieJar, Self::Error>>;
+    /// セッションを無効にして、cookieを削除する
+    fn logout<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: LogoutParams<'a>,
+    ) -> BoxFuture<'a, Result<PrivateCookieJar, Self::Error>>;
+    /// 有効なセッション; 新しい順
+    fn list_sessions<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: ListSessionsParams,
+    ) -> BoxFuture<'a, Result<Vec<SessionInfo>, Self::Error>>;
+    fn revoke_session<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: RevokeSessionParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>>;
+    /// 無効にした数を返す
+    fn revoke_other_sessions<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: RevokeOtherSessionsParams,
+    ) -> BoxFuture<'a, Result<u64, Self::Error>>;
 }
 
 #[allow(clippy::type_complexity)]
@@ -77,11 +168,63 @@ pub trait ProvideSessionService: Send + Sync + 'static {
         let ctx = self.context();
         self.session_service().save(ctx, params)
     }
+
+    fn logout<'a>(
+        &'a self,
+        params: LogoutParams<'a>,
+    ) -> BoxFuture<
+        'a,
+        Result<PrivateCookieJar, <Self::SessionService as SessionService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.session_service().logout(ctx, params)
+    }
+
+    fn list_sessions(
+        &self,
+        params: ListSessionsParams,
+    ) -> BoxFuture<
+        '_,
+        Result<Vec<SessionInfo>, <Self::SessionService as SessionService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.session_service().list_sessions(ctx, params)
+    }
+
+    fn revoke_session(
+        &self,
+        params: RevokeSessionParams,
+    ) -> BoxFuture<'_, Result<(), <Self::SessionService as SessionService<Self::Context>>::Error>>
+    {
+        let ctx = self.context();
+        self.session_service().revoke_session(ctx, params)
+    }
+
+    fn revoke_other_sessions(
+        &self,
+        params: RevokeOtherSessionsParams,
+    ) -> BoxFuture<'_, Result<u64, <Self::SessionService as SessionService<Self::Context>>::Error>>
+    {
+        let ctx = self.context();
+        self.session_service().revoke_other_sessions(ctx, params)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct SessionServiceImpl;
 
+pub fn build_server<State>(state: Arc<State>) -> SessionServiceServer<State>
+where
+    State: ProvideSessionService,
+{
+    SessionServiceServer::new(grpc::ServiceImpl::new(state))
+}
+
+pub type SessionServiceServer<State> =
+    schema::session::session_service_server::SessionServiceServer<grpc::ServiceImpl<State>>;
+
+pub use schema::session::session_service_server::SERVICE_NAME;
+
 // extract できなかったら Unauthorized を返すレイヤー
 pub fn build_http_layer<State>(state: Arc<State>) -> layer::SessionLayer<State, layer::HTTP>
 where
@@ -1,13 +1,28 @@
-#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Unauthorized")]
     Unauthorized,
+    #[error("Not found")]
+    NotFound,
+    #[error("Database error")]
+    Sqlx(#[from] sqlx::Error),
+    #[error(transparent)]
+    Status(#[from] tonic::Status),
 }
 
 impl From<Error> for tonic::Status {
     fn from(value: Error) -> Self {
         match value {
             Error::Unauthorized => tonic::Status::unauthenticated("Unauthorized"),
+            Error::NotFound => tonic::Status::not_found("Not found"),
+            Error::Sqlx(e) => {
+                tracing::error!(error = &e as &dyn std::error::Error);
+                tonic::Status::internal("Database error")
+            }
+            Error::Status(s) => {
+                tracing::error!(error = &s as &dyn std::error::Error, "Unexpected");
+                s
+            }
         }
     }
 }
@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use axum::response::IntoResponse;
+use uuid::Uuid;
+
+use crate::prelude::IntoStatus;
+
+// MARK: type conversions
+
+impl super::SessionInfo {
+    fn into_schema(self, current: super::SessionId) -> schema::session::Session {
+        let super::SessionInfo {
+            id,
+            user_id: _,
+            user_agent,
+            created_at,
+            last_seen_at,
+            expires_at,
+        } = self;
+        schema::session::Session {
+            id: id.0.to_string(),
+            user_agent,
+            created_at: Some(created_at.into()),
+            last_seen_at: Some(last_seen_at.into()),
+            expires_at: Some(expires_at.into()),
+            current: id == current,
+        }
+    }
+}
+
+// MARK: ServiceImpl
+
+pub struct ServiceImpl<State> {
+    state: Arc<State>,
+}
+
+impl<State> Clone for ServiceImpl<State>
+where
+    State: super::ProvideSessionService,
+{
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<State> ServiceImpl<State>
+where
+    State: super::ProvideSessionService,
+{
+    pub(super) fn new(state: Arc<State>) -> Self {
+        Self { state }
+    }
+}
+
+#[async_trait::async_trait]
+impl<State> schema::session::session_service_server::SessionService for ServiceImpl<State>
+where
+    State: super::ProvideSessionService,
+{
+    async fn logout(
+        &self,
+        request: tonic::Request<schema::session::LogoutRequest>,
+    ) -> Result<tonic::Response<schema::session::LogoutResponse>, tonic::Status> {
+        let (meta, _, schema::session::LogoutRequest {}) = request.into_parts();
+        let header_map = meta.into_headers();
+        let cookies = self
+            .state
+            .logout(super::LogoutParams {
+                header_map: &header_map,
+            })
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let mut res = tonic::Response::new(schema::session::LogoutResponse {});
+        // cookieの削除をSet-Cookieで返す
+        let (parts, _) = cookies.into_response().into_parts();
+        *res.metadata_mut() = tonic::metadata::MetadataMap::from_headers(parts.headers);
+        Ok(res)
+    }
+
+    async fn list_sessions(
+        &self,
+        request: tonic::Request<schema::session::ListSessionsRequest>,
+    ) -> Result<tonic::Response<schema::session::ListSessionsResponse>, tonic::Status> {
+        let (meta, _, schema::session::ListSessionsRequest {}) = request.into_parts();
+        let header_map = meta.into_headers();
+        let session = self
+            .state
+            .extract(super::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let sessions = self
+            .state
+            .list_sessions(super::ListSessionsParams {
+                user_id: session.user_id,
+            })
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let res = schema::session::ListSessionsResponse {
+            sessions: sessions
+                .into_iter()
+                .map(|s| s.into_schema(session.id))
+                .collect(),
+        };
+        Ok(tonic::Response::new(res))
+    }
+
+    async fn revoke_session(
+        &self,
+        request: tonic::Request<schema::session::RevokeSessionRequest>,
+    ) -> Result<tonic::Response<schema::session::RevokeSessionResponse>, tonic::Status> {
+        let (meta, _, schema::session::RevokeSessionRequest { id }) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(super::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let params = super::RevokeSessionParams {
+            user_id,
+            id: super::SessionId(
+                Uuid::parse_str(&id)
+                    .map_err(|_| tonic::Status::invalid_argument("Invalid UUID"))?,
+            ),
+        };
+        self.state
+            .revoke_session(params)
+            .await
+            .map_err(IntoStatus::into_status)?;
+        Ok(tonic::Response::new(
+            schema::session::RevokeSessionResponse {},
+        ))
+    }
+
+    async fn revoke_other_sessions(
+        &self,
+        request: tonic::Request<schema::session::RevokeOtherSessionsRequest>,
+    ) -> Result<tonic::Response<schema::session::RevokeOtherSessionsResponse>, tonic::Status> {
+        let (meta, _, schema::session::RevokeOtherSessionsRequest {}) = request.into_parts();
+        let header_map = meta.into_headers();
+        let session = self
+            .state
+            .extract(super::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let revoked = self
+            .state
+            .revoke_other_sessions(super::RevokeOtherSessionsParams {
+                user_id: session.user_id,
+                current: session.id,
+            })
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let res = schema::session::RevokeOtherSessionsResponse {
+            revoked: revoked.try_into().unwrap_or(u32::MAX),
+        };
+        Ok(tonic::Response::new(res))
+    }
+}
@@ -2,13 +2,30 @@ use axum_extra::extract::{
     cookie::{Cookie, Key},
     PrivateCookieJar,
 };
+use chrono::{DateTime, Utc};
 use futures::FutureExt as _;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, MySqlPool};
+use uuid::Uuid;
 
-use super::{CookieDomain, SessionName};
+use super::{CookieDomain, CookieSecure, SessionLifetime, SessionName};
+use crate::prelude::{IntoStatus, Timestamp};
+
+/// `last_seen_at`を更新する間隔(秒); アクセスの度に書き込まないように
+const LAST_SEEN_RESOLUTION: i64 = 60;
+
+/// `user_agent`列の長さ
+const USER_AGENT_MAX_LEN: usize = 511;
 
 impl<Context> super::SessionService<Context> for super::SessionServiceImpl
 where
-    Context: AsRef<Key> + AsRef<SessionName> + AsRef<CookieDomain>,
+    Context: AsRef<Key>
+        + AsRef<SessionName>
+        + AsRef<CookieDomain>
+        + AsRef<CookieSecure>
+        + AsRef<SessionLifetime>
+        + AsRef<MySqlPool>
+        + crate::api_token::ProvideApiTokenService,
 {
     type Error = super::Error;
 
@@ -17,11 +34,14 @@ where
         ctx: &'a Context,
         params: super::ExtractParams<'a>,
     ) -> futures::future::BoxFuture<'a, Result<super::Session, Self::Error>> {
+        if let Some(secret) = bearer_token(params.0) {
+            return extract_api_token(ctx, secret).boxed();
+        }
         let key: &Key = ctx.as_ref();
         let session_name: &SessionName = ctx.as_ref();
         let jar = PrivateCookieJar::from_headers(params.0, key.clone());
 
-        extract(jar, &session_name.0).boxed()
+        extract(ctx.as_ref(), ctx.as_ref(), jar, &session_name.0).boxed()
     }
 
     fn save<'a>(
@@ -33,35 +53,329 @@ where
         let session_name: &SessionName = ctx.as_ref();
         let domain: &CookieDomain = ctx.as_ref();
         let jar = PrivateCookieJar::from_headers(params.header_map, key.clone());
+        let user_agent = params
+            .header_map
+            .get(http::header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+
+        let cookie = CookieAttrs {
+            name: &session_name.0,
+            domain: &domain.0,
+            secure: ctx.as_ref(),
+        };
+
+        save(
+            ctx.as_ref(),
+            ctx.as_ref(),
+            jar,
+            cookie,
+            params.user_id,
+            user_agent,
+        )
+        .boxed()
+    }
+
+    fn logout<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::LogoutParams<'a>,
+    ) -> futures::future::BoxFuture<'a, Result<PrivateCookieJar, Self::Error>> {
+        let key: &Key = ctx.as_ref();
+        let session_name: &SessionName = ctx.as_ref();
+        let domain: &CookieDomain = ctx.as_ref();
+        let jar = PrivateCookieJar::from_headers(params.header_map, key.clone());
+
+        let cookie = CookieAttrs {
+            name: &session_name.0,
+            domain: &domain.0,
+            secure: ctx.as_ref(),
+        };
+
+        logout(ctx.as_ref(), jar, cookie).boxed()
+    }
+
+    fn list_sessions<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::ListSessionsParams,
+    ) -> futures::future::BoxFuture<'a, Result<Vec<super::SessionInfo>, Self::Error>> {
+        list_sessions(ctx.as_ref(), params).boxed()
+    }
+
+    fn revoke_session<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::RevokeSessionParams,
+    ) -> futures::future::BoxFuture<'a, Result<(), Self::Error>> {
+        revoke_session(ctx.as_ref(), params).boxed()
+    }
+
+    fn revoke_other_sessions<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::RevokeOtherSessionsParams,
+    ) -> futures::future::BoxFuture<'a, Result<u64, Self::Error>> {
+        revoke_other_sessions(ctx.as_ref(), params).boxed()
+    }
+}
+
+// MARK: DB operations
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
+struct SessionRow {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub user_agent: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl From<SessionRow> for super::SessionInfo {
+    fn from(value: SessionRow) -> Self {
+        Self {
+            id: super::SessionId(value.id),
+            user_id: crate::user::UserId(value.user_id),
+            user_agent: value.user_agent,
+            created_at: Timestamp(value.created_at),
+            last_seen_at: Timestamp(value.last_seen_at),
+            expires_at: Timestamp(value.expires_at),
+        }
+    }
+}
 
-        save(jar, &session_name.0, &domain.0, params.user_id).boxed()
+fn expires_at(lifetime: &SessionLifetime, now: DateTime<Utc>) -> DateTime<Utc> {
+    let lifetime = i64::try_from(lifetime.0)
+        .ok()
+        .and_then(chrono::TimeDelta::try_seconds)
+        .unwrap_or(chrono::TimeDelta::MAX);
+    now.checked_add_signed(lifetime)
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+/// cookieに保存されたセッションID
+fn session_id(cookie_jar: &PrivateCookieJar, session_name: &str) -> Option<super::SessionId> {
+    cookie_jar
+        .get(session_name)
+        .and_then(|cookie| cookie.value().parse().ok())
+        .map(super::SessionId)
+}
+
+/// セッションを保存するcookieの属性
+struct CookieAttrs<'a> {
+    name: &'a str,
+    domain: &'a str,
+    secure: &'a CookieSecure,
+}
+
+impl CookieAttrs<'_> {
+    fn build(&self, value: String) -> Cookie<'static> {
+        Cookie::build((self.name.to_string(), value))
+            .domain(self.domain.to_string())
+            .path("/")
+            .secure(self.secure.0)
+            .http_only(true)
+            .build()
     }
 }
 
 async fn extract(
+    pool: &MySqlPool,
+    lifetime: &SessionLifetime,
     cookie_jar: PrivateCookieJar,
     session_name: &str,
 ) -> Result<super::Session, super::Error> {
-    let user_id = cookie_jar
-        .get(session_name)
-        .and_then(|cookie| cookie.value().parse().ok())
-        .map(crate::user::UserId)
-        .ok_or(super::Error::Unauthorized)?;
+    let id = session_id(&cookie_jar, session_name).ok_or(super::Error::Unauthorized)?;
+    let row: SessionRow = sqlx::query_as(
+        r#"
+            SELECT * FROM `sessions`
+            WHERE `id` = ? AND `revoked_at` IS NULL AND `expires_at` > NOW()
+        "#,
+    )
+    .bind(id.0)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(super::Error::Unauthorized)?;
 
-    Ok(super::Session { user_id })
+    let now = Utc::now();
+    if now - row.last_seen_at >= chrono::TimeDelta::seconds(LAST_SEEN_RESOLUTION) {
+        sqlx::query("UPDATE `sessions` SET `last_seen_at` = ?, `expires_at` = ? WHERE `id` = ?")
+            .bind(now)
+            .bind(expires_at(lifetime, now))
+            .bind(id.0)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(super::Session {
+        id,
+        user_id: crate::user::UserId(row.user_id),
+        scopes: None,
+    })
+}
+
+/// `Authorization: Bearer <token>`のトークン
+fn bearer_token(header_map: &http::HeaderMap) -> Option<String> {
+    let value = header_map.get(http::header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("Bearer")
+        .then(|| token.trim().to_string())
+}
+
+async fn extract_api_token(
+    api_token_service: &impl crate::api_token::ProvideApiTokenService,
+    secret: String,
+) -> Result<super::Session, super::Error> {
+    let token = api_token_service
+        .authenticate_api_token(crate::api_token::AuthenticateApiTokenParams { secret })
+        .await
+        .map_err(IntoStatus::into_status)?
+        .ok_or(super::Error::Unauthorized)?;
+    Ok(super::Session {
+        id: super::SessionId(token.id.0),
+        user_id: token.user_id,
+        scopes: Some(token.scopes),
+    })
 }
 
 async fn save(
+    pool: &MySqlPool,
+    lifetime: &SessionLifetime,
     cookie_jar: PrivateCookieJar,
-    session_name: impl Into<String>,
-    domain: impl Into<String>,
+    cookie: CookieAttrs<'_>,
     user_id: crate::user::UserId,
+    mut user_agent: String,
 ) -> Result<PrivateCookieJar, super::Error> {
-    let cookie = Cookie::build((session_name.into(), user_id.0.to_string()))
-        .domain(domain.into())
-        .path("/")
-        .secure(true)
-        .http_only(true)
-        .to_owned();
-    Ok(cookie_jar.add(cookie))
+    // 期限切れ・無効にされたセッションは残しておく必要がない
+    sqlx::query(
+        r#"
+            DELETE FROM `sessions`
+            WHERE `user_id` = ? AND (`expires_at` <= NOW() OR `revoked_at` IS NOT NULL)
+        "#,
+    )
+    .bind(user_id.0)
+    .execute(pool)
+    .await?;
+
+    if user_agent.len() > USER_AGENT_MAX_LEN {
+        let mut end = USER_AGENT_MAX_LEN;
+        while !user_agent.is_char_boundary(end) {
+            end -= 1;
+        }
+        user_agent.truncate(end);
+    }
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    sqlx::query(
+        r#"
+            INSERT INTO `sessions` (`id`, `user_id`, `user_agent`, `created_at`, `last_seen_at`, `expires_at`)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(id)
+    .bind(user_id.0)
+    .bind(user_agent)
+    .bind(now)
+    .bind(now)
+    .bind(expires_at(lifetime, now))
+    .execute(pool)
+    .await?;
+    tracing::info!(user_id = %user_id.0, "Created a session");
+
+    Ok(cookie_jar.add(cookie.build(id.to_string())))
+}
+
+async fn logout(
+    pool: &MySqlPool,
+    cookie_jar: PrivateCookieJar,
+    cookie: CookieAttrs<'_>,
+) -> Result<PrivateCookieJar, super::Error> {
+    let id = session_id(&cookie_jar, cookie.name).ok_or(super::Error::Unauthorized)?;
+    sqlx::query(
+        "UPDATE `sessions` SET `revoked_at` = NOW() WHERE `id` = ? AND `revoked_at` IS NULL",
+    )
+    .bind(id.0)
+    .execute(pool)
+    .await?;
+    Ok(cookie_jar.remove(cookie.build(String::new())))
+}
+
+async fn list_sessions(
+    pool: &MySqlPool,
+    params: super::ListSessionsParams,
+) -> Result<Vec<super::SessionInfo>, super::Error> {
+    let super::ListSessionsParams { user_id } = params;
+    let rows: Vec<SessionRow> = sqlx::query_as(
+        r#"
+            SELECT * FROM `sessions`
+            WHERE `user_id` = ? AND `revoked_at` IS NULL AND `expires_at` > NOW()
+            ORDER BY `created_at` DESC
+        "#,
+    )
+    .bind(user_id.0)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+async fn revoke_session(
+    pool: &MySqlPool,
+    params: super::RevokeSessionParams,
+) -> Result<(), super::Error> {
+    let super::RevokeSessionParams { user_id, id } = params;
+    let result = sqlx::query(
+        r#"
+            UPDATE `sessions` SET `revoked_at` = NOW()
+            WHERE `id` = ? AND `user_id` = ? AND `revoked_at` IS NULL
+        "#,
+    )
+    .bind(id.0)
+    .bind(user_id.0)
+    .execute(pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(super::Error::NotFound);
+    }
+    tracing::info!(user_id = %user_id.0, "Revoked a session");
+    Ok(())
+}
+
+async fn revoke_other_sessions(
+    pool: &MySqlPool,
+    params: super::RevokeOtherSessionsParams,
+) -> Result<u64, super::Error> {
+    let super::RevokeOtherSessionsParams { user_id, current } = params;
+    let result = sqlx::query(
+        r#"
+            UPDATE `sessions` SET `revoked_at` = NOW()
+            WHERE `user_id` = ? AND `id` <> ? AND `revoked_at` IS NULL
+        "#,
+    )
+    .bind(user_id.0)
+    .bind(current.0)
+    .execute(pool)
+    .await?;
+    tracing::info!(
+        user_id = %user_id.0,
+        revoked = result.rows_affected(),
+        "Revoked other sessions"
+    );
+    Ok(result.rows_affected())
+}
+
+#[test]
+fn test_bearer_token() {
+    let mut header_map = http::HeaderMap::new();
+    assert_eq!(bearer_token(&header_map), None);
+    header_map.insert(
+        http::header::AUTHORIZATION,
+        "Bearer h24w14_abc".parse().unwrap(),
+    );
+    assert_eq!(bearer_token(&header_map).as_deref(), Some("h24w14_abc"));
+    header_map.insert(http::header::AUTHORIZATION, "Basic abc".parse().unwrap());
+    assert_eq!(bearer_token(&header_map), None);
 }
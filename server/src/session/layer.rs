@@ -103,7 +103,20 @@ where
         Box::pin(async move {
             let extract_params = super::ExtractParams(req.headers());
             match ctx.extract(extract_params).await {
-                Ok(super::Session { user_id }) => {
+                Ok(super::Session {
+                    user_id,
+                    scopes: Some(scopes),
+                    ..
+                }) => {
+                    // APIトークンはスコープに含まれるRPCのみ呼べる
+                    let allowed = crate::api_token::required_scope(req.uri().path())
+                        .is_some_and(|scope| scopes.contains(scope));
+                    if !allowed {
+                        return Ok(Kind::forbidden());
+                    }
+                    tracing::trace!(user_id = %user_id.0, "pass API token");
+                }
+                Ok(super::Session { user_id, .. }) => {
                     tracing::trace!(user_id = %user_id.0, "pass session");
                 }
                 Err(_) => return Ok(Kind::unauthorized()),
@@ -118,6 +131,7 @@ where
 
 pub trait ToResponse: private::Sealed {
     fn unauthorized() -> Response<Body>;
+    fn forbidden() -> Response<Body>;
 }
 #[derive(Debug, Clone, Copy, Default)]
 pub struct HTTP;
@@ -131,6 +145,13 @@ impl ToResponse for HTTP {
             .body(Body::empty())
             .unwrap()
     }
+
+    fn forbidden() -> Response<Body> {
+        Response::builder()
+            .status(http::StatusCode::FORBIDDEN)
+            .body(Body::empty())
+            .unwrap()
+    }
 }
 
 impl ToResponse for Grpc {
@@ -139,6 +160,12 @@ impl ToResponse for Grpc {
             .into_http()
             .map(Body::new)
     }
+
+    fn forbidden() -> Response<Body> {
+        tonic::Status::permission_denied("Insufficient API token scope")
+            .into_http()
+            .map(Body::new)
+    }
 }
 
 mod private {
@@ -3,11 +3,15 @@
 pub mod error;
 pub mod grpc;
 mod r#impl;
+mod relay;
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
 
 use crate::prelude::{IntoStatus, Timestamp};
 
@@ -31,10 +35,69 @@ pub struct SpeakerPhone {
     // r#type: SpeakerPhoneType,
     // name_type: SpeakerPhoneNameType,
     pub name: Channel,
+    /// 設置したユーザー; 所有者の記録以前に設置されたものは`None`
+    pub user_id: Option<crate::user::UserId>,
     pub created_at: Timestamp,
     pub updated_at: Timestamp,
 }
 
+/// SpeakerPhoneの設置に関する制限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct SpeakerPhoneLimits {
+    /// 1ユーザーが設置できる数
+    pub max_per_user: u32,
+    /// ある地点から半径`area_radius`の範囲に設置できる数
+    pub max_per_area: u32,
+    pub area_radius: u32,
+    /// 指定できる受信範囲(半径)の下限
+    pub min_receive_range: u32,
+    /// 指定できる受信範囲(半径)の上限
+    pub max_receive_range: u32,
+    /// 受信範囲が指定されなかったときの値
+    pub default_receive_range: u32,
+}
+
+/// SpeakerPhoneごとの中継タスクと、traQチャンネルの購読を管理する
+#[derive(Clone, Default)]
+pub struct SpeakerPhoneRelays {
+    inner: Arc<Mutex<SpeakerPhoneRelaysInner>>,
+}
+
+#[derive(Default)]
+struct SpeakerPhoneRelaysInner {
+    relays: HashMap<SpeakerPhoneId, Relay>,
+    /// 購読中のtraQチャンネル; 使うSpeakerPhoneがなくなればキャンセルする
+    channels: HashMap<crate::traq::channel::TraqChannelId, CancellationToken>,
+}
+
+struct Relay {
+    speaker_phone: watch::Sender<SpeakerPhone>,
+    cancel: CancellationToken,
+    status: RelayStatus,
+}
+
+/// SpeakerPhoneの中継タスクの状態
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct RelayStatus {
+    pub speaker_phone_id: SpeakerPhoneId,
+    /// 中継先として解決できたtraQチャンネル
+    pub channel: Option<crate::traq::channel::TraqChannel>,
+    pub state: RelayState,
+    /// 失敗して再起動した回数
+    pub restarts: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum RelayState {
+    Starting,
+    Running,
+    /// 再起動を待っている
+    Failed {
+        error: String,
+    },
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct GetSpeakerPhoneParams {
     pub id: SpeakerPhoneId,
@@ -48,13 +111,40 @@ pub struct GetSpeakerPhonesInAreaParams {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct CreateSpeakerPhoneParams {
+    pub user_id: crate::user::UserId,
     pub name: String,
     pub position: crate::world::Coordinate,
+    /// `None`なら[`SpeakerPhoneLimits::default_receive_range`]
+    pub receive_range: Option<u32>,
+}
+
+/// `None`のフィールドは変更しない
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct UpdateSpeakerPhoneParams {
+    pub id: SpeakerPhoneId,
+    /// 操作するユーザー; SpeakerPhoneの所有者でなければならない
+    pub user_id: crate::user::UserId,
+    pub position: Option<crate::world::Coordinate>,
+    pub name: Option<String>,
+    pub receive_range: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct DeleteSpeakerPhoneParams {
+    pub id: SpeakerPhoneId,
+    /// 操作するユーザー; SpeakerPhoneの所有者でなければならない
+    pub user_id: crate::user::UserId,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct LoadAllSpeakerPhonesParams {}
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct GetRelayStatusesParams {
+    /// 閲覧するユーザー; 管理者でなければならない
+    pub user_id: crate::user::UserId,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct GetAvailableChannelsParams {}
 
@@ -78,18 +168,40 @@ pub trait SpeakerPhoneService<Context>: Send + Sync + 'static {
     ) -> BoxFuture<'a, Result<Vec<SpeakerPhone>, Self::Error>>;
     /// DBに入れる + spawn subscribing event
     /// spawnはTaskManager, subscribeはEventService参照
+    /// [`SpeakerPhoneLimits`]を超える場合は設置しない
     fn create_speaker_phone<'a>(
         &'a self,
         ctx: &'a Context,
         params: CreateSpeakerPhoneParams,
     ) -> BoxFuture<'a, Result<SpeakerPhone, Self::Error>>;
+    /// 移動・チャンネルの付け替え・受信範囲の変更
+    /// 中継ループには`Event::SpeakerPhoneUpdated`で伝わる
+    fn update_speaker_phone<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: UpdateSpeakerPhoneParams,
+    ) -> BoxFuture<'a, Result<SpeakerPhone, Self::Error>>;
+    /// 中継ループには`Event::SpeakerPhoneDeleted`で伝わり、
+    /// チャンネルを使うSpeakerPhoneがなくなればBOTが退出する
+    fn delete_speaker_phone<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: DeleteSpeakerPhoneParams,
+    ) -> BoxFuture<'a, Result<SpeakerPhone, Self::Error>>;
     /// アプリ起動時の処理
-    /// 既存のspeaker_phone全てでspawn subscribing eventする
+    /// 既存のspeaker_phone全てで中継タスクをspawnし、以降の作成・変更・削除にも追従する
+    /// traQチャンネルも購読し、投稿されたメッセージをSpeakerPhoneの周囲に反映する
     fn load_all_speaker_phones(
         &self,
         ctx: Arc<Context>,
         params: LoadAllSpeakerPhonesParams,
     ) -> BoxFuture<'_, Result<(), Self::Error>>;
+    /// 中継タスクの状態の一覧
+    fn get_relay_statuses<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: GetRelayStatusesParams,
+    ) -> BoxFuture<'a, Result<Vec<RelayStatus>, Self::Error>>;
     fn get_available_channels<'a>(
         &'a self,
         ctx: &'a Context,
@@ -151,6 +263,47 @@ pub trait ProvideSpeakerPhoneService: Send + Sync + 'static {
         self.speaker_phone_service()
             .create_speaker_phone(ctx, params)
     }
+    fn update_speaker_phone(
+        &self,
+        params: UpdateSpeakerPhoneParams,
+    ) -> BoxFuture<
+        '_,
+        Result<
+            SpeakerPhone,
+            <Self::SpeakerPhoneService as SpeakerPhoneService<Self::Context>>::Error,
+        >,
+    > {
+        let ctx = self.context();
+        self.speaker_phone_service()
+            .update_speaker_phone(ctx, params)
+    }
+    fn delete_speaker_phone(
+        &self,
+        params: DeleteSpeakerPhoneParams,
+    ) -> BoxFuture<
+        '_,
+        Result<
+            SpeakerPhone,
+            <Self::SpeakerPhoneService as SpeakerPhoneService<Self::Context>>::Error,
+        >,
+    > {
+        let ctx = self.context();
+        self.speaker_phone_service()
+            .delete_speaker_phone(ctx, params)
+    }
+    fn get_relay_statuses(
+        &self,
+        params: GetRelayStatusesParams,
+    ) -> BoxFuture<
+        '_,
+        Result<
+            Vec<RelayStatus>,
+            <Self::SpeakerPhoneService as SpeakerPhoneService<Self::Context>>::Error,
+        >,
+    > {
+        let ctx = self.context();
+        self.speaker_phone_service().get_relay_statuses(ctx, params)
+    }
     fn get_available_channels(
         &self,
         params: GetAvailableChannelsParams,
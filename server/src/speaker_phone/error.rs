@@ -6,6 +6,20 @@ pub enum Error {
     BadPositionProvided,
     #[error("Bad channel name")]
     BadChannelProvided,
+    #[error("Bad receive range")]
+    BadReceiveRangeProvided,
+    #[error("Only the owner can modify the speaker phone")]
+    Forbidden,
+    #[error("Only admins can see relay statuses")]
+    AdminOnly,
+    #[error("Too many speaker phones placed by the user")]
+    UserQuotaExceeded,
+    #[error("Too many speaker phones in the area")]
+    AreaQuotaExceeded,
+    #[error("traQ channel not found")]
+    ChannelNotFound,
+    #[error("Subscription closed")]
+    SubscriptionClosed,
     #[error("Database error")]
     Sqlx(#[from] sqlx::Error),
     #[error(transparent)]
@@ -18,6 +32,21 @@ impl From<Error> for tonic::Status {
             Error::NotFound => tonic::Status::not_found("Not found"),
             Error::BadPositionProvided => tonic::Status::not_found("Bad position"),
             Error::BadChannelProvided => tonic::Status::invalid_argument("Bad channel name"),
+            Error::BadReceiveRangeProvided => tonic::Status::invalid_argument("Bad receive range"),
+            Error::Forbidden => {
+                tonic::Status::permission_denied("Only the owner can modify the speaker phone")
+            }
+            Error::AdminOnly => {
+                tonic::Status::permission_denied("Only admins can see relay statuses")
+            }
+            Error::UserQuotaExceeded => {
+                tonic::Status::resource_exhausted("Too many speaker phones placed by the user")
+            }
+            Error::AreaQuotaExceeded => {
+                tonic::Status::resource_exhausted("Too many speaker phones in the area")
+            }
+            Error::ChannelNotFound => tonic::Status::not_found("traQ channel not found"),
+            Error::SubscriptionClosed => tonic::Status::unavailable("Subscription closed"),
             Error::Sqlx(e) => {
                 tracing::error!(error = &e as &dyn std::error::Error);
                 tonic::Status::internal("Database error")
@@ -13,6 +13,7 @@ impl From<super::SpeakerPhone> for schema::SpeakerPhone {
             position,
             receive_range,
             name,
+            user_id,
             created_at,
             updated_at,
         } = value;
@@ -21,12 +22,38 @@ impl From<super::SpeakerPhone> for schema::SpeakerPhone {
             position: Some(position.into()),
             receive_range,
             name: name.0,
+            user_id: user_id.map(|id| id.0.to_string()),
             created_at: Some(created_at.into()),
             updated_at: Some(updated_at.into()),
         }
     }
 }
 
+impl From<super::RelayStatus> for schema::RelayStatus {
+    fn from(value: super::RelayStatus) -> Self {
+        use schema::relay_status::State;
+
+        let super::RelayStatus {
+            speaker_phone_id,
+            channel,
+            state,
+            restarts,
+        } = value;
+        let (state, error) = match state {
+            super::RelayState::Starting => (State::Starting, None),
+            super::RelayState::Running => (State::Running, None),
+            super::RelayState::Failed { error } => (State::Failed, Some(error)),
+        };
+        Self {
+            speaker_phone_id: speaker_phone_id.0.to_string(),
+            channel: channel.map(|channel| channel.path),
+            state: state.into(),
+            error,
+            restarts,
+        }
+    }
+}
+
 // MARK: ServiceImpl
 
 pub struct ServiceImpl<State> {
@@ -85,14 +112,31 @@ where
         &self,
         request: tonic::Request<schema::CreateSpeakerPhoneRequest>,
     ) -> Result<tonic::Response<schema::CreateSpeakerPhoneResponse>, tonic::Status> {
-        let (_, _, schema::CreateSpeakerPhoneRequest { position, name }) = request.into_parts();
+        let (
+            meta,
+            _,
+            schema::CreateSpeakerPhoneRequest {
+                position,
+                name,
+                receive_range,
+            },
+        ) = request.into_parts();
         let Some(position) = position else {
             return Err(tonic::Status::invalid_argument("Position is required"));
         };
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
 
         let params = super::CreateSpeakerPhoneParams {
+            user_id,
             name,
             position: position.into(),
+            receive_range,
         };
         let speaker_phone = self
             .state
@@ -106,6 +150,98 @@ where
         Ok(tonic::Response::new(res))
     }
 
+    async fn update_speaker_phone(
+        &self,
+        request: tonic::Request<schema::UpdateSpeakerPhoneRequest>,
+    ) -> Result<tonic::Response<schema::UpdateSpeakerPhoneResponse>, tonic::Status> {
+        let (
+            meta,
+            _,
+            schema::UpdateSpeakerPhoneRequest {
+                id,
+                position,
+                name,
+                receive_range,
+            },
+        ) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let params = super::UpdateSpeakerPhoneParams {
+            id: super::SpeakerPhoneId(
+                uuid::Uuid::parse_str(&id)
+                    .map_err(|_| tonic::Status::invalid_argument("Invalid UUID"))?,
+            ),
+            user_id,
+            position: position.map(Into::into),
+            name,
+            receive_range,
+        };
+        let speaker_phone = self
+            .state
+            .update_speaker_phone(params)
+            .await
+            .map_err(IntoStatus::into_status)?
+            .into();
+        let res = schema::UpdateSpeakerPhoneResponse {
+            speaker_phone: Some(speaker_phone),
+        };
+        Ok(tonic::Response::new(res))
+    }
+
+    async fn delete_speaker_phone(
+        &self,
+        request: tonic::Request<schema::DeleteSpeakerPhoneRequest>,
+    ) -> Result<tonic::Response<schema::DeleteSpeakerPhoneResponse>, tonic::Status> {
+        let (meta, _, schema::DeleteSpeakerPhoneRequest { id }) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let params = super::DeleteSpeakerPhoneParams {
+            id: super::SpeakerPhoneId(
+                uuid::Uuid::parse_str(&id)
+                    .map_err(|_| tonic::Status::invalid_argument("Invalid UUID"))?,
+            ),
+            user_id,
+        };
+        self.state
+            .delete_speaker_phone(params)
+            .await
+            .map_err(IntoStatus::into_status)?;
+        Ok(tonic::Response::new(schema::DeleteSpeakerPhoneResponse {}))
+    }
+
+    async fn get_relay_statuses(
+        &self,
+        request: tonic::Request<schema::GetRelayStatusesRequest>,
+    ) -> Result<tonic::Response<schema::GetRelayStatusesResponse>, tonic::Status> {
+        let (meta, _, schema::GetRelayStatusesRequest {}) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let statuses = self
+            .state
+            .get_relay_statuses(super::GetRelayStatusesParams { user_id })
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let res = schema::GetRelayStatusesResponse {
+            statuses: statuses.into_iter().map(Into::into).collect(),
+        };
+        Ok(tonic::Response::new(res))
+    }
+
     async fn get_available_channels(
         &self,
         _request: tonic::Request<schema::GetAvailableChannelsRequest>,
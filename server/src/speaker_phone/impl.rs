@@ -1,21 +1,23 @@
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
-use futures::{FutureExt, TryStreamExt};
+use futures::FutureExt;
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, MySqlPool};
 
 use crate::prelude::IntoStatus;
 
-const RECEIVE_RANGE: u32 = 100;
-
 impl<Context> super::SpeakerPhoneService<Context> for super::SpeakerPhoneServiceImpl
 where
     Context: AsRef<MySqlPool>
         + AsRef<crate::task::TaskManager>
+        + AsRef<super::SpeakerPhoneLimits>
+        + AsRef<super::SpeakerPhoneRelays>
         + crate::event::ProvideEventService
+        + crate::traq::bot::ProvideTraqBotService
         + crate::traq::channel::ProvideTraqChannelService
         + crate::traq::message::ProvideTraqMessageService
         + crate::traq::user::ProvideTraqUserService
+        + crate::user::ProvideUserService
         + crate::world::ProvideWorldService,
 {
     type Error = super::Error;
@@ -41,7 +43,23 @@ where
         ctx: &'a Context,
         params: super::CreateSpeakerPhoneParams,
     ) -> futures::future::BoxFuture<'a, Result<super::SpeakerPhone, Self::Error>> {
-        create_speaker_phone(ctx, ctx, ctx, ctx.as_ref(), params).boxed()
+        create_speaker_phone(ctx, ctx, ctx, ctx.as_ref(), ctx.as_ref(), params).boxed()
+    }
+
+    fn update_speaker_phone<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::UpdateSpeakerPhoneParams,
+    ) -> futures::future::BoxFuture<'a, Result<super::SpeakerPhone, Self::Error>> {
+        update_speaker_phone(ctx, ctx, ctx, ctx.as_ref(), ctx.as_ref(), params).boxed()
+    }
+
+    fn delete_speaker_phone<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::DeleteSpeakerPhoneParams,
+    ) -> futures::future::BoxFuture<'a, Result<super::SpeakerPhone, Self::Error>> {
+        delete_speaker_phone(ctx, ctx.as_ref(), params).boxed()
     }
 
     fn load_all_speaker_phones(
@@ -52,6 +70,14 @@ where
         load_all_speaker_phones(ctx, params).boxed()
     }
 
+    fn get_relay_statuses<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::GetRelayStatusesParams,
+    ) -> futures::future::BoxFuture<'a, Result<Vec<super::RelayStatus>, Self::Error>> {
+        get_relay_statuses(ctx, ctx.as_ref(), params).boxed()
+    }
+
     fn get_available_channels<'a>(
         &'a self,
         ctx: &'a Context,
@@ -78,6 +104,7 @@ struct SpeakerPhoneRow {
     pub position_y: u32,
     pub receive_range: u32,
     pub name: String,
+    pub user_id: Option<uuid::Uuid>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -92,6 +119,7 @@ impl From<SpeakerPhoneRow> for super::SpeakerPhone {
             },
             receive_range: value.receive_range,
             name: super::Channel(value.name),
+            user_id: value.user_id.map(crate::user::UserId),
             created_at: super::Timestamp(value.created_at),
             updated_at: super::Timestamp(value.updated_at),
         }
@@ -120,13 +148,13 @@ async fn get_speaker_phones_in_area(
     params: super::GetSpeakerPhonesInAreaParams,
 ) -> Result<Vec<super::SpeakerPhone>, super::Error> {
     let super::GetSpeakerPhonesInAreaParams { center, size } = params;
-    // TODO: SpeakerPhoneの中央はAreaにないが範囲が被っているやつも含めたいね
+    // 受信範囲の外接矩形で絞り込んでから、円と矩形が重なるものを選ぶ
     let speaker_phones: Vec<SpeakerPhoneRow> = sqlx::query_as(
         r#"
             SELECT * FROM `speaker_phones`
             WHERE
-                `position_x` BETWEEN ? AND ?
-                AND `position_y` BETWEEN ? AND ?
+                `position_x` + `receive_range` >= ? AND `position_x` <= ? + `receive_range`
+                AND `position_y` + `receive_range` >= ? AND `position_y` <= ? + `receive_range`
         "#,
     )
     .bind(center.x.saturating_sub(size.width / 2))
@@ -135,7 +163,18 @@ async fn get_speaker_phones_in_area(
     .bind(center.y.saturating_add(size.height / 2))
     .fetch_all(pool)
     .await?;
-    Ok(speaker_phones.into_iter().map(Into::into).collect())
+    let speaker_phones = speaker_phones
+        .into_iter()
+        .map(super::SpeakerPhone::from)
+        .filter(|speaker_phone| {
+            speaker_phone.position.is_circle_overlapping_rect(
+                speaker_phone.receive_range,
+                center,
+                size,
+            )
+        })
+        .collect();
+    Ok(speaker_phones)
 }
 
 async fn create_speaker_phone(
@@ -143,36 +182,39 @@ async fn create_speaker_phone(
     traq_channel_service: &impl crate::traq::channel::ProvideTraqChannelService,
     world_service: &impl crate::world::ProvideWorldService,
     pool: &MySqlPool,
+    limits: &super::SpeakerPhoneLimits,
     params: super::CreateSpeakerPhoneParams,
 ) -> Result<super::SpeakerPhone, super::Error> {
-    let super::CreateSpeakerPhoneParams { position, name } = params;
-    let world_size = world_service
-        .get_world_size(crate::world::GetWorldSizeParams {})
-        .await
-        .map_err(IntoStatus::into_status)?;
-    if position.x >= world_size.width || position.y >= world_size.height {
-        return Err(super::Error::BadPositionProvided);
-    }
-    let all_channels = traq_channel_service
-        .get_all_channels(crate::traq::channel::GetAllChannelsParams {})
-        .await
-        .map_err(IntoStatus::into_status)?;
-    let Some(_traq_channel) = all_channels.iter().find(|ch| ch.path == name) else {
-        return Err(super::Error::BadChannelProvided);
+    let super::CreateSpeakerPhoneParams {
+        user_id,
+        position,
+        name,
+        receive_range,
+    } = params;
+    let receive_range = match receive_range {
+        Some(range) => check_receive_range(limits, range)?,
+        None => limits.default_receive_range,
     };
+    check_position(world_service, position).await?;
+    check_channel(traq_channel_service, &name).await?;
+    // 同時に作成されても上限を超えないよう、数えた行をロックしたまま追加する
+    let mut tx = pool.begin().await?;
+    check_user_quota(&mut tx, limits, user_id).await?;
+    check_area_quota(&mut tx, limits, position, None).await?;
     let speaker_phone = SpeakerPhoneRow {
         id: uuid::Uuid::now_v7(),
         position_x: position.x,
         position_y: position.y,
-        receive_range: RECEIVE_RANGE,
+        receive_range,
         name,
+        user_id: Some(user_id.0),
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
     };
     sqlx::query(
         r#"
-            INSERT INTO `speaker_phones` (`id`, `position_x`, `position_y`, `receive_range`, `name`, `created_at`, `updated_at`)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO `speaker_phones` (`id`, `position_x`, `position_y`, `receive_range`, `name`, `user_id`, `created_at`, `updated_at`)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
         "#,
     )
     .bind(speaker_phone.id)
@@ -180,10 +222,12 @@ async fn create_speaker_phone(
     .bind(speaker_phone.position_y)
     .bind(speaker_phone.receive_range)
     .bind(speaker_phone.name)
+    .bind(speaker_phone.user_id)
     .bind(speaker_phone.created_at)
     .bind(speaker_phone.updated_at)
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
+    tx.commit().await?;
     tracing::info!(id = %speaker_phone.id, "Created a speaker phone");
     let speaker_phone = get_speaker_phone(
         pool,
@@ -201,175 +245,257 @@ async fn create_speaker_phone(
     Ok(speaker_phone)
 }
 
-async fn load_all_speaker_phones<Context>(
-    ctx: Arc<Context>,
-    _params: super::LoadAllSpeakerPhonesParams,
-) -> Result<(), super::Error>
-where
-    Context: AsRef<MySqlPool>
-        + AsRef<crate::task::TaskManager>
-        + crate::traq::channel::ProvideTraqChannelService
-        + crate::traq::message::ProvideTraqMessageService
-        + crate::traq::user::ProvideTraqUserService
-        + crate::event::ProvideEventService,
-{
-    let pool: &MySqlPool = (*ctx).as_ref();
-
-    let speaker_phones: Vec<super::SpeakerPhone> =
-        sqlx::query_as::<_, SpeakerPhoneRow>(r#"SELECT * FROM `speaker_phones`"#)
-            .fetch_all(pool)
-            .await?
-            .into_iter()
-            .map(Into::into)
-            .collect::<Vec<super::SpeakerPhone>>();
-
-    let channels = ctx
-        .get_all_channels(crate::traq::channel::GetAllChannelsParams {})
+async fn check_position(
+    world_service: &impl crate::world::ProvideWorldService,
+    position: crate::world::Coordinate,
+) -> Result<(), super::Error> {
+    let world_size = world_service
+        .get_world_size(crate::world::GetWorldSizeParams {})
         .await
         .map_err(IntoStatus::into_status)?;
+    if position.x >= world_size.width || position.y >= world_size.height {
+        return Err(super::Error::BadPositionProvided);
+    }
+    Ok(())
+}
 
-    let channel_map: std::collections::HashMap<
-        super::SpeakerPhoneId,
-        crate::traq::channel::TraqChannel,
-    > = speaker_phones
-        .iter()
-        .filter_map(|speaker_phone| {
-            channels
-                .iter()
-                .find(|channel| channel.path == speaker_phone.name.0)
-                .map(|channel| (speaker_phone.id, channel.clone()))
-        })
-        .collect();
-
-    let ctx_clone = ctx.clone();
-    let task_manager: &crate::task::TaskManager = (*ctx_clone).as_ref();
-    task_manager
-        .spawn(|_cancellation_token| async move {
-            let traq_user_service = &*ctx;
-            let traq_message_service = &*ctx;
-            let event_service = &*ctx;
-            run_subscription_loop(
-                traq_user_service,
-                traq_message_service,
-                event_service,
-                speaker_phones,
-                channel_map,
-            )
-            .await;
+async fn check_channel(
+    traq_channel_service: &impl crate::traq::channel::ProvideTraqChannelService,
+    name: &str,
+) -> Result<(), super::Error> {
+    let channel = traq_channel_service
+        .find_channel_by_path(crate::traq::channel::FindChannelByPathParams {
+            path: name.to_string(),
         })
-        .await;
-
+        .await
+        .map_err(IntoStatus::into_status)?;
+    if channel.is_none() {
+        return Err(super::Error::BadChannelProvided);
+    }
     Ok(())
 }
 
-async fn run_subscription_loop(
-    traq_user_service: &impl crate::traq::user::ProvideTraqUserService,
-    traq_message_service: &impl crate::traq::message::ProvideTraqMessageService,
-    event_service: &impl crate::event::ProvideEventService,
-    mut speaker_phones: Vec<super::SpeakerPhone>,
-    channel_map: HashMap<super::SpeakerPhoneId, crate::traq::channel::TraqChannel>,
-) {
-    let mut speaker_phone_rx = event_service
-        .subscribe_speaker_phones()
-        .map_err(|e| super::Error::from(e.into_status()));
-    let mut message_rx = event_service
-        .subscribe_messages()
-        .map_err(|e| super::Error::from(e.into_status()));
-
-    loop {
-        let channel_map = channel_map.clone();
-        tokio::select! {
-            speaker_phone = speaker_phone_rx.try_next() => {
-                let speaker_phone = match speaker_phone {
-                    Ok(Some(speaker_phone)) => speaker_phone,
-                    Ok(None) => break,
-                    Err(err) => {
-                        tracing::error!(error = %err, "Failed to receive a speaker phone");
-                        continue;
-                    }
-                };
-
-                speaker_phones.push(speaker_phone);
-            }
-
-            message = message_rx.try_next() => {
-                let message = match message {
-                    Ok(Some(msg)) => msg,
-                    Ok(None) => break,
-                    Err(err) => {
-                        tracing::error!(error = %err, "Failed to receive a message");
-                        continue;
-                    }
-                };
-
-                for speaker_phone in &speaker_phones {
-                    post_message_to_traq(
-                        traq_user_service,
-                        traq_message_service,
-                        speaker_phone,
-                        &channel_map,
-                        &message,
-                    ).await;
-                }
-            }
-        }
+fn check_receive_range(
+    limits: &super::SpeakerPhoneLimits,
+    receive_range: u32,
+) -> Result<u32, super::Error> {
+    if !(limits.min_receive_range..=limits.max_receive_range).contains(&receive_range) {
+        return Err(super::Error::BadReceiveRangeProvided);
     }
+    Ok(receive_range)
 }
 
-async fn post_message_to_traq(
-    traq_user_service: &impl crate::traq::user::ProvideTraqUserService,
-    traq_message_service: &impl crate::traq::message::ProvideTraqMessageService,
-    speaker_phone: &super::SpeakerPhone,
-    channel_map: &HashMap<super::SpeakerPhoneId, crate::traq::channel::TraqChannel>,
-    message: &crate::message::Message,
-) {
-    if message.content.starts_with("[]()") {
-        return;
+/// 数えた行はトランザクションの終わりまでロックする
+async fn check_user_quota(
+    conn: &mut sqlx::MySqlConnection,
+    limits: &super::SpeakerPhoneLimits,
+    user_id: crate::user::UserId,
+) -> Result<(), super::Error> {
+    let ids: Vec<(uuid::Uuid,)> =
+        sqlx::query_as(r#"SELECT `id` FROM `speaker_phones` WHERE `user_id` = ? FOR UPDATE"#)
+            .bind(user_id.0)
+            .fetch_all(conn)
+            .await?;
+    if ids.len() >= limits.max_per_user as usize {
+        return Err(super::Error::UserQuotaExceeded);
     }
+    Ok(())
+}
 
-    if !message
-        .position
-        .is_inside_circle(speaker_phone.position, speaker_phone.receive_range)
-    {
-        return;
+/// `position`の周囲に設置されているSpeakerPhoneの数を確認する
+/// 移動の場合は`moving`に移動するSpeakerPhoneを渡し、数に含めない
+///
+/// 数えた行はトランザクションの終わりまでロックする
+async fn check_area_quota(
+    conn: &mut sqlx::MySqlConnection,
+    limits: &super::SpeakerPhoneLimits,
+    position: crate::world::Coordinate,
+    moving: Option<super::SpeakerPhoneId>,
+) -> Result<(), super::Error> {
+    let radius = limits.area_radius;
+    let nearby: Vec<SpeakerPhoneRow> = sqlx::query_as(
+        r#"
+            SELECT * FROM `speaker_phones`
+            WHERE `position_x` BETWEEN ? AND ? AND `position_y` BETWEEN ? AND ?
+            FOR UPDATE
+        "#,
+    )
+    .bind(position.x.saturating_sub(radius))
+    .bind(position.x.saturating_add(radius))
+    .bind(position.y.saturating_sub(radius))
+    .bind(position.y.saturating_add(radius))
+    .fetch_all(conn)
+    .await?;
+    let count = nearby
+        .into_iter()
+        .map(super::SpeakerPhone::from)
+        .filter(|s| Some(s.id) != moving)
+        .filter(|s| s.position.is_inside_circle(position, radius))
+        .count();
+    if count >= limits.max_per_area as usize {
+        return Err(super::Error::AreaQuotaExceeded);
     }
+    Ok(())
+}
 
-    let channel = channel_map
-        .get(&speaker_phone.id)
-        .expect("SpeakerPhoneのチャンネルが存在する");
+/// 所有者本人のSpeakerPhoneを取得する
+async fn get_own_speaker_phone(
+    pool: &MySqlPool,
+    id: super::SpeakerPhoneId,
+    user_id: crate::user::UserId,
+) -> Result<super::SpeakerPhone, super::Error> {
+    let speaker_phone = get_speaker_phone(pool, super::GetSpeakerPhoneParams { id }).await?;
+    if speaker_phone.user_id != Some(user_id) {
+        return Err(super::Error::Forbidden);
+    }
+    Ok(speaker_phone)
+}
 
-    let traq_user = traq_user_service
-        .find_traq_user_by_app_user_id(crate::traq::user::FindTraqUserByAppUserIdParams {
-            id: message.user_id,
-        })
-        .await
-        .map_err(IntoStatus::into_status);
-    let traq_user = match traq_user {
-        Ok(Some(u)) => u,
-        Ok(None) => {
-            tracing::error!("User not found");
-            return;
+async fn update_speaker_phone(
+    event_service: &impl crate::event::ProvideEventService,
+    traq_channel_service: &impl crate::traq::channel::ProvideTraqChannelService,
+    world_service: &impl crate::world::ProvideWorldService,
+    pool: &MySqlPool,
+    limits: &super::SpeakerPhoneLimits,
+    params: super::UpdateSpeakerPhoneParams,
+) -> Result<super::SpeakerPhone, super::Error> {
+    let super::UpdateSpeakerPhoneParams {
+        id,
+        user_id,
+        position,
+        name,
+        receive_range,
+    } = params;
+    let before = get_own_speaker_phone(pool, id, user_id).await?;
+    if let Some(position) = position {
+        check_position(world_service, position).await?;
+    }
+    let name = match name {
+        Some(name) if name != before.name.0 => {
+            check_channel(traq_channel_service, &name).await?;
+            name
         }
-        Err(err) => {
-            tracing::error!(error = %err, "Failed to find a user");
-            return;
+        _ => before.name.0.clone(),
+    };
+    let receive_range = match receive_range {
+        Some(range) => check_receive_range(limits, range)?,
+        None => before.receive_range,
+    };
+    // 同時に移動されても上限を超えないよう、数えた行をロックしたまま更新する
+    let mut tx = pool.begin().await?;
+    let position = match position {
+        Some(position) => {
+            check_area_quota(&mut tx, limits, position, Some(id)).await?;
+            position
         }
+        None => before.position,
     };
+    sqlx::query(
+        r#"
+            UPDATE `speaker_phones`
+            SET `position_x` = ?, `position_y` = ?, `receive_range` = ?, `name` = ?, `updated_at` = ?
+            WHERE `id` = ?
+        "#,
+    )
+    .bind(position.x)
+    .bind(position.y)
+    .bind(receive_range)
+    .bind(name)
+    .bind(chrono::Utc::now())
+    .bind(id.0)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    tracing::info!(id = %id.0, "Updated a speaker phone");
+    let after = get_speaker_phone(pool, super::GetSpeakerPhoneParams { id }).await?;
 
-    let mut modified_message = message.clone();
-    modified_message.content = format!("[](){}", message.content);
-    let res = traq_message_service
-        .send_message(crate::traq::message::SendMessageParams {
-            inner: modified_message,
-            channel_id: channel.id,
-            user_id: traq_user.id,
+    event_service
+        .publish_event(crate::event::Event::SpeakerPhoneUpdated {
+            before,
+            after: after.clone(),
         })
         .await
-        .map_err(IntoStatus::into_status);
-    match res {
-        Ok(_) => tracing::info!("Sent a message"),
-        Err(err) => tracing::error!(error = %err, "Failed to send a message"),
+        .map_err(IntoStatus::into_status)?;
+    Ok(after)
+}
+
+async fn delete_speaker_phone(
+    event_service: &impl crate::event::ProvideEventService,
+    pool: &MySqlPool,
+    params: super::DeleteSpeakerPhoneParams,
+) -> Result<super::SpeakerPhone, super::Error> {
+    let super::DeleteSpeakerPhoneParams { id, user_id } = params;
+    let speaker_phone = get_own_speaker_phone(pool, id, user_id).await?;
+    sqlx::query(r#"DELETE FROM `speaker_phones` WHERE `id` = ?"#)
+        .bind(id.0)
+        .execute(pool)
+        .await?;
+    tracing::info!(id = %id.0, "Deleted a speaker phone");
+
+    event_service
+        .publish_event(crate::event::Event::SpeakerPhoneDeleted(
+            speaker_phone.clone(),
+        ))
+        .await
+        .map_err(IntoStatus::into_status)?;
+    Ok(speaker_phone)
+}
+
+async fn load_all_speaker_phones<Context>(
+    ctx: Arc<Context>,
+    _params: super::LoadAllSpeakerPhonesParams,
+) -> Result<(), super::Error>
+where
+    Context: AsRef<MySqlPool>
+        + AsRef<crate::task::TaskManager>
+        + AsRef<super::SpeakerPhoneRelays>
+        + crate::traq::bot::ProvideTraqBotService
+        + crate::traq::channel::ProvideTraqChannelService
+        + crate::traq::message::ProvideTraqMessageService
+        + crate::traq::user::ProvideTraqUserService
+        + crate::event::ProvideEventService
+        + crate::world::ProvideWorldService,
+{
+    super::relay::start(ctx).await
+}
+
+/// 全てのSpeakerPhoneを取得する
+pub(super) async fn get_all_speaker_phones(
+    pool: &MySqlPool,
+) -> Result<Vec<super::SpeakerPhone>, super::Error> {
+    let speaker_phones: Vec<SpeakerPhoneRow> = sqlx::query_as(r#"SELECT * FROM `speaker_phones`"#)
+        .fetch_all(pool)
+        .await?;
+    Ok(speaker_phones.into_iter().map(Into::into).collect())
+}
+
+/// `channel`を中継するSpeakerPhoneを取得する
+pub(super) async fn get_speaker_phones_by_channel(
+    pool: &MySqlPool,
+    channel: &crate::traq::channel::TraqChannel,
+) -> Result<Vec<super::SpeakerPhone>, super::Error> {
+    let speaker_phones: Vec<SpeakerPhoneRow> =
+        sqlx::query_as(r#"SELECT * FROM `speaker_phones` WHERE `name` = ?"#)
+            .bind(&channel.path)
+            .fetch_all(pool)
+            .await?;
+    Ok(speaker_phones.into_iter().map(Into::into).collect())
+}
+
+async fn get_relay_statuses(
+    user_service: &impl crate::user::ProvideUserService,
+    relays: &super::SpeakerPhoneRelays,
+    params: super::GetRelayStatusesParams,
+) -> Result<Vec<super::RelayStatus>, super::Error> {
+    let super::GetRelayStatusesParams { user_id } = params;
+    let admin = user_service
+        .check_admin(crate::user::CheckAdminParams { id: user_id })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    if !admin {
+        return Err(super::Error::AdminOnly);
     }
+    Ok(relays.statuses())
 }
 
 async fn get_available_channels(
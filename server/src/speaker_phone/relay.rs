@@ -0,0 +1,736 @@
+//! SpeakerPhoneの中継
+//!
+//! SpeakerPhoneごとに、周囲のメッセージをtraQへ送るタスクを監視付きで動かす
+//! traQチャンネルの購読はチャンネルごとに1つで、
+//! 使うSpeakerPhoneがなくなれば購読をやめてBOTを退出させる
+
+use std::{
+    collections::{hash_map::Entry, HashSet},
+    fmt,
+    future::Future,
+    sync::{Arc, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use futures::{StreamExt, TryStreamExt};
+use sqlx::MySqlPool;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use crate::prelude::IntoStatus;
+
+const RESTART_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+// MARK: registry
+
+impl fmt::Debug for super::SpeakerPhoneRelays {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpeakerPhoneRelays")
+            .field("relays", &"...")
+            .finish()
+    }
+}
+
+impl super::SpeakerPhoneRelays {
+    /// 中継タスクの状態の一覧; SpeakerPhoneのID順
+    pub fn statuses(&self) -> Vec<super::RelayStatus> {
+        let inner = self.lock();
+        let mut statuses: Vec<_> = inner
+            .relays
+            .values()
+            .map(|relay| relay.status.clone())
+            .collect();
+        statuses.sort_by_key(|status| status.speaker_phone_id.0);
+        statuses
+    }
+
+    fn lock(&self) -> MutexGuard<'_, super::SpeakerPhoneRelaysInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn ids(&self) -> Vec<super::SpeakerPhoneId> {
+        self.lock().relays.keys().copied().collect()
+    }
+
+    /// 中継タスクがなければ登録し、タスクに渡す`Receiver`を返す
+    /// 既にあれば中身を差し替える
+    fn register(
+        &self,
+        speaker_phone: super::SpeakerPhone,
+    ) -> Option<(watch::Receiver<super::SpeakerPhone>, CancellationToken)> {
+        let mut inner = self.lock();
+        if let Some(relay) = inner.relays.get(&speaker_phone.id) {
+            relay.speaker_phone.send_if_modified(|current| {
+                let modified = *current != speaker_phone;
+                *current = speaker_phone;
+                modified
+            });
+            return None;
+        }
+        let id = speaker_phone.id;
+        let (tx, rx) = watch::channel(speaker_phone);
+        let cancel = CancellationToken::new();
+        let relay = super::Relay {
+            speaker_phone: tx,
+            cancel: cancel.clone(),
+            status: super::RelayStatus {
+                speaker_phone_id: id,
+                channel: None,
+                state: super::RelayState::Starting,
+                restarts: 0,
+            },
+        };
+        inner.relays.insert(id, relay);
+        Some((rx, cancel))
+    }
+
+    /// 中継タスクを止め、使われなくなったチャンネルがあれば返す
+    fn unregister(&self, id: super::SpeakerPhoneId) -> Option<crate::traq::channel::TraqChannel> {
+        let mut inner = self.lock();
+        let relay = inner.relays.remove(&id)?;
+        relay.cancel.cancel();
+        inner.release_if_unused(relay.status.channel?)
+    }
+
+    /// `id`の中継先を`channel`に付け替える
+    fn assign_channel(
+        &self,
+        id: super::SpeakerPhoneId,
+        channel: Option<crate::traq::channel::TraqChannel>,
+    ) -> ChannelAssignment {
+        let mut inner = self.lock();
+        let mut assignment = ChannelAssignment::default();
+        // 登録が解除された後
+        let Some(relay) = inner.relays.get_mut(&id) else {
+            return assignment;
+        };
+        let previous = std::mem::replace(&mut relay.status.channel, channel.clone());
+        if let Some(channel) = channel {
+            if let Entry::Vacant(entry) = inner.channels.entry(channel.id) {
+                let cancel = CancellationToken::new();
+                entry.insert(cancel.clone());
+                assignment.subscribe = Some((channel, cancel));
+            }
+        }
+        if let Some(previous) = previous {
+            assignment.release = inner.release_if_unused(previous);
+        }
+        assignment
+    }
+
+    fn set_state(&self, id: super::SpeakerPhoneId, state: super::RelayState) {
+        if let Some(relay) = self.lock().relays.get_mut(&id) {
+            relay.status.state = state;
+        }
+    }
+
+    fn record_failure(&self, id: super::SpeakerPhoneId, error: &super::Error) {
+        if let Some(relay) = self.lock().relays.get_mut(&id) {
+            relay.status.state = super::RelayState::Failed {
+                error: error.to_string(),
+            };
+            relay.status.restarts = relay.status.restarts.saturating_add(1);
+        }
+    }
+}
+
+impl super::SpeakerPhoneRelaysInner {
+    /// どの中継タスクも使っていなければチャンネルの購読をやめる
+    fn release_if_unused(
+        &mut self,
+        channel: crate::traq::channel::TraqChannel,
+    ) -> Option<crate::traq::channel::TraqChannel> {
+        let used = self
+            .relays
+            .values()
+            .any(|relay| relay.status.channel.as_ref().map(|c| c.id) == Some(channel.id));
+        if used {
+            return None;
+        }
+        let cancel = self.channels.remove(&channel.id)?;
+        cancel.cancel();
+        Some(channel)
+    }
+}
+
+/// [`super::SpeakerPhoneRelays::assign_channel`]の後に行う処理
+#[derive(Default)]
+struct ChannelAssignment {
+    /// 新たに購読するチャンネル
+    subscribe: Option<(crate::traq::channel::TraqChannel, CancellationToken)>,
+    /// 使われなくなったチャンネル
+    release: Option<crate::traq::channel::TraqChannel>,
+}
+
+// MARK: registry loop
+
+/// 既存のSpeakerPhoneの中継を始め、以降の作成・変更・削除に追従するタスクをspawnする
+pub(super) async fn start<Context>(ctx: Arc<Context>) -> Result<(), super::Error>
+where
+    Context: AsRef<MySqlPool>
+        + AsRef<crate::task::TaskManager>
+        + AsRef<super::SpeakerPhoneRelays>
+        + crate::traq::bot::ProvideTraqBotService
+        + crate::traq::channel::ProvideTraqChannelService
+        + crate::traq::message::ProvideTraqMessageService
+        + crate::traq::user::ProvideTraqUserService
+        + crate::event::ProvideEventService
+        + crate::world::ProvideWorldService,
+{
+    // 中継タスクを揃えている間のイベントも受け取れるよう、先に購読しておく
+    let events = ctx
+        .subscribe_events()
+        .map_err(|e| super::Error::from(e.into_status()))
+        .boxed();
+    reconcile(&ctx).await?;
+
+    let ctx_clone = Arc::clone(&ctx);
+    let task_manager: &crate::task::TaskManager = (*ctx_clone).as_ref();
+    task_manager
+        .spawn(|cancellation_token| run_registry_loop(ctx, events, cancellation_token))
+        .await;
+    Ok(())
+}
+
+async fn run_registry_loop<Context>(
+    ctx: Arc<Context>,
+    mut events: futures::stream::BoxStream<'static, Result<crate::event::Event, super::Error>>,
+    cancellation_token: CancellationToken,
+) where
+    Context: AsRef<MySqlPool>
+        + AsRef<crate::task::TaskManager>
+        + AsRef<super::SpeakerPhoneRelays>
+        + crate::traq::bot::ProvideTraqBotService
+        + crate::traq::channel::ProvideTraqChannelService
+        + crate::traq::message::ProvideTraqMessageService
+        + crate::traq::user::ProvideTraqUserService
+        + crate::event::ProvideEventService
+        + crate::world::ProvideWorldService,
+{
+    loop {
+        let event = tokio::select! {
+            () = cancellation_token.cancelled() => break,
+            e = events.next() => e,
+        };
+        let event = match event {
+            Some(Ok(event)) => event,
+            None => break,
+            Some(Err(err)) => {
+                // SpeakerPhoneの変更を取りこぼしたかもしれない
+                tracing::warn!(error = %err, "Failed to receive an event; reconciling relays");
+                if let Err(err) = reconcile(&ctx).await {
+                    tracing::error!(error = %err, "Failed to reconcile relays");
+                }
+                continue;
+            }
+        };
+        match event {
+            crate::event::Event::SpeakerPhone(speaker_phone)
+            | crate::event::Event::SpeakerPhoneUpdated {
+                after: speaker_phone,
+                ..
+            } => start_relay(&ctx, speaker_phone).await,
+            crate::event::Event::SpeakerPhoneDeleted(speaker_phone) => {
+                stop_relay(&*ctx, speaker_phone.id).await
+            }
+            event => reflect_message_modification(&*ctx, event).await,
+        }
+    }
+}
+
+/// DBにあるSpeakerPhoneと中継タスクを一致させる
+async fn reconcile<Context>(ctx: &Arc<Context>) -> Result<(), super::Error>
+where
+    Context: AsRef<MySqlPool>
+        + AsRef<crate::task::TaskManager>
+        + AsRef<super::SpeakerPhoneRelays>
+        + crate::traq::bot::ProvideTraqBotService
+        + crate::traq::channel::ProvideTraqChannelService
+        + crate::traq::message::ProvideTraqMessageService
+        + crate::traq::user::ProvideTraqUserService
+        + crate::event::ProvideEventService
+        + crate::world::ProvideWorldService,
+{
+    let speaker_phones = super::r#impl::get_all_speaker_phones((**ctx).as_ref()).await?;
+    let relays: &super::SpeakerPhoneRelays = (**ctx).as_ref();
+    let ids: HashSet<_> = speaker_phones.iter().map(|s| s.id).collect();
+    for id in relays.ids() {
+        if !ids.contains(&id) {
+            stop_relay(&**ctx, id).await;
+        }
+    }
+    for speaker_phone in speaker_phones {
+        start_relay(ctx, speaker_phone).await;
+    }
+    Ok(())
+}
+
+/// 中継タスクをspawnする; 既にあれば中身を更新する
+async fn start_relay<Context>(ctx: &Arc<Context>, speaker_phone: super::SpeakerPhone)
+where
+    Context: AsRef<MySqlPool>
+        + AsRef<crate::task::TaskManager>
+        + AsRef<super::SpeakerPhoneRelays>
+        + crate::traq::bot::ProvideTraqBotService
+        + crate::traq::channel::ProvideTraqChannelService
+        + crate::traq::message::ProvideTraqMessageService
+        + crate::traq::user::ProvideTraqUserService
+        + crate::event::ProvideEventService
+        + crate::world::ProvideWorldService,
+{
+    let id = speaker_phone.id;
+    let relays: &super::SpeakerPhoneRelays = (**ctx).as_ref();
+    let Some((speaker_phone, cancel)) = relays.register(speaker_phone) else {
+        return;
+    };
+    let task_manager: &crate::task::TaskManager = (**ctx).as_ref();
+    spawn_until_cancelled(
+        task_manager,
+        cancel,
+        supervise_relay(Arc::clone(ctx), id, speaker_phone),
+    )
+    .await;
+    tracing::debug!(id = %id.0, "Spawned a speaker phone relay");
+}
+
+async fn stop_relay<Context>(ctx: &Context, id: super::SpeakerPhoneId)
+where
+    Context: AsRef<super::SpeakerPhoneRelays> + crate::traq::bot::ProvideTraqBotService,
+{
+    let relays: &super::SpeakerPhoneRelays = ctx.as_ref();
+    if let Some(channel) = relays.unregister(id) {
+        leave_channel(ctx, channel).await;
+    }
+    tracing::debug!(id = %id.0, "Stopped a speaker phone relay");
+}
+
+// MARK: supervision
+
+/// `cancel`がキャンセルされるか、アプリが終了するまで`fut`を動かす
+async fn spawn_until_cancelled<Fut>(
+    task_manager: &crate::task::TaskManager,
+    cancel: CancellationToken,
+    fut: Fut,
+) where
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    task_manager
+        .spawn(|shutdown| async move {
+            tokio::select! {
+                () = shutdown.cancelled() => {}
+                () = cancel.cancelled() => {}
+                () = fut => {}
+            }
+        })
+        .await;
+}
+
+/// `run`が失敗する度に間隔を空けて再起動する; `Ok`を返せば終了する
+async fn supervise<F, Fut>(mut run: F, mut on_failure: impl FnMut(super::Error))
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), super::Error>>,
+{
+    let mut failures = 0;
+    loop {
+        let started = tokio::time::Instant::now();
+        let Err(err) = run().await else {
+            return;
+        };
+        // しばらく動いていたなら連続した失敗とはみなさない
+        if started.elapsed() >= RESTART_BACKOFF_MAX {
+            failures = 0;
+        }
+        on_failure(err);
+        tokio::time::sleep(restart_backoff(failures)).await;
+        failures += 1;
+    }
+}
+
+fn restart_backoff(failures: u32) -> Duration {
+    RESTART_BACKOFF_MIN
+        .saturating_mul(1 << failures.min(16))
+        .min(RESTART_BACKOFF_MAX)
+}
+
+// MARK: relay
+
+async fn supervise_relay<Context>(
+    ctx: Arc<Context>,
+    id: super::SpeakerPhoneId,
+    speaker_phone: watch::Receiver<super::SpeakerPhone>,
+) where
+    Context: AsRef<MySqlPool>
+        + AsRef<crate::task::TaskManager>
+        + AsRef<super::SpeakerPhoneRelays>
+        + crate::traq::bot::ProvideTraqBotService
+        + crate::traq::channel::ProvideTraqChannelService
+        + crate::traq::message::ProvideTraqMessageService
+        + crate::traq::user::ProvideTraqUserService
+        + crate::event::ProvideEventService
+        + crate::world::ProvideWorldService,
+{
+    let relays: &super::SpeakerPhoneRelays = (*ctx).as_ref();
+    supervise(
+        || run_relay(&ctx, id, speaker_phone.clone()),
+        |err| {
+            tracing::error!(id = %id.0, error = %err, "Speaker phone relay failed");
+            relays.record_failure(id, &err);
+        },
+    )
+    .await;
+}
+
+/// 受信範囲内のアプリのメッセージをtraQへ送る
+/// 登録が解除されると`Ok`で終わる
+#[tracing::instrument(skip_all, fields(id = %id.0))]
+async fn run_relay<Context>(
+    ctx: &Arc<Context>,
+    id: super::SpeakerPhoneId,
+    mut speaker_phone: watch::Receiver<super::SpeakerPhone>,
+) -> Result<(), super::Error>
+where
+    Context: AsRef<MySqlPool>
+        + AsRef<crate::task::TaskManager>
+        + AsRef<super::SpeakerPhoneRelays>
+        + crate::traq::bot::ProvideTraqBotService
+        + crate::traq::channel::ProvideTraqChannelService
+        + crate::traq::message::ProvideTraqMessageService
+        + crate::traq::user::ProvideTraqUserService
+        + crate::event::ProvideEventService
+        + crate::world::ProvideWorldService,
+{
+    let relays: &super::SpeakerPhoneRelays = (**ctx).as_ref();
+    let mut messages = ctx
+        .subscribe_messages()
+        .map_err(|e| super::Error::from(e.into_status()));
+    let mut current = speaker_phone.borrow_and_update().clone();
+    let mut channel = resolve_channel(ctx, &current).await?;
+    relays.set_state(id, super::RelayState::Running);
+    tracing::info!(channel = %channel.path, "Relaying a speaker phone");
+
+    loop {
+        tokio::select! {
+            changed = speaker_phone.changed() => {
+                // 登録が解除された
+                if changed.is_err() {
+                    return Ok(());
+                }
+                let next = speaker_phone.borrow_and_update().clone();
+                let renamed = next.name != current.name;
+                current = next;
+                if renamed {
+                    channel = resolve_channel(ctx, &current).await?;
+                    tracing::info!(channel = %channel.path, "Re-pointed a speaker phone");
+                }
+            }
+            message = messages.try_next() => match message {
+                Ok(Some(message)) => {
+                    post_message_to_traq(&**ctx, &**ctx, &current, &channel, &message).await;
+                }
+                Ok(None) => return Err(super::Error::SubscriptionClosed),
+                Err(err) => tracing::warn!(error = %err, "Failed to receive a message"),
+            },
+        }
+    }
+}
+
+/// SpeakerPhoneの名前からtraQチャンネルを探し、中継先として登録する
+async fn resolve_channel<Context>(
+    ctx: &Arc<Context>,
+    speaker_phone: &super::SpeakerPhone,
+) -> Result<crate::traq::channel::TraqChannel, super::Error>
+where
+    Context: AsRef<MySqlPool>
+        + AsRef<crate::task::TaskManager>
+        + AsRef<super::SpeakerPhoneRelays>
+        + crate::traq::bot::ProvideTraqBotService
+        + crate::traq::channel::ProvideTraqChannelService
+        + crate::traq::message::ProvideTraqMessageService
+        + crate::traq::user::ProvideTraqUserService
+        + crate::event::ProvideEventService
+        + crate::world::ProvideWorldService,
+{
+    let channel = ctx
+        .find_channel_by_path(crate::traq::channel::FindChannelByPathParams {
+            path: speaker_phone.name.0.clone(),
+        })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    let relays: &super::SpeakerPhoneRelays = (**ctx).as_ref();
+    let ChannelAssignment { subscribe, release } =
+        relays.assign_channel(speaker_phone.id, channel.clone());
+    if let Some((channel, cancel)) = subscribe {
+        let task_manager: &crate::task::TaskManager = (**ctx).as_ref();
+        spawn_until_cancelled(
+            task_manager,
+            cancel,
+            supervise_channel_mirroring(Arc::clone(ctx), channel),
+        )
+        .await;
+    }
+    if let Some(channel) = release {
+        leave_channel(&**ctx, channel).await;
+    }
+    channel.ok_or(super::Error::ChannelNotFound)
+}
+
+async fn leave_channel(
+    traq_bot_service: &impl crate::traq::bot::ProvideTraqBotService,
+    channel: crate::traq::channel::TraqChannel,
+) {
+    let res = traq_bot_service
+        .leave_channel(crate::traq::bot::LeaveChannelParams { id: channel.id })
+        .await
+        .map_err(IntoStatus::into_status);
+    match res {
+        Ok(()) => tracing::info!(channel = %channel.path, "Left a traQ channel"),
+        Err(err) => tracing::error!(error = %err, "Failed to leave a traQ channel"),
+    }
+}
+
+async fn post_message_to_traq(
+    traq_user_service: &impl crate::traq::user::ProvideTraqUserService,
+    traq_message_service: &impl crate::traq::message::ProvideTraqMessageService,
+    speaker_phone: &super::SpeakerPhone,
+    channel: &crate::traq::channel::TraqChannel,
+    message: &crate::message::Message,
+) {
+    if message.content.starts_with("[]()") {
+        return;
+    }
+
+    if !message
+        .position
+        .is_inside_circle(speaker_phone.position, speaker_phone.receive_range)
+    {
+        return;
+    }
+
+    // traQから反映されたメッセージは送り返さない
+    let synced = traq_message_service
+        .check_message_sent(crate::traq::message::CheckMessageSentParams {
+            message: message.clone(),
+        })
+        .await
+        .map_err(IntoStatus::into_status);
+    match synced {
+        Ok(None) => {}
+        Ok(Some(_)) => return,
+        Err(err) => {
+            tracing::error!(error = %err, "Failed to check a message");
+            return;
+        }
+    }
+
+    let traq_user = traq_user_service
+        .find_traq_user_by_app_user_id(crate::traq::user::FindTraqUserByAppUserIdParams {
+            id: message.user_id,
+        })
+        .await
+        .map_err(IntoStatus::into_status);
+    let traq_user = match traq_user {
+        Ok(Some(u)) => u,
+        Ok(None) => {
+            tracing::error!("User not found");
+            return;
+        }
+        Err(err) => {
+            tracing::error!(error = %err, "Failed to find a user");
+            return;
+        }
+    };
+
+    let mut modified_message = message.clone();
+    modified_message.content = format!("[](){}", message.content);
+    let res = traq_message_service
+        .send_message(crate::traq::message::SendMessageParams {
+            inner: modified_message,
+            channel_id: channel.id,
+            user_id: traq_user.id,
+        })
+        .await
+        .map_err(IntoStatus::into_status);
+    match res {
+        Ok(_) => tracing::info!("Sent a message"),
+        Err(err) => tracing::error!(error = %err, "Failed to send a message"),
+    }
+}
+
+/// アプリでのメッセージの編集・削除を、同期済みのtraQメッセージに反映させる
+async fn reflect_message_modification(
+    traq_message_service: &impl crate::traq::message::ProvideTraqMessageService,
+    event: crate::event::Event,
+) {
+    let res = match event {
+        crate::event::Event::MessageUpdated(inner) => traq_message_service
+            .edit_message(crate::traq::message::EditMessageParams { inner })
+            .await
+            .map_err(IntoStatus::into_status),
+        crate::event::Event::MessageDeleted(inner) => traq_message_service
+            .delete_message(crate::traq::message::DeleteMessageParams { inner })
+            .await
+            .map_err(IntoStatus::into_status),
+        _ => return,
+    };
+    match res {
+        Ok(Some(synced)) => {
+            tracing::info!(traq_message_id = %synced.id.0, "Reflected a message modification")
+        }
+        Ok(None) => {}
+        Err(err) => tracing::error!(error = %err, "Failed to reflect a message modification"),
+    }
+}
+
+// MARK: traQ channel mirroring
+
+async fn supervise_channel_mirroring<Context>(
+    ctx: Arc<Context>,
+    channel: crate::traq::channel::TraqChannel,
+) where
+    Context: AsRef<MySqlPool>
+        + crate::traq::bot::ProvideTraqBotService
+        + crate::traq::message::ProvideTraqMessageService
+        + crate::traq::user::ProvideTraqUserService
+        + crate::world::ProvideWorldService,
+{
+    supervise(
+        || run_traq_channel_mirroring(&*ctx, &channel),
+        |err| {
+            tracing::error!(channel = %channel.path, error = %err, "traQ channel mirroring failed");
+        },
+    )
+    .await;
+}
+
+/// traQチャンネルに投稿されたメッセージをSpeakerPhoneの周囲に反映する
+#[tracing::instrument(skip_all, fields(channel = %channel.path))]
+async fn run_traq_channel_mirroring<Context>(
+    ctx: &Context,
+    channel: &crate::traq::channel::TraqChannel,
+) -> Result<(), super::Error>
+where
+    Context: AsRef<MySqlPool>
+        + crate::traq::bot::ProvideTraqBotService
+        + crate::traq::message::ProvideTraqMessageService
+        + crate::traq::user::ProvideTraqUserService
+        + crate::world::ProvideWorldService,
+{
+    let mut traq_messages = ctx
+        .subscribe_channel(crate::traq::bot::SubscribeChannelParams { id: channel.id })
+        .await
+        .map_err(IntoStatus::into_status)?
+        .map_err(IntoStatus::into_status);
+    tracing::info!("Subscribed a traQ channel");
+
+    loop {
+        let traq_message = match traq_messages.try_next().await {
+            Ok(Some(m)) => m,
+            // BOTがチャンネルから退出させられた
+            Ok(None) => return Err(super::Error::SubscriptionClosed),
+            Err(err) => {
+                tracing::error!(error = %err, "Failed to receive a traQ message");
+                continue;
+            }
+        };
+        if let Err(err) = recv_message_from_traq(ctx, channel, traq_message).await {
+            tracing::error!(
+                error = &err as &dyn std::error::Error,
+                "Failed to reflect a traQ message"
+            );
+        }
+    }
+}
+
+async fn recv_message_from_traq<Context>(
+    ctx: &Context,
+    channel: &crate::traq::channel::TraqChannel,
+    traq_message: crate::traq::message::TraqMessage,
+) -> Result<(), super::Error>
+where
+    Context: AsRef<MySqlPool>
+        + crate::traq::message::ProvideTraqMessageService
+        + crate::traq::user::ProvideTraqUserService
+        + crate::world::ProvideWorldService,
+{
+    // アプリから送ったメッセージ
+    if traq_message.content.starts_with("[]()") {
+        return Ok(());
+    }
+    let received = ctx
+        .check_message_received(crate::traq::message::CheckMessageReceivedParams {
+            traq_message: traq_message.clone(),
+        })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    if received.is_some() {
+        return Ok(());
+    }
+
+    let speaker_phones =
+        super::r#impl::get_speaker_phones_by_channel(ctx.as_ref(), channel).await?;
+    let seed = traq_message.id.0.as_u128();
+    let Some(speaker_phone) = speaker_phones
+        .get(seed as usize % speaker_phones.len().max(1))
+        .cloned()
+    else {
+        return Ok(());
+    };
+    let world_size = ctx
+        .get_world_size(crate::world::GetWorldSizeParams {})
+        .await
+        .map_err(IntoStatus::into_status)?;
+    let position = scatter_around(&speaker_phone, world_size, seed);
+
+    let traq_user = ctx
+        .find_traq_user(crate::traq::user::FindTraqUserParams {
+            id: traq_message.user_id,
+        })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    let traq_user = match traq_user {
+        Some(u) => u,
+        None => ctx
+            .register_traq_user(crate::traq::user::RegisterTraqUserParams {
+                id: traq_message.user_id,
+            })
+            .await
+            .map_err(IntoStatus::into_status)?,
+    };
+
+    let synced = ctx
+        .recv_message(crate::traq::message::RecvMessageParams {
+            traq_message,
+            user_id: traq_user.inner.id,
+            position,
+        })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    tracing::info!(id = %synced.inner.id.0, "Received a message from traQ");
+    Ok(())
+}
+
+/// SpeakerPhoneの受信範囲の半分以内にメッセージを散らす
+fn scatter_around(
+    speaker_phone: &super::SpeakerPhone,
+    world_size: crate::world::Size,
+    seed: u128,
+) -> crate::world::Coordinate {
+    let angle = (seed & 0xFFFF) as f64 / 65536.0 * std::f64::consts::TAU;
+    let distance = (((seed >> 16) & 0xFFFF) as f64 / 65536.0).sqrt()
+        * (speaker_phone.receive_range / 2) as f64;
+    let x = speaker_phone.position.x as f64 + distance * angle.cos();
+    let y = speaker_phone.position.y as f64 + distance * angle.sin();
+    crate::world::Coordinate {
+        x: (x.max(0.0) as u32).min(world_size.width.saturating_sub(1)),
+        y: (y.max(0.0) as u32).min(world_size.height.saturating_sub(1)),
+    }
+}
+
+#[test]
+fn test_restart_backoff() {
+    assert_eq!(restart_backoff(0), RESTART_BACKOFF_MIN);
+    assert_eq!(restart_backoff(1), RESTART_BACKOFF_MIN * 2);
+    assert_eq!(restart_backoff(u32::MAX), RESTART_BACKOFF_MAX);
+}
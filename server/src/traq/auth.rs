@@ -19,15 +19,57 @@ pub struct TraqOauthClientConfig {
     pub client_secret: String,
 }
 
+/// traQのトークンを暗号化して保存するための鍵
+#[derive(Clone)]
+pub struct TraqTokenKey(aes_gcm::Aes256Gcm);
+
+impl std::fmt::Debug for TraqTokenKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TraqTokenKey").finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct OAuth2EntrypointUriParams {}
 
+#[derive(Debug, Clone)]
+pub struct OAuth2Entrypoint {
+    pub uri: String,
+    /// stateとPKCEのverifierを保存するcookie; リダイレクト時に検証する
+    pub cookies: crate::session::PrivateCookieJar,
+}
+
+#[derive(Debug, Clone)]
+pub struct OAuth2Redirected {
+    pub user: AuthorizedUser,
+    /// 検証済みのstateのcookieを削除する
+    pub cookies: crate::session::PrivateCookieJar,
+}
+
 #[non_exhaustive]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct AuthorizedUser {
     pub user_id: super::user::TraqUserId,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum AuthStatus {
+    Authorized(AuthorizedUser),
+    /// traQでトークンが無効にされたため、認可し直す必要がある
+    ReauthRequired,
+    Unauthorized,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct GetAuthStatusParams {
+    pub user_id: super::user::TraqUserId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct MarkReauthRequiredParams {
+    pub user_id: super::user::TraqUserId,
+}
+
 #[derive(Debug, Clone)]
 pub struct BuildRequestAsAuthorizedUserParams<'a> {
     pub user: &'a AuthorizedUser,
@@ -42,20 +84,34 @@ pub trait TraqAuthService<Context>: Send + Sync + 'static {
         &'a self,
         ctx: &'a Context,
         params: OAuth2EntrypointUriParams,
-    ) -> BoxFuture<'a, Result<String, Self::Error>>;
+    ) -> BoxFuture<'a, Result<OAuth2Entrypoint, Self::Error>>;
+    /// cookieに保存したstateとPKCEのverifierを検証する
     fn oauth2_handle_redirect<'a>(
         &'a self,
         ctx: &'a Context,
         req: &'a http::Request<()>,
-    ) -> BoxFuture<'a, Result<AuthorizedUser, Self::Error>>;
+    ) -> BoxFuture<'a, Result<OAuth2Redirected, Self::Error>>;
+    /// 認可し直す必要があるユーザーは`None`
     fn check_authorized<'a>(
         &'a self,
         ctx: &'a Context,
         user_id: super::user::TraqUserId,
     ) -> BoxFuture<'a, Result<Option<AuthorizedUser>, Self::Error>>;
+    fn get_auth_status<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: GetAuthStatusParams,
+    ) -> BoxFuture<'a, Result<AuthStatus, Self::Error>>;
+    /// traQが401を返したときに呼ぶ; 次に認可されるまでトークンを使わない
+    fn mark_reauth_required<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: MarkReauthRequiredParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>>;
     /// Bearer Token を設定した [`RequestBuilder`] を作る
     ///
     /// 設定する Bearer Token は OAuth2.0 Authorization Code Flow で取得したもの
+    /// 期限が近ければ refresh token で更新する
     fn build_request_as_authorized_user<'a>(
         &'a self,
         ctx: &'a Context,
@@ -76,7 +132,7 @@ pub trait ProvideTraqAuthService: Send + Sync + 'static {
         params: OAuth2EntrypointUriParams,
     ) -> BoxFuture<
         '_,
-        Result<String, <Self::TraqAuthService as TraqAuthService<Self::Context>>::Error>,
+        Result<OAuth2Entrypoint, <Self::TraqAuthService as TraqAuthService<Self::Context>>::Error>,
     > {
         let ctx = self.context();
         self.traq_auth_service().oauth2_entrypoint_uri(ctx, params)
@@ -86,7 +142,7 @@ pub trait ProvideTraqAuthService: Send + Sync + 'static {
         req: &'a http::Request<()>,
     ) -> BoxFuture<
         'a,
-        Result<AuthorizedUser, <Self::TraqAuthService as TraqAuthService<Self::Context>>::Error>,
+        Result<OAuth2Redirected, <Self::TraqAuthService as TraqAuthService<Self::Context>>::Error>,
     > {
         let ctx = self.context();
         self.traq_auth_service().oauth2_handle_redirect(ctx, req)
@@ -104,6 +160,24 @@ pub trait ProvideTraqAuthService: Send + Sync + 'static {
         let ctx = self.context();
         self.traq_auth_service().check_authorized(ctx, user_id)
     }
+    fn get_auth_status(
+        &self,
+        params: GetAuthStatusParams,
+    ) -> BoxFuture<
+        '_,
+        Result<AuthStatus, <Self::TraqAuthService as TraqAuthService<Self::Context>>::Error>,
+    > {
+        let ctx = self.context();
+        self.traq_auth_service().get_auth_status(ctx, params)
+    }
+    fn mark_reauth_required(
+        &self,
+        params: MarkReauthRequiredParams,
+    ) -> BoxFuture<'_, Result<(), <Self::TraqAuthService as TraqAuthService<Self::Context>>::Error>>
+    {
+        let ctx = self.context();
+        self.traq_auth_service().mark_reauth_required(ctx, params)
+    }
     fn build_request_as_authorized_user<'a>(
         &'a self,
         params: BuildRequestAsAuthorizedUserParams<'a>,
@@ -122,7 +196,11 @@ pub struct TraqAuthServiceImpl;
 
 pub fn build_server<State>(this: Arc<State>) -> AuthServiceServer<State>
 where
-    State: ProvideTraqAuthService + crate::session::ProvideSessionService + Sized,
+    State: ProvideTraqAuthService
+        + crate::session::ProvideSessionService
+        + crate::traq::user::ProvideTraqUserService
+        + AsRef<crate::session::AuthMode>
+        + Sized,
 {
     let service = grpc::ServiceImpl::new(this);
     AuthServiceServer::new(service)
@@ -5,6 +5,12 @@ use http::StatusCode;
 pub enum Error {
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
+    /// stateが保存したものと一致しない、または期限切れ
+    #[error("Invalid OAuth2 state")]
+    InvalidState,
+    /// トークンが無効にされた、または更新できなかった
+    #[error("traQ re-authorization required")]
+    ReauthRequired,
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
     #[error(transparent)]
@@ -19,6 +25,10 @@ impl From<Error> for tonic::Status {
     fn from(value: Error) -> Self {
         match value {
             Error::InvalidRequest(msg) => tonic::Status::invalid_argument(msg),
+            Error::InvalidState => tonic::Status::permission_denied("Invalid OAuth2 state"),
+            Error::ReauthRequired => {
+                tonic::Status::unauthenticated("traQ re-authorization required")
+            }
             Error::Sqlx(e) => {
                 tracing::error!(error = &e as &dyn std::error::Error, "Sqlx error");
                 tonic::Status::internal(e.to_string())
@@ -43,6 +53,13 @@ impl IntoResponse for Error {
     fn into_response(self) -> Response {
         match self {
             Error::InvalidRequest(msg) => (StatusCode::BAD_REQUEST, msg).into_response(),
+            Error::InvalidState => {
+                tracing::warn!("OAuth2 state mismatch");
+                (StatusCode::FORBIDDEN, "Invalid OAuth2 state").into_response()
+            }
+            Error::ReauthRequired => {
+                (StatusCode::UNAUTHORIZED, "traQ re-authorization required").into_response()
+            }
             Error::Sqlx(e) => {
                 tracing::error!(error = &e as &dyn std::error::Error, "Sqlx error");
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
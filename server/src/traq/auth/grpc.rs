@@ -19,7 +19,10 @@ where
 
 impl<State> ServiceImpl<State>
 where
-    State: super::ProvideTraqAuthService + crate::session::ProvideSessionService,
+    State: super::ProvideTraqAuthService
+        + crate::session::ProvideSessionService
+        + crate::traq::user::ProvideTraqUserService
+        + AsRef<crate::session::AuthMode>,
 {
     pub(super) fn new(state: Arc<State>) -> Self {
         Self { state }
@@ -29,20 +32,72 @@ where
 #[async_trait::async_trait]
 impl<State> schema::auth::auth_service_server::AuthService for ServiceImpl<State>
 where
-    State: super::ProvideTraqAuthService + crate::session::ProvideSessionService,
+    State: super::ProvideTraqAuthService
+        + crate::session::ProvideSessionService
+        + crate::traq::user::ProvideTraqUserService
+        + AsRef<crate::session::AuthMode>,
 {
     async fn auth(
         &self,
         _request: tonic::Request<schema::auth::AuthRequest>,
     ) -> Result<tonic::Response<schema::auth::AuthResponse>, tonic::Status> {
-        let uri = self
+        use axum::response::IntoResponse;
+
+        let auth_mode: &crate::session::AuthMode = (*self.state).as_ref();
+        if *auth_mode == crate::session::AuthMode::Dev {
+            let res = schema::auth::AuthResponse {
+                location: "/dev/login".to_string(),
+            };
+            return Ok(tonic::Response::new(res));
+        }
+
+        let super::OAuth2Entrypoint { uri, cookies } = self
             .state
             .oauth2_entrypoint_uri(super::OAuth2EntrypointUriParams {})
             .await
             .map_err(IntoStatus::into_status)?;
 
         let res = schema::auth::AuthResponse { location: uri };
+        let mut res = tonic::Response::new(res);
+        // stateのcookieをSet-Cookieで返す
+        let (parts, _) = cookies.into_response().into_parts();
+        *res.metadata_mut() = tonic::metadata::MetadataMap::from_headers(parts.headers);
+        Ok(res)
+    }
 
+    async fn get_auth_status(
+        &self,
+        request: tonic::Request<schema::auth::GetAuthStatusRequest>,
+    ) -> Result<tonic::Response<schema::auth::GetAuthStatusResponse>, tonic::Status> {
+        let (meta, _, schema::auth::GetAuthStatusRequest {}) = request.into_parts();
+        let header_map = meta.into_headers();
+        let user_id = self
+            .state
+            .extract(crate::session::ExtractParams(&header_map))
+            .await
+            .map_err(IntoStatus::into_status)?
+            .user_id;
+        let traq_user = self
+            .state
+            .find_traq_user_by_app_user_id(crate::traq::user::FindTraqUserByAppUserIdParams {
+                id: user_id,
+            })
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let status = match traq_user {
+            Some(traq_user) => self
+                .state
+                .get_auth_status(super::GetAuthStatusParams {
+                    user_id: traq_user.id,
+                })
+                .await
+                .map_err(IntoStatus::into_status)?,
+            None => super::AuthStatus::Unauthorized,
+        };
+        let res = schema::auth::GetAuthStatusResponse {
+            authorized: matches!(status, super::AuthStatus::Authorized(_)),
+            reauth_required: status == super::AuthStatus::ReauthRequired,
+        };
         Ok(tonic::Response::new(res))
     }
 }
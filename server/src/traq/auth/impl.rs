@@ -1,15 +1,30 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
 use axum::extract::Query;
+use axum_extra::extract::cookie::{Cookie, Key, SameSite};
+use chrono::{DateTime, Utc};
 use futures::FutureExt;
 use oauth2::{
-    basic::BasicClient, AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, Scope,
-    TokenResponse, TokenUrl,
+    basic::BasicClient, AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken,
+    PkceCodeChallenge, PkceCodeVerifier, RefreshToken, Scope, TokenResponse, TokenUrl,
 };
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, MySqlPool};
 use uuid::Uuid;
 
-use crate::session::SaveParams;
+use crate::session::{CookieDomain, PrivateCookieJar, SessionName};
+
+/// 認可を開始してからリダイレクトされるまでの猶予(秒)
+const OAUTH2_STATE_LIFETIME: i64 = 10 * 60;
+
+/// アクセストークンの期限がこの秒数以内なら更新する
+const TOKEN_REFRESH_MARGIN: i64 = 60;
+
+/// AES-GCMのnonceの長さ
+const NONCE_LEN: usize = 12;
 
 #[inline]
 fn traq_oauth_auth_url(host: &str) -> String {
@@ -23,7 +38,50 @@ fn traq_oauth_token_url(host: &str) -> String {
 #[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
 pub struct TokenRow {
     pub traq_user_id: Uuid,
-    pub token: String,
+    /// [`super::TraqTokenKey`]で暗号化したもの
+    pub access_token: Vec<u8>,
+    pub refresh_token: Option<Vec<u8>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub needs_reauth: bool,
+}
+
+impl TokenRow {
+    /// 更新しなくても使えるか
+    fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .is_none_or(|e| now + chrono::TimeDelta::seconds(TOKEN_REFRESH_MARGIN) < e)
+    }
+}
+
+impl super::TraqTokenKey {
+    /// 32バイトの鍵から作る
+    pub fn new(key: &[u8]) -> Option<Self> {
+        Aes256Gcm::new_from_slice(key).ok().map(Self)
+    }
+
+    pub fn generate() -> Self {
+        Self(Aes256Gcm::new(&Aes256Gcm::generate_key(OsRng)))
+    }
+
+    /// nonceと暗号文を連結して返す
+    fn encrypt(&self, plaintext: &str) -> Result<Vec<u8>, super::Error> {
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let ciphertext = self
+            .0
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| super::Error::Unknown)?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// 鍵が変わっていたら復号できない
+    fn decrypt(&self, data: &[u8]) -> Option<String> {
+        if data.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plaintext = self.0.decrypt(Nonce::from_slice(nonce), ciphertext).ok()?;
+        String::from_utf8(plaintext).ok()
+    }
 }
 
 impl<Context> super::TraqAuthService<Context> for super::TraqAuthServiceImpl
@@ -32,8 +90,11 @@ where
         + AsRef<Client>
         + AsRef<super::TraqOauthClientConfig>
         + AsRef<crate::traq::TraqHost>
-        + crate::traq::user::ProvideTraqUserService
-        + crate::session::ProvideSessionService,
+        + AsRef<Key>
+        + AsRef<SessionName>
+        + AsRef<CookieDomain>
+        + AsRef<super::TraqTokenKey>
+        + crate::traq::user::ProvideTraqUserService,
 {
     type Error = super::Error;
 
@@ -41,27 +102,37 @@ where
         &'a self,
         ctx: &'a Context,
         _params: super::OAuth2EntrypointUriParams,
-    ) -> futures::future::BoxFuture<'a, Result<String, Self::Error>> {
+    ) -> futures::future::BoxFuture<'a, Result<super::OAuth2Entrypoint, Self::Error>> {
         let config: &super::TraqOauthClientConfig = ctx.as_ref();
         let host: &crate::traq::TraqHost = ctx.as_ref();
         let client = create_oauth_client(&host.0, config);
+        let key: &Key = ctx.as_ref();
+        let jar = PrivateCookieJar::new(key.clone());
 
-        oauth2_entrypoint_uri(client).boxed()
+        oauth2_entrypoint_uri(client, jar, ctx.as_ref(), ctx.as_ref()).boxed()
     }
 
     fn oauth2_handle_redirect<'a>(
         &'a self,
         ctx: &'a Context,
         req: &'a http::Request<()>,
-    ) -> futures::future::BoxFuture<'a, Result<super::AuthorizedUser, Self::Error>> {
+    ) -> futures::future::BoxFuture<'a, Result<super::OAuth2Redirected, Self::Error>> {
         let config: &super::TraqOauthClientConfig = ctx.as_ref();
         let host: &crate::traq::TraqHost = ctx.as_ref();
         let client = create_oauth_client(&host.0, config);
         let req_client: &reqwest::Client = ctx.as_ref();
-        let pool = ctx.as_ref();
         let traq_host: &crate::traq::TraqHost = ctx.as_ref();
+        let key: &Key = ctx.as_ref();
+        let jar = PrivateCookieJar::from_headers(req.headers(), key.clone());
+        let session_name: &SessionName = ctx.as_ref();
+        let domain: &CookieDomain = ctx.as_ref();
+        let state_cookie = OAuth2StateCookie {
+            jar,
+            session_name,
+            domain,
+        };
 
-        oauth2_handle_redirect(client, req_client, req, ctx, pool, &traq_host.0).boxed()
+        oauth2_handle_redirect(client, req_client, req, state_cookie, ctx, &traq_host.0).boxed()
     }
 
     fn check_authorized<'a>(
@@ -74,64 +145,154 @@ where
         check_authorized(pool, user).boxed()
     }
 
+    fn get_auth_status<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::GetAuthStatusParams,
+    ) -> futures::future::BoxFuture<'a, Result<super::AuthStatus, Self::Error>> {
+        get_auth_status(ctx.as_ref(), params).boxed()
+    }
+
+    fn mark_reauth_required<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::MarkReauthRequiredParams,
+    ) -> futures::future::BoxFuture<'a, Result<(), Self::Error>> {
+        let super::MarkReauthRequiredParams { user_id } = params;
+        mark_reauth_required(ctx.as_ref(), user_id).boxed()
+    }
+
     fn build_request_as_authorized_user<'a>(
         &'a self,
         ctx: &'a Context,
         params: super::BuildRequestAsAuthorizedUserParams<'a>,
     ) -> futures::future::BoxFuture<'a, Result<reqwest::RequestBuilder, Self::Error>> {
+        let config: &super::TraqOauthClientConfig = ctx.as_ref();
+        let host: &crate::traq::TraqHost = ctx.as_ref();
+        let oauth_client = create_oauth_client(&host.0, config);
         let pool = ctx.as_ref();
         let client = ctx.as_ref();
+        let token_key = ctx.as_ref();
 
-        build_request_as_authorized_user(pool, client, params).boxed()
+        build_request_as_authorized_user(oauth_client, pool, client, token_key, params).boxed()
     }
 }
 
-async fn oauth2_entrypoint_uri(client: OauthClient) -> Result<String, super::Error> {
-    let (url, _) = client
+// MARK: OAuth2 state
+
+/// リダイレクト時に検証するため、cookieに暗号化して保存する値
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct OAuth2State {
+    csrf_token: String,
+    pkce_verifier: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// stateを保存するcookie
+struct OAuth2StateCookie<'a> {
+    jar: PrivateCookieJar,
+    session_name: &'a SessionName,
+    domain: &'a CookieDomain,
+}
+
+impl OAuth2StateCookie<'_> {
+    fn name(&self) -> String {
+        format!("{}_oauth2", self.session_name.0)
+    }
+
+    fn cookie(&self, value: String) -> Cookie<'static> {
+        Cookie::build((self.name(), value))
+            .domain(self.domain.0.clone())
+            .path("/")
+            .secure(true)
+            .http_only(true)
+            // traQからのリダイレクトでも送信されるように
+            .same_site(SameSite::Lax)
+            .build()
+    }
+
+    fn save(self, state: &OAuth2State) -> Result<PrivateCookieJar, super::Error> {
+        let value = serde_json::to_string(state).map_err(|_| super::Error::Unknown)?;
+        let cookie = self.cookie(value);
+        Ok(self.jar.add(cookie))
+    }
+
+    /// 保存されていたstateと、それを削除したjarを返す
+    fn take(self) -> (Option<OAuth2State>, PrivateCookieJar) {
+        let state = self
+            .jar
+            .get(&self.name())
+            .and_then(|cookie| serde_json::from_str(cookie.value()).ok());
+        let cookie = self.cookie(String::new());
+        (state, self.jar.remove(cookie))
+    }
+}
+
+async fn oauth2_entrypoint_uri(
+    client: OauthClient,
+    jar: PrivateCookieJar,
+    session_name: &SessionName,
+    domain: &CookieDomain,
+) -> Result<super::OAuth2Entrypoint, super::Error> {
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (url, csrf_token) = client
         .authorize_url(CsrfToken::new_random)
         .add_scope(Scope::new("read".to_string()))
         .add_scope(Scope::new("write".to_string()))
+        .set_pkce_challenge(pkce_challenge)
         .url();
 
-    Ok(url.to_string())
+    let state = OAuth2State {
+        csrf_token: csrf_token.secret().clone(),
+        pkce_verifier: pkce_verifier.secret().clone(),
+        expires_at: Utc::now() + chrono::TimeDelta::seconds(OAUTH2_STATE_LIFETIME),
+    };
+    let state_cookie = OAuth2StateCookie {
+        jar,
+        session_name,
+        domain,
+    };
+    let cookies = state_cookie.save(&state)?;
+
+    Ok(super::OAuth2Entrypoint {
+        uri: url.to_string(),
+        cookies,
+    })
 }
 
 #[derive(Deserialize)]
 struct Oauth2RedirectQuery {
     code: String,
+    state: String,
 }
 
 async fn oauth2_handle_redirect<Context>(
     client: OauthClient,
     req_client: &reqwest::Client,
     req_: &http::Request<()>,
+    state_cookie: OAuth2StateCookie<'_>,
     context: &Context,
-    pool: &MySqlPool,
     traq_host: &str,
-) -> Result<super::AuthorizedUser, super::Error>
+) -> Result<super::OAuth2Redirected, super::Error>
 where
-    Context: crate::traq::user::ProvideTraqUserService + crate::session::ProvideSessionService,
+    Context:
+        AsRef<MySqlPool> + AsRef<super::TraqTokenKey> + crate::traq::user::ProvideTraqUserService,
 {
-    let code = Query::<Oauth2RedirectQuery>::try_from_uri(req_.uri())
-        .map_err(|e| super::Error::InvalidRequest(e.to_string()))?
-        .code
-        .clone();
+    let Query(Oauth2RedirectQuery { code, state }) =
+        Query::<Oauth2RedirectQuery>::try_from_uri(req_.uri())
+            .map_err(|e| super::Error::InvalidRequest(e.to_string()))?;
+    // stateは一度しか使えないよう、検証の成否によらず削除する
+    let (saved, cookies) = state_cookie.take();
+    let saved = saved
+        .filter(|saved| saved.csrf_token == state && Utc::now() < saved.expires_at)
+        .ok_or(super::Error::InvalidState)?;
+
     let token = client
-        .exchange_code(AuthorizationCode::new(code.to_string()))
+        .exchange_code(AuthorizationCode::new(code))
+        .set_pkce_verifier(PkceCodeVerifier::new(saved.pkce_verifier))
         .request_async(req_client)
         .await
-        .map_err(|e| match e {
-            oauth2::RequestTokenError::Request(oauth2::HttpClientError::Reqwest(err)) => {
-                super::Error::Reqwest(*err)
-            }
-            oauth2::RequestTokenError::ServerResponse(err) => {
-                super::Error::InvalidRequest(err.to_string())
-            }
-            oauth2::RequestTokenError::Parse(err, _) => {
-                super::Error::InvalidRequest(err.to_string())
-            }
-            _ => super::Error::Unknown,
-        })?;
+        .map_err(request_token_error)?;
     let access_token = token.access_token().secret();
 
     let uri = format!("https://{traq_host}/api/v3/users/me");
@@ -154,35 +315,17 @@ where
         .parse()
         .map_err(|_| super::Error::Unknown)?;
 
-    let (user, app_user_id) = get_or_register_user(context, id).await?;
-
-    sqlx::query(
-        r#"
-            INSERT INTO `traq_token` (`traq_user_id`, `token`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `token` = ?
-        "#
-    )
-        .bind(user.user_id.0)
-        .bind(access_token)
-        .bind(access_token)
-        .execute(pool)
-        .await
-        .map_err(super::Error::Sqlx)?;
+    let user = get_or_register_user(context, id).await?;
 
-    let _ = context
-        .save(SaveParams {
-            user_id: app_user_id,
-            header_map: req_.headers(),
-        })
-        .await
-        .map_err(|e| super::Error::Other(e.into()))?;
+    store_token(context.as_ref(), context.as_ref(), user.user_id, &token).await?;
 
-    Ok(user)
+    Ok(super::OAuth2Redirected { user, cookies })
 }
 
 async fn get_or_register_user<Context>(
     context: &Context,
     id: Uuid,
-) -> Result<(super::AuthorizedUser, crate::user::UserId), super::Error>
+) -> Result<super::AuthorizedUser, super::Error>
 where
     Context: crate::traq::user::ProvideTraqUserService,
 {
@@ -192,7 +335,7 @@ where
         })
         .await
         .map_err(|e| super::Error::Other(e.into()))?
-        .map(|user| (super::AuthorizedUser { user_id: user.id }, user.inner.id));
+        .map(|user| super::AuthorizedUser { user_id: user.id });
 
     if let Some(user) = user {
         return Ok(user);
@@ -204,36 +347,184 @@ where
         })
         .await
         .map_err(|e| super::Error::Other(e.into()))
-        .map(|user| (super::AuthorizedUser { user_id: user.id }, user.inner.id))
+        .map(|user| super::AuthorizedUser { user_id: user.id })
 }
 
 async fn check_authorized(
     pool: &MySqlPool,
     user_id: crate::traq::user::TraqUserId,
 ) -> Result<Option<super::AuthorizedUser>, super::Error> {
-    sqlx::query_as::<_, TokenRow>("SELECT * FROM `traq_token` WHERE `traq_user_id` = ?")
+    let status = get_auth_status(pool, super::GetAuthStatusParams { user_id }).await?;
+    match status {
+        super::AuthStatus::Authorized(user) => Ok(Some(user)),
+        super::AuthStatus::ReauthRequired | super::AuthStatus::Unauthorized => Ok(None),
+    }
+}
+
+async fn get_auth_status(
+    pool: &MySqlPool,
+    params: super::GetAuthStatusParams,
+) -> Result<super::AuthStatus, super::Error> {
+    let super::GetAuthStatusParams { user_id } = params;
+    let status = match find_token(pool, user_id).await? {
+        Some(row) if row.needs_reauth => super::AuthStatus::ReauthRequired,
+        Some(_) => super::AuthStatus::Authorized(super::AuthorizedUser { user_id }),
+        None => super::AuthStatus::Unauthorized,
+    };
+    Ok(status)
+}
+
+async fn mark_reauth_required(
+    pool: &MySqlPool,
+    user_id: crate::traq::user::TraqUserId,
+) -> Result<(), super::Error> {
+    sqlx::query("UPDATE `traq_token` SET `needs_reauth` = TRUE WHERE `traq_user_id` = ?")
         .bind(user_id.0)
-        .fetch_optional(pool)
-        .await
-        .map(|row| row.map(|_| super::AuthorizedUser { user_id }))
-        .map_err(super::Error::Sqlx)
+        .execute(pool)
+        .await?;
+    tracing::info!(traq_user_id = %user_id.0, "traQ token needs re-authorization");
+    Ok(())
 }
 
 async fn build_request_as_authorized_user<'a>(
+    oauth_client: OauthClient,
     pool: &'a MySqlPool,
     client: &'a reqwest::Client,
+    token_key: &'a super::TraqTokenKey,
     params: super::BuildRequestAsAuthorizedUserParams<'a>,
 ) -> Result<reqwest::RequestBuilder, super::Error> {
-    let token =
-        sqlx::query_as::<_, TokenRow>("SELECT * FROM `traq_token` WHERE `traq_user_id` = ?")
-            .bind(params.user.user_id.0)
-            .fetch_one(pool)
-            .await
-            .map_err(super::Error::Sqlx)?;
+    let access_token =
+        access_token(&oauth_client, pool, client, token_key, params.user.user_id).await?;
 
     Ok(client
         .request(params.method, params.uri)
-        .bearer_auth(token.token))
+        .bearer_auth(access_token))
+}
+
+// MARK: token storage
+
+async fn find_token(
+    pool: &MySqlPool,
+    user_id: crate::traq::user::TraqUserId,
+) -> Result<Option<TokenRow>, super::Error> {
+    let row = sqlx::query_as("SELECT * FROM `traq_token` WHERE `traq_user_id` = ?")
+        .bind(user_id.0)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row)
+}
+
+/// 暗号化して保存する; refresh tokenが返されなかった場合は以前のものを使い続ける
+async fn store_token(
+    pool: &MySqlPool,
+    token_key: &super::TraqTokenKey,
+    user_id: crate::traq::user::TraqUserId,
+    token: &impl TokenResponse,
+) -> Result<(), super::Error> {
+    let access_token = token_key.encrypt(token.access_token().secret())?;
+    let refresh_token = token
+        .refresh_token()
+        .map(|t| token_key.encrypt(t.secret()))
+        .transpose()?;
+    let expires_at = token
+        .expires_in()
+        .and_then(|d| chrono::TimeDelta::from_std(d).ok())
+        .map(|d| Utc::now() + d);
+    sqlx::query(
+        r#"
+            INSERT INTO `traq_token` (`traq_user_id`, `access_token`, `refresh_token`, `expires_at`, `needs_reauth`)
+            VALUES (?, ?, ?, ?, FALSE)
+            ON DUPLICATE KEY UPDATE
+                `access_token` = VALUES(`access_token`),
+                `refresh_token` = COALESCE(VALUES(`refresh_token`), `refresh_token`),
+                `expires_at` = VALUES(`expires_at`),
+                `needs_reauth` = FALSE
+        "#,
+    )
+    .bind(user_id.0)
+    .bind(access_token)
+    .bind(refresh_token)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 保存されたアクセストークン; 期限が近ければ更新する
+async fn access_token(
+    oauth_client: &OauthClient,
+    pool: &MySqlPool,
+    client: &reqwest::Client,
+    token_key: &super::TraqTokenKey,
+    user_id: crate::traq::user::TraqUserId,
+) -> Result<String, super::Error> {
+    let row = find_token(pool, user_id)
+        .await?
+        .ok_or(super::Error::ReauthRequired)?;
+    if row.needs_reauth {
+        return Err(super::Error::ReauthRequired);
+    }
+
+    if row.is_fresh(Utc::now()) {
+        let Some(access_token) = token_key.decrypt(&row.access_token) else {
+            tracing::warn!(traq_user_id = %user_id.0, "Failed to decrypt traQ access token");
+            mark_reauth_required(pool, user_id).await?;
+            return Err(super::Error::ReauthRequired);
+        };
+        return Ok(access_token);
+    }
+
+    let Some(refresh_token) = row
+        .refresh_token
+        .as_deref()
+        .and_then(|t| token_key.decrypt(t))
+    else {
+        mark_reauth_required(pool, user_id).await?;
+        return Err(super::Error::ReauthRequired);
+    };
+    let res = oauth_client
+        .exchange_refresh_token(&RefreshToken::new(refresh_token))
+        .request_async(client)
+        .await;
+    match res {
+        Ok(token) => {
+            store_token(pool, token_key, user_id, &token).await?;
+            tracing::debug!(traq_user_id = %user_id.0, "Refreshed traQ access token");
+            Ok(token.access_token().secret().clone())
+        }
+        Err(oauth2::RequestTokenError::ServerResponse(err)) => {
+            // 同時に更新されていた場合は、使用済みのrefresh tokenが拒否される
+            let current = find_token(pool, user_id).await?;
+            let refreshed = current
+                .filter(|c| !c.needs_reauth && c.is_fresh(Utc::now()))
+                .and_then(|c| token_key.decrypt(&c.access_token));
+            if let Some(access_token) = refreshed {
+                return Ok(access_token);
+            }
+            tracing::warn!(traq_user_id = %user_id.0, error = %err, "Failed to refresh traQ access token");
+            mark_reauth_required(pool, user_id).await?;
+            Err(super::Error::ReauthRequired)
+        }
+        Err(e) => Err(request_token_error(e)),
+    }
+}
+
+fn request_token_error(
+    e: oauth2::RequestTokenError<
+        oauth2::HttpClientError<reqwest::Error>,
+        oauth2::StandardErrorResponse<oauth2::basic::BasicErrorResponseType>,
+    >,
+) -> super::Error {
+    match e {
+        oauth2::RequestTokenError::Request(oauth2::HttpClientError::Reqwest(err)) => {
+            super::Error::Reqwest(*err)
+        }
+        oauth2::RequestTokenError::ServerResponse(err) => {
+            super::Error::InvalidRequest(err.to_string())
+        }
+        oauth2::RequestTokenError::Parse(err, _) => super::Error::InvalidRequest(err.to_string()),
+        _ => super::Error::Unknown,
+    }
 }
 
 fn create_oauth_client(host: &str, config: &super::TraqOauthClientConfig) -> OauthClient {
@@ -258,3 +549,15 @@ type OauthClient = oauth2::Client<
     oauth2::EndpointNotSet,
     oauth2::EndpointSet,
 >;
+
+#[test]
+fn test_token_key_roundtrip() {
+    let key = super::TraqTokenKey::generate();
+    let encrypted = key.encrypt("token").unwrap();
+    assert_ne!(&encrypted[NONCE_LEN..], b"token");
+    assert_eq!(key.decrypt(&encrypted).as_deref(), Some("token"));
+
+    // 別の鍵では復号できない
+    let other = super::TraqTokenKey::generate();
+    assert_eq!(other.decrypt(&encrypted), None);
+}
@@ -26,6 +26,12 @@ pub struct TraqChannelsCache(Arc<RwLock<Option<Vec<TraqChannel>>>>);
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct GetAllChannelsParams {}
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct FindChannelByPathParams {
+    /// `#`から始まるフルパス
+    pub path: String,
+}
+
 pub trait TraqChannelService<Context>: Send + Sync + 'static {
     type Error: IntoStatus;
 
@@ -34,6 +40,13 @@ pub trait TraqChannelService<Context>: Send + Sync + 'static {
         ctx: &'a Context,
         params: GetAllChannelsParams,
     ) -> BoxFuture<'a, Result<Vec<TraqChannel>, Self::Error>>;
+    /// キャッシュになければtraQから取得し直す
+    /// 起動後に作成されたチャンネルも見つけられる
+    fn find_channel_by_path<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: FindChannelByPathParams,
+    ) -> BoxFuture<'a, Result<Option<TraqChannel>, Self::Error>>;
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -60,4 +73,18 @@ pub trait ProvideTraqChannelService: Send + Sync + 'static {
         let ctx = self.context();
         self.traq_channel_service().get_all_channels(ctx, params)
     }
+    fn find_channel_by_path(
+        &self,
+        params: FindChannelByPathParams,
+    ) -> BoxFuture<
+        '_,
+        Result<
+            Option<TraqChannel>,
+            <Self::TraqChannelService as TraqChannelService<Self::Context>>::Error,
+        >,
+    > {
+        let ctx = self.context();
+        self.traq_channel_service()
+            .find_channel_by_path(ctx, params)
+    }
 }
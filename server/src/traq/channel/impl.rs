@@ -23,6 +23,14 @@ where
     ) -> futures::future::BoxFuture<'a, Result<Vec<super::TraqChannel>, Self::Error>> {
         get_all_channels(ctx, params).boxed()
     }
+
+    fn find_channel_by_path<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::FindChannelByPathParams,
+    ) -> futures::future::BoxFuture<'a, Result<Option<super::TraqChannel>, Self::Error>> {
+        find_channel_by_path(ctx, params).boxed()
+    }
 }
 
 async fn get_all_channels<Context>(
@@ -36,6 +44,33 @@ where
     if let Some(cache) = &*cache.0.read().await {
         return Ok(cache.clone());
     }
+    fetch_all_channels(ctx).await
+}
+
+async fn find_channel_by_path<Context>(
+    ctx: &Context,
+    params: super::FindChannelByPathParams,
+) -> Result<Option<super::TraqChannel>, super::error::Error>
+where
+    Context: ProvideTraqBotService + AsRef<TraqHost> + AsRef<super::TraqChannelsCache>,
+{
+    let super::FindChannelByPathParams { path } = params;
+    let find = |channels: Vec<super::TraqChannel>| channels.into_iter().find(|ch| ch.path == path);
+    if let Some(channel) = find(get_all_channels(ctx, super::GetAllChannelsParams {}).await?) {
+        return Ok(Some(channel));
+    }
+    // キャッシュの後に作成されたチャンネルかもしれない
+    Ok(find(fetch_all_channels(ctx).await?))
+}
+
+/// traQからチャンネル一覧を取得し、キャッシュを更新する
+async fn fetch_all_channels<Context>(
+    ctx: &Context,
+) -> Result<Vec<super::TraqChannel>, super::error::Error>
+where
+    Context: ProvideTraqBotService + AsRef<TraqHost> + AsRef<super::TraqChannelsCache>,
+{
+    let cache: &super::TraqChannelsCache = ctx.as_ref();
     // send request
     let traq_host: &TraqHost = ctx.as_ref();
 
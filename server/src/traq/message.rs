@@ -44,6 +44,17 @@ pub struct RecvMessageParams {
     pub position: crate::world::Coordinate,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct EditMessageParams {
+    /// 編集後のメッセージ
+    pub inner: crate::message::Message,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct DeleteMessageParams {
+    pub inner: crate::message::Message,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CheckMessageSentParams {
     pub message: crate::message::Message,
@@ -79,6 +90,22 @@ pub trait TraqMessageService<Context>: Send + Sync + 'static {
         ctx: &'a Context,
         params: CheckMessageReceivedParams,
     ) -> BoxFuture<'a, Result<Option<SyncedTraqMessage>, Self::Error>>;
+    /// アプリでの編集をtraQのメッセージに反映させる
+    ///
+    /// traQと同期されていないメッセージであれば`None`
+    fn edit_message<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: EditMessageParams,
+    ) -> BoxFuture<'a, Result<Option<SyncedTraqMessage>, Self::Error>>;
+    /// アプリでの削除をtraQのメッセージに反映させる
+    ///
+    /// traQと同期されていないメッセージであれば`None`
+    fn delete_message<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: DeleteMessageParams,
+    ) -> BoxFuture<'a, Result<Option<SyncedTraqMessage>, Self::Error>>;
 }
 
 #[allow(clippy::type_complexity)]
@@ -142,6 +169,32 @@ pub trait ProvideTraqMessageService: Send + Sync + 'static {
         self.traq_message_service()
             .check_message_received(ctx, params)
     }
+    fn edit_message(
+        &self,
+        params: EditMessageParams,
+    ) -> BoxFuture<
+        '_,
+        Result<
+            Option<SyncedTraqMessage>,
+            <Self::TraqMessageService as TraqMessageService<Self::Context>>::Error,
+        >,
+    > {
+        let ctx = self.context();
+        self.traq_message_service().edit_message(ctx, params)
+    }
+    fn delete_message(
+        &self,
+        params: DeleteMessageParams,
+    ) -> BoxFuture<
+        '_,
+        Result<
+            Option<SyncedTraqMessage>,
+            <Self::TraqMessageService as TraqMessageService<Self::Context>>::Error,
+        >,
+    > {
+        let ctx = self.context();
+        self.traq_message_service().delete_message(ctx, params)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -2,6 +2,9 @@
 pub enum Error {
     #[error("Unauthorized")]
     Unauthorized,
+    /// traQでトークンが無効にされた
+    #[error("traQ re-authorization required")]
+    ReauthRequired,
     #[error("Not found")]
     NotFound,
     #[error("Received unexpected response from traQ")]
@@ -18,6 +21,9 @@ impl From<Error> for tonic::Status {
     fn from(value: Error) -> Self {
         match value {
             Error::Unauthorized => tonic::Status::unauthenticated("Unauthorized"),
+            Error::ReauthRequired => {
+                tonic::Status::unauthenticated("traQ re-authorization required")
+            }
             Error::NotFound => tonic::Status::not_found("Not found"),
             Error::UnexpectedResponseFromTraq => {
                 tracing::warn!("Received unexpected response from traQ");
@@ -11,7 +11,8 @@ where
     Context: AsRef<MySqlPool>
         + AsRef<TraqHost>
         + crate::message::ProvideMessageService
-        + crate::traq::auth::ProvideTraqAuthService,
+        + crate::traq::auth::ProvideTraqAuthService
+        + crate::traq::user::ProvideTraqUserService,
 {
     type Error = super::Error;
 
@@ -20,7 +21,7 @@ where
         ctx: &'a Context,
         params: super::SendMessageParams,
     ) -> BoxFuture<'a, Result<super::SyncedTraqMessage, Self::Error>> {
-        send_message(ctx, ctx.as_ref(), ctx.as_ref(), params).boxed()
+        send_message(ctx, ctx, ctx.as_ref(), ctx.as_ref(), params).boxed()
     }
 
     fn recv_message<'a>(
@@ -28,7 +29,7 @@ where
         ctx: &'a Context,
         params: super::RecvMessageParams,
     ) -> BoxFuture<'a, Result<super::SyncedTraqMessage, Self::Error>> {
-        recv_message(ctx, ctx.as_ref(), params).boxed()
+        recv_message(ctx, ctx.as_ref(), ctx.as_ref(), params).boxed()
     }
 
     fn check_message_sent<'a>(
@@ -48,6 +49,40 @@ where
         let super::CheckMessageReceivedParams { traq_message } = params;
         check_message_received(ctx, ctx.as_ref(), traq_message).boxed()
     }
+
+    fn edit_message<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::EditMessageParams,
+    ) -> BoxFuture<'a, Result<Option<super::SyncedTraqMessage>, Self::Error>> {
+        let super::EditMessageParams { inner } = params;
+        modify_message(
+            ctx,
+            ctx,
+            ctx.as_ref(),
+            ctx.as_ref(),
+            inner,
+            Modification::Edit,
+        )
+        .boxed()
+    }
+
+    fn delete_message<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::DeleteMessageParams,
+    ) -> BoxFuture<'a, Result<Option<super::SyncedTraqMessage>, Self::Error>> {
+        let super::DeleteMessageParams { inner } = params;
+        modify_message(
+            ctx,
+            ctx,
+            ctx.as_ref(),
+            ctx.as_ref(),
+            inner,
+            Modification::Delete,
+        )
+        .boxed()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, FromRow)]
@@ -63,6 +98,7 @@ struct TraqMessageRow {
 
 #[tracing::instrument(skip_all)]
 async fn send_message(
+    message_service: &impl crate::message::ProvideMessageService,
     traq_auth_service: &impl crate::traq::auth::ProvideTraqAuthService,
     traq_host: &crate::traq::TraqHost,
     pool: &MySqlPool,
@@ -74,11 +110,16 @@ async fn send_message(
         user_id,
     } = params;
 
-    let authorized_user = traq_auth_service
-        .check_authorized(user_id)
-        .await
-        .map_err(IntoStatus::into_status)?
-        .ok_or(super::Error::Unauthorized)?;
+    let authorized_user = authorize(traq_auth_service, user_id).await?;
+
+    let content = cite_parent(
+        traq_host,
+        pool,
+        message.content.clone(),
+        message.parent_id,
+        channel_id,
+    )
+    .await?;
 
     let channel_id = channel_id.0;
     let uri = format!("https://{traq_host}/api/v3/channels/{channel_id}/messages");
@@ -92,10 +133,14 @@ async fn send_message(
         .await
         .map_err(IntoStatus::into_status)?
         .json(&serde_json::json!({
-            "content": message.content,
+            "content": content,
             "embed": true,
         }));
-    let response: serde_json::Value = request.send().await?.error_for_status()?.json().await?;
+    let response = request.send().await?;
+    let response: serde_json::Value = check_response(traq_auth_service, &authorized_user, response)
+        .await?
+        .json()
+        .await?;
     tracing::trace!(value = ?response, "Message sent");
 
     let response = response
@@ -140,6 +185,10 @@ async fn send_message(
     .bind(content)
     .execute(pool)
     .await?;
+    message_service
+        .mark_message_relayed(crate::message::MarkMessageRelayedParams { id: message.id })
+        .await
+        .map_err(IntoStatus::into_status)?;
 
     Ok(super::SyncedTraqMessage {
         id: crate::traq::message::TraqMessageId(id),
@@ -152,6 +201,7 @@ async fn send_message(
 #[tracing::instrument(skip_all)]
 async fn recv_message(
     message_service: &impl crate::message::ProvideMessageService,
+    traq_host: &crate::traq::TraqHost,
     pool: &MySqlPool,
     params: super::RecvMessageParams,
 ) -> Result<super::SyncedTraqMessage, super::Error> {
@@ -160,21 +210,20 @@ async fn recv_message(
         user_id,
         position,
     } = params;
-    let inner = message_service
-        .create_message(crate::message::CreateMessageParams {
-            user_id,
-            position,
-            content: traq_message.content,
-        })
-        .await
-        .map_err(|e| {
-            tracing::error!(
-                error = &e as &dyn std::error::Error,
-                "Failed to create app message"
-            );
-            e.into_status()
-        })?;
 
+    // アプリに反映済みのメッセージを引用していれば、その返信にする
+    let mut parent_id = None;
+    if let Some(cited) = find_cited_message_id(traq_host, &traq_message.content) {
+        let row: Option<TraqMessageRow> =
+            sqlx::query_as(r#"SELECT * FROM `traq_messages` WHERE `id` = ?"#)
+                .bind(cited)
+                .fetch_optional(pool)
+                .await?;
+        parent_id = row.map(|row| crate::message::MessageId(row.message_id));
+    }
+
+    // 配信されたメッセージをtraQに送り返さないよう、公開より先に対応付けを保存する
+    let message_id = crate::message::MessageId(uuid::Uuid::now_v7());
     sqlx::query(
         r#"
             INSERT INTO `traq_messages` (
@@ -187,12 +236,40 @@ async fn recv_message(
         "#,
     )
     .bind(traq_message.id.0)
-    .bind(inner.id.0)
+    .bind(message_id.0)
     .bind(traq_message.channel_id.0)
     .bind(user_id.0)
-    .bind(&inner.content)
+    .bind(&traq_message.content)
     .execute(pool)
     .await?;
+
+    let res = message_service
+        .create_message(crate::message::CreateMessageParams {
+            id: Some(message_id),
+            user_id,
+            position,
+            content: traq_message.content,
+            parent_id,
+        })
+        .await;
+    let inner = match res {
+        Ok(inner) => inner,
+        Err(e) => {
+            tracing::error!(
+                error = &e as &dyn std::error::Error,
+                "Failed to create app message"
+            );
+            sqlx::query(r#"DELETE FROM `traq_messages` WHERE `id` = ?"#)
+                .bind(traq_message.id.0)
+                .execute(pool)
+                .await?;
+            return Err(e.into_status().into());
+        }
+    };
+    message_service
+        .mark_message_relayed(crate::message::MarkMessageRelayedParams { id: inner.id })
+        .await
+        .map_err(IntoStatus::into_status)?;
     tracing::trace!(
         traq_message_id = ?traq_message.id,
         message_id = ?inner.id,
@@ -262,3 +339,208 @@ async fn check_message_sent(
     });
     Ok(row)
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Modification {
+    Edit,
+    Delete,
+}
+
+/// 同期済みのtraQメッセージを、送信者として編集または削除する
+#[tracing::instrument(skip_all, fields(message_id = %message.id.0, ?modification))]
+async fn modify_message(
+    traq_auth_service: &impl crate::traq::auth::ProvideTraqAuthService,
+    traq_user_service: &impl crate::traq::user::ProvideTraqUserService,
+    traq_host: &crate::traq::TraqHost,
+    pool: &MySqlPool,
+    message: crate::message::Message,
+    modification: Modification,
+) -> Result<Option<super::SyncedTraqMessage>, super::Error> {
+    // 複数のスピーカーフォンから送られていれば、traQのメッセージも複数ある
+    let rows: Vec<TraqMessageRow> =
+        sqlx::query_as(r#"SELECT * FROM `traq_messages` WHERE `message_id` = ?"#)
+            .bind(message.id.0)
+            .fetch_all(pool)
+            .await?;
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    let traq_user = traq_user_service
+        .find_traq_user_by_app_user_id(crate::traq::user::FindTraqUserByAppUserIdParams {
+            id: message.user_id,
+        })
+        .await
+        .map_err(IntoStatus::into_status)?
+        .ok_or(super::Error::NotFound)?;
+    let authorized_user = authorize(traq_auth_service, traq_user.id).await?;
+
+    // 1つ失敗しても残りには反映させる
+    let mut synced = None;
+    let mut first_error = None;
+    for row in rows {
+        let res = modify_traq_message(
+            traq_auth_service,
+            traq_host,
+            pool,
+            &authorized_user,
+            &message,
+            &row,
+            modification,
+        )
+        .await;
+        match res {
+            Ok(()) => {
+                tracing::info!(traq_message_id = %row.id, "Reflected modification to traQ");
+                synced.get_or_insert(super::SyncedTraqMessage {
+                    id: super::TraqMessageId(row.id),
+                    channel_id: crate::traq::channel::TraqChannelId(row.channel_id),
+                    user_id: crate::traq::user::TraqUserId(row.user_id),
+                    inner: message.clone(),
+                });
+            }
+            Err(e) => {
+                tracing::error!(
+                    error = &e as &dyn std::error::Error,
+                    traq_message_id = %row.id,
+                    "Failed to reflect modification to traQ"
+                );
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match (synced, first_error) {
+        (Some(synced), _) => Ok(Some(synced)),
+        (None, Some(e)) => Err(e),
+        (None, None) => Ok(None),
+    }
+}
+
+async fn modify_traq_message(
+    traq_auth_service: &impl crate::traq::auth::ProvideTraqAuthService,
+    traq_host: &crate::traq::TraqHost,
+    pool: &MySqlPool,
+    authorized_user: &crate::traq::auth::AuthorizedUser,
+    message: &crate::message::Message,
+    row: &TraqMessageRow,
+    modification: Modification,
+) -> Result<(), super::Error> {
+    let uri = format!("https://{traq_host}/api/v3/messages/{}", row.id);
+    let method = match modification {
+        Modification::Edit => http::Method::PUT,
+        Modification::Delete => http::Method::DELETE,
+    };
+    let params = crate::traq::auth::BuildRequestAsAuthorizedUserParams {
+        user: authorized_user,
+        uri: &uri,
+        method,
+    };
+    let request = traq_auth_service
+        .build_request_as_authorized_user(params)
+        .await
+        .map_err(IntoStatus::into_status)?;
+
+    match modification {
+        Modification::Edit => {
+            // アプリから送ったメッセージは目印を付けたままにする
+            let mut content = message.content.clone();
+            if row.content.starts_with("[]()") {
+                content = format!("[](){content}");
+            }
+            let channel_id = crate::traq::channel::TraqChannelId(row.channel_id);
+            let content =
+                cite_parent(traq_host, pool, content, message.parent_id, channel_id).await?;
+            let response = request
+                .json(&serde_json::json!({
+                    "content": content,
+                    "embed": true,
+                }))
+                .send()
+                .await?;
+            check_response(traq_auth_service, authorized_user, response).await?;
+            sqlx::query(r#"UPDATE `traq_messages` SET `content` = ? WHERE `id` = ?"#)
+                .bind(&content)
+                .bind(row.id)
+                .execute(pool)
+                .await?;
+        }
+        Modification::Delete => {
+            let response = request.send().await?;
+            check_response(traq_auth_service, authorized_user, response).await?;
+        }
+    }
+    Ok(())
+}
+
+/// ユーザーとしてtraQにリクエストできるか
+async fn authorize(
+    traq_auth_service: &impl crate::traq::auth::ProvideTraqAuthService,
+    user_id: crate::traq::user::TraqUserId,
+) -> Result<crate::traq::auth::AuthorizedUser, super::Error> {
+    let status = traq_auth_service
+        .get_auth_status(crate::traq::auth::GetAuthStatusParams { user_id })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    match status {
+        crate::traq::auth::AuthStatus::Authorized(user) => Ok(user),
+        crate::traq::auth::AuthStatus::ReauthRequired => Err(super::Error::ReauthRequired),
+        crate::traq::auth::AuthStatus::Unauthorized => Err(super::Error::Unauthorized),
+    }
+}
+
+/// traQがトークンを拒否した場合は、認可し直すまでそのトークンを使わない
+async fn check_response(
+    traq_auth_service: &impl crate::traq::auth::ProvideTraqAuthService,
+    user: &crate::traq::auth::AuthorizedUser,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, super::Error> {
+    if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+        return Ok(response.error_for_status()?);
+    }
+    traq_auth_service
+        .mark_reauth_required(crate::traq::auth::MarkReauthRequiredParams {
+            user_id: user.user_id,
+        })
+        .await
+        .map_err(IntoStatus::into_status)?;
+    Err(super::Error::ReauthRequired)
+}
+
+/// 返信であれば、`channel_id`にある返信先のtraQメッセージの引用を`content`に付ける
+async fn cite_parent(
+    traq_host: &crate::traq::TraqHost,
+    pool: &MySqlPool,
+    content: String,
+    parent_id: Option<crate::message::MessageId>,
+    channel_id: crate::traq::channel::TraqChannelId,
+) -> Result<String, super::Error> {
+    let Some(parent_id) = parent_id else {
+        return Ok(content);
+    };
+    let parent: Option<TraqMessageRow> = sqlx::query_as(
+        r#"SELECT * FROM `traq_messages` WHERE `message_id` = ? AND `channel_id` = ?"#,
+    )
+    .bind(parent_id.0)
+    .bind(channel_id.0)
+    .fetch_optional(pool)
+    .await?;
+    let content = match parent {
+        Some(parent) => format!("{content}\n{}", message_url(traq_host, parent.id)),
+        None => content,
+    };
+    Ok(content)
+}
+
+fn message_url(traq_host: &crate::traq::TraqHost, id: Uuid) -> String {
+    format!("https://{traq_host}/messages/{id}")
+}
+
+/// 本文中で最初に引用されているtraQメッセージのID
+fn find_cited_message_id(traq_host: &crate::traq::TraqHost, content: &str) -> Option<Uuid> {
+    let prefix = format!("https://{traq_host}/messages/");
+    content.match_indices(&prefix).find_map(|(i, _)| {
+        let rest = &content[i + prefix.len()..];
+        rest.get(..36)?.parse().ok()
+    })
+}
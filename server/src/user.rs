@@ -28,17 +28,42 @@ pub struct User {
     pub updated_at: Timestamp,
 }
 
+/// 管理者のユーザー名
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Admins(pub std::collections::HashSet<String>);
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct GetUserParams {
     pub id: UserId,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct FindUserByNameParams {
+    pub name: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct CreateUserParams {
     pub name: String,
     pub display_name: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct GetLastPositionParams {
+    pub id: UserId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct SaveLastPositionParams {
+    pub id: UserId,
+    pub position: crate::world::Coordinate,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct CheckAdminParams {
+    pub id: UserId,
+}
+
 pub trait UserService<Context>: Send + Sync + 'static {
     type Error: IntoStatus;
 
@@ -47,11 +72,34 @@ pub trait UserService<Context>: Send + Sync + 'static {
         ctx: &'a Context,
         params: GetUserParams,
     ) -> BoxFuture<'a, Result<User, Self::Error>>;
+    /// 同じ名前のユーザーが複数いれば、最初に作成されたもの
+    fn find_user_by_name<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: FindUserByNameParams,
+    ) -> BoxFuture<'a, Result<Option<User>, Self::Error>>;
     fn create_user<'a>(
         &'a self,
         ctx: &'a Context,
         params: CreateUserParams,
     ) -> BoxFuture<'a, Result<User, Self::Error>>;
+    /// 最後に探索を終えた位置; 記録が無ければ`None`
+    fn get_last_position<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: GetLastPositionParams,
+    ) -> BoxFuture<'a, Result<Option<crate::world::Coordinate>, Self::Error>>;
+    fn save_last_position<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: SaveLastPositionParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>>;
+    /// 管理者であれば`true`; 存在しないユーザーは`false`
+    fn check_admin<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: CheckAdminParams,
+    ) -> BoxFuture<'a, Result<bool, Self::Error>>;
 
     // NOTE: `update_user`と`delete_user`は今の所実装しない
 }
@@ -71,6 +119,14 @@ pub trait ProvideUserService: Send + Sync + 'static {
         let ctx = self.context();
         self.user_service().get_user(ctx, params)
     }
+    fn find_user_by_name(
+        &self,
+        params: FindUserByNameParams,
+    ) -> BoxFuture<'_, Result<Option<User>, <Self::UserService as UserService<Self::Context>>::Error>>
+    {
+        let ctx = self.context();
+        self.user_service().find_user_by_name(ctx, params)
+    }
     fn create_user(
         &self,
         params: CreateUserParams,
@@ -78,6 +134,33 @@ pub trait ProvideUserService: Send + Sync + 'static {
         let ctx = self.context();
         self.user_service().create_user(ctx, params)
     }
+    fn get_last_position(
+        &self,
+        params: GetLastPositionParams,
+    ) -> BoxFuture<
+        '_,
+        Result<
+            Option<crate::world::Coordinate>,
+            <Self::UserService as UserService<Self::Context>>::Error,
+        >,
+    > {
+        let ctx = self.context();
+        self.user_service().get_last_position(ctx, params)
+    }
+    fn save_last_position(
+        &self,
+        params: SaveLastPositionParams,
+    ) -> BoxFuture<'_, Result<(), <Self::UserService as UserService<Self::Context>>::Error>> {
+        let ctx = self.context();
+        self.user_service().save_last_position(ctx, params)
+    }
+    fn check_admin(
+        &self,
+        params: CheckAdminParams,
+    ) -> BoxFuture<'_, Result<bool, <Self::UserService as UserService<Self::Context>>::Error>> {
+        let ctx = self.context();
+        self.user_service().check_admin(ctx, params)
+    }
 }
 
 pub fn build_server<State>(state: Arc<State>) -> UserServiceServer<State>
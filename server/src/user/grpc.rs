@@ -99,4 +99,28 @@ where
         let res = schema::GetMeResponse { user: Some(user) };
         Ok(tonic::Response::new(res))
     }
+
+    async fn get_last_position(
+        &self,
+        request: tonic::Request<schema::GetLastPositionRequest>,
+    ) -> Result<tonic::Response<schema::GetLastPositionResponse>, tonic::Status> {
+        let (meta, _, schema::GetLastPositionRequest {}) = request.into_parts();
+        let headers = meta.into_headers();
+        let session = self
+            .state
+            .extract(crate::session::ExtractParams(&headers))
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let position = self
+            .state
+            .get_last_position(super::GetLastPositionParams {
+                id: session.user_id,
+            })
+            .await
+            .map_err(IntoStatus::into_status)?;
+        let res = schema::GetLastPositionResponse {
+            position: position.map(Into::into),
+        };
+        Ok(tonic::Response::new(res))
+    }
 }
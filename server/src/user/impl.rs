@@ -6,7 +6,7 @@ use uuid::Uuid;
 
 impl<Context> super::UserService<Context> for super::UserServiceImpl
 where
-    Context: AsRef<MySqlPool>,
+    Context: AsRef<MySqlPool> + AsRef<super::Admins>,
 {
     type Error = super::Error;
 
@@ -18,6 +18,14 @@ where
         get_user(ctx.as_ref(), params).boxed()
     }
 
+    fn find_user_by_name<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::FindUserByNameParams,
+    ) -> BoxFuture<'a, Result<Option<super::User>, Self::Error>> {
+        find_user_by_name(ctx.as_ref(), params).boxed()
+    }
+
     fn create_user<'a>(
         &'a self,
         ctx: &'a Context,
@@ -25,6 +33,30 @@ where
     ) -> BoxFuture<'a, Result<super::User, Self::Error>> {
         create_user(ctx.as_ref(), params).boxed()
     }
+
+    fn get_last_position<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::GetLastPositionParams,
+    ) -> BoxFuture<'a, Result<Option<crate::world::Coordinate>, Self::Error>> {
+        get_last_position(ctx.as_ref(), params).boxed()
+    }
+
+    fn save_last_position<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::SaveLastPositionParams,
+    ) -> BoxFuture<'a, Result<(), Self::Error>> {
+        save_last_position(ctx.as_ref(), params).boxed()
+    }
+
+    fn check_admin<'a>(
+        &'a self,
+        ctx: &'a Context,
+        params: super::CheckAdminParams,
+    ) -> BoxFuture<'a, Result<bool, Self::Error>> {
+        check_admin(ctx.as_ref(), ctx.as_ref(), params).boxed()
+    }
 }
 
 // MARK: DB operations
@@ -64,6 +96,19 @@ async fn get_user(
     user.map(Into::into).ok_or(super::Error::NotFound)
 }
 
+async fn find_user_by_name(
+    pool: &MySqlPool,
+    params: super::FindUserByNameParams,
+) -> Result<Option<super::User>, super::Error> {
+    let super::FindUserByNameParams { name } = params;
+    let user: Option<UserRow> =
+        sqlx::query_as(r#"SELECT * FROM `users` WHERE `name` = ? ORDER BY `id` ASC LIMIT 1"#)
+            .bind(name)
+            .fetch_optional(pool)
+            .await?;
+    Ok(user.map(Into::into))
+}
+
 async fn create_user(
     pool: &MySqlPool,
     params: super::CreateUserParams,
@@ -91,3 +136,72 @@ async fn create_user(
     .await?;
     Ok(user)
 }
+
+#[derive(Debug, Clone, Hash, Deserialize, Serialize, FromRow)]
+struct UserPositionRow {
+    pub user_id: Uuid,
+    pub position_x: u32,
+    pub position_y: u32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+async fn get_last_position(
+    pool: &MySqlPool,
+    params: super::GetLastPositionParams,
+) -> Result<Option<crate::world::Coordinate>, super::Error> {
+    let super::GetLastPositionParams {
+        id: super::UserId(id),
+    } = params;
+    let row: Option<UserPositionRow> =
+        sqlx::query_as(r#"SELECT * FROM `user_positions` WHERE `user_id` = ?"#)
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.map(|r| crate::world::Coordinate {
+        x: r.position_x,
+        y: r.position_y,
+    }))
+}
+
+async fn save_last_position(
+    pool: &MySqlPool,
+    params: super::SaveLastPositionParams,
+) -> Result<(), super::Error> {
+    let super::SaveLastPositionParams {
+        id: super::UserId(id),
+        position,
+    } = params;
+    sqlx::query(
+        r#"
+            INSERT INTO `user_positions` (`user_id`, `position_x`, `position_y`)
+            VALUES (?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                `position_x` = VALUES(`position_x`),
+                `position_y` = VALUES(`position_y`)
+        "#,
+    )
+    .bind(id)
+    .bind(position.x)
+    .bind(position.y)
+    .execute(pool)
+    .await?;
+    tracing::debug!(id = %id, ?position, "Saved last position");
+    Ok(())
+}
+
+async fn check_admin(
+    admins: &super::Admins,
+    pool: &MySqlPool,
+    params: super::CheckAdminParams,
+) -> Result<bool, super::Error> {
+    let super::CheckAdminParams { id } = params;
+    if admins.0.is_empty() {
+        return Ok(false);
+    }
+    match get_user(pool, super::GetUserParams { id }).await {
+        Ok(user) => Ok(admins.0.contains(&user.name)),
+        Err(super::Error::NotFound) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
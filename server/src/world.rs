@@ -1,6 +1,7 @@
 //! `world.proto`
 
 pub mod error;
+pub mod grid;
 pub mod grpc;
 mod r#impl;
 
@@ -34,6 +35,53 @@ impl Coordinate {
         let radius = radius as u64;
         distance <= radius * radius
     }
+
+    /// `center`, `size`で表される矩形の内側(境界を含まない)にあるか
+    pub fn is_inside_rect(self, center: Coordinate, size: Size) -> bool {
+        let x_min = center.x.saturating_sub(size.width >> 1);
+        let x_max = center.x.saturating_add(size.width >> 1);
+        let y_min = center.y.saturating_sub(size.height >> 1);
+        let y_max = center.y.saturating_add(size.height >> 1);
+        x_min < self.x && self.x < x_max && y_min < self.y && self.y < y_max
+    }
+
+    /// 自身を中心とする半径`radius`の円が、`center`, `size`で表される矩形(境界を含む)と重なるか
+    pub fn is_circle_overlapping_rect(self, radius: u32, center: Coordinate, size: Size) -> bool {
+        let nearest = Coordinate {
+            x: self.x.clamp(
+                center.x.saturating_sub(size.width >> 1),
+                center.x.saturating_add(size.width >> 1),
+            ),
+            y: self.y.clamp(
+                center.y.saturating_sub(size.height >> 1),
+                center.y.saturating_add(size.height >> 1),
+            ),
+        };
+        nearest.is_inside_circle(self, radius)
+    }
+
+    /// `to`に向かって最大`max_distance`だけ進んだ座標
+    pub fn step_towards(self, to: Coordinate, max_distance: f64) -> Self {
+        let dx = to.x as f64 - self.x as f64;
+        let dy = to.y as f64 - self.y as f64;
+        let distance = dx.hypot(dy);
+        if distance <= max_distance {
+            return to;
+        }
+        let ratio = max_distance / distance;
+        Self {
+            x: (self.x as f64 + dx * ratio).round() as u32,
+            y: (self.y as f64 + dy * ratio).round() as u32,
+        }
+    }
+
+    /// `world_size`の範囲内に収まるように丸める
+    pub fn clamp_into(self, world_size: Size) -> Self {
+        Self {
+            x: self.x.min(world_size.width.saturating_sub(1)),
+            y: self.y.min(world_size.height.saturating_sub(1)),
+        }
+    }
 }
 
 #[test]
@@ -47,6 +95,45 @@ fn test_coordinate_is_inside_circle() {
     assert!(!Coordinate { x: 1, y: 5 }.is_inside_circle(center, radius));
 }
 
+#[test]
+fn test_coordinate_is_inside_rect() {
+    let center = Coordinate { x: 10, y: 10 };
+    let size = Size {
+        width: 10,
+        height: 10,
+    };
+    assert!(Coordinate { x: 10, y: 10 }.is_inside_rect(center, size));
+    assert!(Coordinate { x: 6, y: 14 }.is_inside_rect(center, size));
+    assert!(!Coordinate { x: 5, y: 10 }.is_inside_rect(center, size));
+    assert!(!Coordinate { x: 10, y: 15 }.is_inside_rect(center, size));
+}
+
+#[test]
+fn test_coordinate_is_circle_overlapping_rect() {
+    let center = Coordinate { x: 100, y: 100 };
+    let size = Size {
+        width: 20,
+        height: 20,
+    };
+    assert!(Coordinate { x: 100, y: 100 }.is_circle_overlapping_rect(0, center, size));
+    assert!(Coordinate { x: 120, y: 100 }.is_circle_overlapping_rect(10, center, size));
+    assert!(!Coordinate { x: 121, y: 100 }.is_circle_overlapping_rect(10, center, size));
+    // 角との距離は√(6²+8²) = 10
+    assert!(Coordinate { x: 116, y: 118 }.is_circle_overlapping_rect(10, center, size));
+    assert!(!Coordinate { x: 117, y: 118 }.is_circle_overlapping_rect(10, center, size));
+}
+
+#[test]
+fn test_coordinate_step_towards() {
+    let from = Coordinate { x: 100, y: 100 };
+    let near = Coordinate { x: 103, y: 104 };
+    assert_eq!(from.step_towards(near, 5.0), near);
+    let far = Coordinate { x: 400, y: 500 };
+    assert_eq!(from.step_towards(far, 50.0), Coordinate { x: 130, y: 140 });
+    let back = Coordinate { x: 0, y: 100 };
+    assert_eq!(from.step_towards(back, 10.0), Coordinate { x: 90, y: 100 });
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct GetWorldSizeParams {}
 
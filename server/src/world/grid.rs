@@ -0,0 +1,135 @@
+//! 座標を一定の大きさのセルに分割する空間インデックス
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Coordinate, Size};
+
+/// [`Grid`]の1マス
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Cell {
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Cell {
+    pub fn of(coordinate: Coordinate, cell_size: u32) -> Self {
+        Self {
+            x: coordinate.x / cell_size,
+            y: coordinate.y / cell_size,
+        }
+    }
+}
+
+/// `center`, `size`で表される矩形と重なるセルを列挙する
+pub fn cells_in_rect(
+    cell_size: u32,
+    center: Coordinate,
+    size: Size,
+) -> impl Iterator<Item = Cell> + Send + 'static {
+    let min = Cell::of(
+        Coordinate {
+            x: center.x.saturating_sub(size.width >> 1),
+            y: center.y.saturating_sub(size.height >> 1),
+        },
+        cell_size,
+    );
+    let max = Cell::of(
+        Coordinate {
+            x: center.x.saturating_add(size.width >> 1),
+            y: center.y.saturating_add(size.height >> 1),
+        },
+        cell_size,
+    );
+    (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| Cell { x, y }))
+}
+
+/// キー`K`を座標ごとにセルへ振り分けて保持する
+///
+/// 検索結果はセル単位の候補なので、正確な判定は呼び出し側で行う
+#[derive(Debug, Clone)]
+pub struct Grid<K> {
+    cell_size: u32,
+    cells: HashMap<Cell, HashSet<K>>,
+}
+
+impl<K> Grid<K>
+where
+    K: Copy + Eq + Hash,
+{
+    pub fn new(cell_size: u32) -> Self {
+        assert!(cell_size > 0, "cell_size must be positive");
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> u32 {
+        self.cell_size
+    }
+
+    pub fn insert(&mut self, key: K, position: Coordinate) {
+        let cell = Cell::of(position, self.cell_size);
+        self.cells.entry(cell).or_default().insert(key);
+    }
+
+    pub fn remove(&mut self, key: &K, position: Coordinate) {
+        let cell = Cell::of(position, self.cell_size);
+        let Some(keys) = self.cells.get_mut(&cell) else {
+            return;
+        };
+        keys.remove(key);
+        if keys.is_empty() {
+            self.cells.remove(&cell);
+        }
+    }
+
+    pub fn relocate(&mut self, key: K, from: Coordinate, to: Coordinate) {
+        if Cell::of(from, self.cell_size) == Cell::of(to, self.cell_size) {
+            return;
+        }
+        self.remove(&key, from);
+        self.insert(key, to);
+    }
+
+    pub fn query_rect(&self, center: Coordinate, size: Size) -> impl Iterator<Item = &K> + '_ {
+        cells_in_rect(self.cell_size, center, size)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+    }
+
+    pub fn query_circle(&self, center: Coordinate, radius: u32) -> impl Iterator<Item = &K> + '_ {
+        let size = Size {
+            width: radius.saturating_mul(2),
+            height: radius.saturating_mul(2),
+        };
+        self.query_rect(center, size)
+    }
+}
+
+#[test]
+fn test_grid_query() {
+    let mut grid = Grid::new(10);
+    grid.insert(1, Coordinate { x: 5, y: 5 });
+    grid.insert(2, Coordinate { x: 55, y: 5 });
+    grid.insert(3, Coordinate { x: 95, y: 95 });
+
+    let center = Coordinate { x: 10, y: 10 };
+    let size = Size {
+        width: 20,
+        height: 20,
+    };
+    let hits: HashSet<_> = grid.query_rect(center, size).copied().collect();
+    assert_eq!(hits, HashSet::from([1]));
+
+    grid.relocate(2, Coordinate { x: 55, y: 5 }, Coordinate { x: 12, y: 8 });
+    let hits: HashSet<_> = grid.query_circle(center, 10).copied().collect();
+    assert_eq!(hits, HashSet::from([1, 2]));
+
+    grid.remove(&1, Coordinate { x: 5, y: 5 });
+    let hits: HashSet<_> = grid.query_rect(center, size).copied().collect();
+    assert_eq!(hits, HashSet::from([2]));
+}